@@ -0,0 +1,30 @@
+@REM 2本の接線の間に、緩和曲線つきの円曲線を作図します
+@echo off
+REM #jww
+REM #cd
+REM #hm | サイン(L) | 直線逓減(R) | 三次放物線 | ブロス | クライン | シュラム |
+REM #hc v(VERSION)
+REM #:1
+REM #:2
+REM #:3
+REM #:4
+REM #:5
+REM #:6
+REM #h1
+REM #hc 2本の接線を選択
+REM #1  入口側の接線を指示
+REM #g1
+REM #c  【円曲線半径】R (m)/_/R:
+REM #c  【入口緩和曲線長】TCL0 (m) ※0は緩和曲線なし/_/TCL0:
+REM #c  【出口緩和曲線長】TCL1 (m) ※無指定は入口と同じ/_/TCL1:
+REM #c  【TS距離程】L0 (m) ※BVEの距離程, 無指定は0/_/L0:
+REM #c  【注記】1:作図する, 無指定は作図しない/_/注記:
+REM #e
+
+@REM 出口側の緩和曲線を変える場合は、例えば ,3 (三次放物線) のように指定します
+set EXIT=
+
+@REM 線色・線種・レイヤを固定する場合は、例えば /LC:2 /LT:1 /LY:A /LG:0 のように指定します
+set STYLE=
+
+f5rail.exe /CURVE:%1%EXIT% %2 %3 %4 %5 %6 %STYLE% /TEMP:./JWC_TEMP.TXT 2> log.txt REM 1>&2
@@ -0,0 +1,19 @@
+@REM BVEマップの自軌道を作図します
+@echo off
+REM #jww
+REM #cd
+REM #hc v(VERSION)
+REM #h1
+REM #hc 接続する線・円弧を選択 (選択しなければ原点から作図)
+REM #1  始点にする端点を指示
+REM #g1
+REM #c  【マップファイル】BVEのマップファイル ※相対パスは図面のフォルダから/_/ROUTE:
+REM #c  【始点距離程】L0 (m) ※無指定は0/_/L0:
+REM #c  【終点距離程】(m) ※無指定はマップの最後の構文まで/_/終点距離程:
+REM #c  【目盛り間隔】(m) ※無指定は20/_/目盛り間隔:
+REM #e
+
+@REM 線色・線種・レイヤを固定する場合は、例えば /LC:2 /LT:1 /LY:A /LG:0 のように指定します
+set STYLE=
+
+f5rail.exe %1 %2 %3 %4 %STYLE% /TEMP:./JWC_TEMP.TXT 2> log.txt REM 1>&2
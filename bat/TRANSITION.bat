@@ -2,13 +2,45 @@
 @echo off
 REM #jww
 REM #cd
-REM #hm | サイン(L) | 直線逓減(R) |
+REM #hm | サイン(L) | 直線逓減(R) | 三次放物線 | ブロス | クライン | シュラム |
 REM #hc v(VERSION)
 REM #:1
 REM #:2
+REM #:3
+REM #:4
+REM #:5
+REM #:6
+REM #h1
+REM #hc 接続する線・円弧を選択 (選択しなければ原点から作図)
+REM #1  接続する端点を指示
+REM #g1
 REM #c  【開始半径】R0 (m) ※マイナスは左曲線, 無指定は直線/_/R0:
 REM #c  【終了半径】R1 (m) ※マイナスは左曲線, 無指定は直線/_/R1:
-REM #c  【緩和曲線長】TCL (m)/_/TCL:
+REM #c  【緩和曲線長】TCL (m) ※無指定は設計速度から決める/_/TCL:
+REM #c  【始点距離程】L0 (m) ※BVEの距離程, 無指定は0/_/L0:
+REM #c  【注記】1:作図する, 無指定は作図しない/_/注記:
+REM #c  【区間の並び】半径/長さ,半径/長さ,… ※指定するとR1・TCLは無視, 半径0は直線/_/CHAIN:
+REM #c  【BVE】1:曲線のマップファイルも出力する, 無指定は出力しない/_/BVE:
+REM #c  【設計速度】V (km/h) ※カントを設計する, 無指定は設計しない/_/V:
 REM #e
 
-f5rail.exe /TRANSITION:%1 %2 %3 %4 /L0:0 /TEMP:./JWC_TEMP.TXT 2> log.txt REM 1>&2
+@REM カントの条件を変える場合は、例えば /G:1435 /CM:105 /CD:60 /CANT:1 のように指定します
+@REM (軌間mm, 最大カントmm, 許容カント不足量mm, カントの逓減関数)
+set CANT=
+
+@REM 許容誤差から分割する場合は、例えば /TOL:0.001 /SNAP:1 のように指定します
+@REM (横方向の許容誤差m, 1mごとの距離程に区切りを揃える)
+set DIVISION=
+
+@REM 線形図を作図する場合は、例えば /線形図:1 /線形図原点:0,-50 /曲率倍率:10000 /カント倍率:0.1 /目盛り間隔:20 のように指定します
+@REM (始点距離程の基線上の位置, 曲率1/mあたりの高さ, カント1mmあたりの高さ, 目盛りの間隔m)
+set DIAGRAM=
+
+@REM BVEマップの書式を変える場合は、例えば /文字コード:shift_jis /桁数:3 のように指定します
+@REM (文字コード utf-8 または shift_jis, 小数点以下の桁数)
+set MAP=
+
+@REM 線色・線種・レイヤを固定する場合は、例えば /LC:2 /LT:1 /LY:A /LG:0 のように指定します
+set STYLE=
+
+f5rail.exe /TRANSITION:%1 %2 %3 %4 %5 %6 %7 %8 %9 %CANT% %DIVISION% %DIAGRAM% %MAP% %STYLE% /TEMP:./JWC_TEMP.TXT 2> log.txt REM 1>&2
@@ -0,0 +1,25 @@
+@REM 勾配と縦曲線の縦断線形を作図します
+@echo off
+REM #jww
+REM #cd
+REM #hc v(VERSION)
+REM #h1
+REM #hc 勾配線と他軌道の軌道名の文字を選択 (表を指定するなら選択しない)
+REM #1  基準点 (基準の距離程・高さの位置) を指示
+REM #g1
+REM #c  【変化点の表】軌道名,距離程,高さ[,縦曲線半径] のCSV ※相対パスは図面のフォルダから, 無指定は選択した勾配線/_/VERTICAL:
+REM #c  【縦曲線半径】(m) ※0は縦曲線なし, 無指定は3000/_/縦曲線半径:
+REM #c  【基準の距離程】L0 (m) ※無指定は0/_/L0:
+REM #c  【基準の高さ】(m) ※無指定は0/_/基準高さ:
+REM #c  【縦倍率】高さ1mあたりの長さ ※無指定は1/_/縦倍率:
+REM #c  【BVE】1:勾配のマップファイルも出力する, 無指定は出力しない/_/BVE:
+REM #e
+
+@REM BVEマップの書式を変える場合は、例えば /文字コード:shift_jis /桁数:3 /出力間隔:5 のように指定します
+@REM (文字コード utf-8 または shift_jis, 小数点以下の桁数, 他軌道の高さの出力間隔m)
+set MAP=
+
+@REM 線色・線種・レイヤを固定する場合は、例えば /LC:2 /LT:1 /LY:A /LG:0 のように指定します
+set STYLE=
+
+f5rail.exe %1 %2 %3 %4 %5 %6 %MAP% %STYLE% /TEMP:./JWC_TEMP.TXT 2> log.txt REM 1>&2
@@ -6,4 +6,12 @@ REM #hf
 REM #c  出力ファイル名 ※引用符(")NG/_/出力ファイル名:
 REM #e
 
-f5rail.exe /TRACK:X "%*" /出力間隔:5 /TEMP_0:JWC_TEMP_0.TXT /TEMP_X:JWC_TEMP_X.TXT /TEMP:JWC_TEMP.TXT 2> log.txt REM 1>&2
+@REM BVEマップの書式を変える場合は、例えば /文字コード:shift_jis /桁数:3 のように指定します
+@REM (文字コード utf-8 または shift_jis, 小数点以下の桁数)
+set MAP=
+
+@REM 既存のマップファイルの同じトラック名の構文を置き換える場合は /差し込み:1 と指定します
+@REM (書き換える前のファイルを .bak として残します)
+set MERGE=
+
+f5rail.exe /TRACK:X "%*" /出力間隔:5 %MAP% %MERGE% /TEMP_0:JWC_TEMP_0.TXT /TEMP_X:JWC_TEMP_X.TXT /TEMP:JWC_TEMP.TXT 2> log.txt REM 1>&2
@@ -1,6 +1,13 @@
-use std::{collections::HashMap, ffi::OsStr};
+use std::{collections::HashMap, ffi::OsStr, ops::RangeInclusive};
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::transition::canvas::Point;
+
+use super::{
+    bve::{Encoding, Format},
+    jww::Style,
+};
 
 #[derive(Debug)]
 /// コマンドライン引数
@@ -36,6 +43,18 @@ impl Args {
     pub fn transition(&self) -> Result<&str> {
         self.get_str("TRANSITION")
     }
+    pub fn chain(&self) -> Result<&str> {
+        self.get_str("CHAIN")
+    }
+    pub fn curve(&self) -> Result<&str> {
+        self.get_str("CURVE")
+    }
+    pub fn route(&self) -> Result<&str> {
+        self.get_str("ROUTE")
+    }
+    pub fn vertical(&self) -> Result<&str> {
+        self.get_str("VERTICAL")
+    }
     pub fn temp_path(&self) -> Result<&str> {
         self.get_str("TEMP")
     }
@@ -54,10 +73,200 @@ impl Args {
     pub fn r1(&self) -> Result<Option<f64>> {
         self.get_radius("R1")
     }
-    pub fn tcl(&self) -> Result<f64> {
-        let tcl = self.get("TCL")?.float()?;
-        ensure!(tcl > 0.0, "TCLに0より大きい値を入力してください");
-        Ok(tcl.into())
+    pub fn tcl(&self) -> Result<Option<f64>> {
+        // 無ければ設計速度から決める
+        match self.get("TCL") {
+            Ok(val) if !val.str().is_empty() => {
+                let tcl = val.float()?;
+                ensure!(tcl > 0.0, "TCLに0より大きい値を入力してください");
+                Ok(Some(tcl))
+            }
+            _ => Ok(None),
+        }
+    }
+    pub fn r(&self) -> Result<f64> {
+        let r = self.get("R")?.float()?;
+        ensure!(r > 0.0, "Rに0より大きい値を入力してください");
+        Ok(r)
+    }
+    pub fn tcl0(&self) -> Result<f64> {
+        self.get("TCL0")?.length()
+    }
+    pub fn tcl1(&self) -> Result<Option<f64>> {
+        // 出口側は無ければ入口側と同じ
+        match self.get("TCL1") {
+            Ok(val) if !val.str().is_empty() => val.length().map(Some),
+            _ => Ok(None),
+        }
+    }
+    pub fn l0(&self) -> Result<f64> {
+        // 距離程は無くてもよい (0m)
+        match self.get("L0") {
+            Ok(val) if !val.str().is_empty() => val.float(),
+            _ => Ok(0.0),
+        }
+    }
+    pub fn speed(&self) -> Result<Option<f64>> {
+        // 設計速度がなければカントを設計しない
+        match self.get("V") {
+            Ok(val) if !val.str().is_empty() => {
+                let v = val.float()?;
+                ensure!(v > 0.0, "Vに0より大きい値を入力してください");
+                Ok(Some(v))
+            }
+            _ => Ok(None),
+        }
+    }
+    pub fn gauge(&self) -> Result<f64> {
+        let gauge = self.get_float_or("G", 1067.0)?;
+        ensure!(gauge > 0.0, "Gに0より大きい値を入力してください");
+        Ok(gauge)
+    }
+    pub fn max_cant(&self) -> Result<f64> {
+        self.get_length_or("CM", 105.0)
+    }
+    pub fn cant_deficiency(&self) -> Result<f64> {
+        self.get_length_or("CD", 60.0)
+    }
+    pub fn cant_diminish(&self) -> Option<&str> {
+        self.get_str("CANT").ok().filter(|s| !s.is_empty())
+    }
+    pub fn tolerance(&self) -> Result<Option<f64>> {
+        // 許容誤差がなければ1m単位で分割する
+        match self.get("TOL") {
+            Ok(val) if !val.str().is_empty() => {
+                let tol = val.float()?;
+                ensure!(tol > 0.0, "TOLに0より大きい値を入力してください");
+                Ok(Some(tol))
+            }
+            _ => Ok(None),
+        }
+    }
+    pub fn snap(&self) -> Result<bool> {
+        self.get_flag("SNAP")
+    }
+    /// 出力始点
+    ///
+    /// 座標ファイルで指示できないとき (DXF) に "x,y" で指定する。
+    pub fn start(&self) -> Result<Option<Point>> {
+        match self.get("始点") {
+            Ok(val) if !val.str().is_empty() => val.point().map(Some),
+            _ => Ok(None),
+        }
+    }
+    pub fn start_distance(&self) -> Result<f64> {
+        self.get_float_or("始点距離程", 0.0)
+    }
+    pub fn end_distance(&self) -> Result<Option<f64>> {
+        // 無指定ならマップの最後まで
+        match self.get("終点距離程") {
+            Ok(val) if !val.str().is_empty() => val.float().map(Some),
+            _ => Ok(None),
+        }
+    }
+    pub fn track_name(&self) -> &str {
+        self.get_str("トラック名")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or(" ")
+    }
+    pub fn own_layer(&self) -> Option<&str> {
+        self.get_str("自軌道画層").ok().filter(|s| !s.is_empty())
+    }
+    pub fn other_layer(&self) -> Option<&str> {
+        self.get_str("他軌道画層").ok().filter(|s| !s.is_empty())
+    }
+    pub fn interval(&self) -> Result<f64> {
+        let interval = self.get("出力間隔")?.float()?;
+        ensure!(interval > 0.0, "出力間隔に0より大きい値を入力してください");
+        Ok(interval)
+    }
+
+    /// 既存のマップファイルに差し込むなら `true`
+    pub fn merge(&self) -> Result<bool> {
+        self.get_flag("差し込み")
+    }
+
+    /// 注記を作図するなら `true`
+    pub fn annotate(&self) -> Result<bool> {
+        self.get_flag("注記")
+    }
+
+    /// 線形図を作図するなら `true`
+    pub fn diagram(&self) -> Result<bool> {
+        self.get_flag("線形図")
+    }
+    pub fn diagram_origin(&self) -> Result<Option<Point>> {
+        match self.get("線形図原点") {
+            Ok(val) if !val.str().is_empty() => val.point().map(Some),
+            _ => Ok(None),
+        }
+    }
+    pub fn diagram_scale(&self) -> Result<f64> {
+        self.get_positive_or("線形図縮尺", 1.0)
+    }
+    pub fn curvature_scale(&self) -> Result<f64> {
+        self.get_positive_or("曲率倍率", 10000.0)
+    }
+    pub fn cant_scale(&self) -> Result<f64> {
+        self.get_positive_or("カント倍率", 0.1)
+    }
+    pub fn tick(&self) -> Result<f64> {
+        self.get_positive_or("目盛り間隔", 20.0)
+    }
+
+    /// 縦曲線半径 (m)
+    ///
+    /// 0なら縦曲線を入れない。
+    pub fn vertical_radius(&self) -> Result<f64> {
+        self.get_length_or("縦曲線半径", 3000.0)
+    }
+
+    /// 縦断面図の基準点の高さ (m)
+    pub fn datum_height(&self) -> Result<f64> {
+        self.get_float_or("基準高さ", 0.0)
+    }
+
+    /// 縦断面図の高さ1mあたりの長さ
+    pub fn vertical_scale(&self) -> Result<f64> {
+        self.get_positive_or("縦倍率", 1.0)
+    }
+
+    /// 他軌道の高さの出力間隔 (m)
+    pub fn height_interval(&self) -> Result<f64> {
+        self.get_positive_or("出力間隔", 5.0)
+    }
+
+    /// BVEマップを出力するなら `true`
+    pub fn bve(&self) -> Result<bool> {
+        self.get_flag("BVE")
+    }
+
+    /// マップファイルの書式
+    ///
+    /// 作図元のファイルは含まない。
+    pub fn map_format(&self) -> Result<Format> {
+        let encoding = match self.get_str("文字コード").unwrap_or("") {
+            "" | "utf-8" | "utf8" => Encoding::Utf8,
+            "shift_jis" | "sjis" => Encoding::ShiftJis,
+            _ => bail!("文字コードに utf-8 か shift_jis を入力してください"),
+        };
+        let precision = self.get_number("桁数", 0..=9)?;
+        Ok(Format {
+            encoding,
+            precision: precision.unwrap_or(Format::default().precision),
+            source: None,
+        })
+    }
+
+    /// 線色・線種・レイヤ
+    pub fn style(&self) -> Result<Style> {
+        Ok(Style {
+            color: self.get_number("LC", 1..=9)?,
+            line_type: self.get_number("LT", 1..=9)?,
+            layer: self.get_hex("LY")?,
+            group: self.get_hex("LG")?,
+        })
     }
 
     fn get<'k>(&self, key: &'k str) -> Result<ArgValue<'k, '_>> {
@@ -74,6 +283,34 @@ impl Args {
         // 半径は無くてもよいが、あるなら適切な値でなければならない
         self.get(key).map_or(Ok(None), |val| val.radius())
     }
+    fn get_float_or(&self, key: &str, default: f64) -> Result<f64> {
+        // 無指定なら既定値
+        match self.get(key) {
+            Ok(val) if !val.str().is_empty() => val.float(),
+            _ => Ok(default),
+        }
+    }
+    fn get_positive_or(&self, key: &str, default: f64) -> Result<f64> {
+        let f = self.get_float_or(key, default)?;
+        ensure!(f > 0.0, "{key}に0より大きい値を入力してください");
+        Ok(f)
+    }
+    fn get_length_or(&self, key: &str, default: f64) -> Result<f64> {
+        match self.get(key) {
+            Ok(val) if !val.str().is_empty() => val.length(),
+            _ => Ok(default),
+        }
+    }
+    fn get_flag(&self, key: &str) -> Result<bool> {
+        self.get(key).map_or(Ok(false), |val| val.flag())
+    }
+    fn get_number(&self, key: &str, range: RangeInclusive<u16>) -> Result<Option<u16>> {
+        self.get(key)
+            .map_or(Ok(None), |val| val.number(range).map(Some))
+    }
+    fn get_hex(&self, key: &str) -> Result<Option<u8>> {
+        self.get(key).map_or(Ok(None), |val| val.hex().map(Some))
+    }
 }
 
 impl<'a> FromIterator<(&'a str, &'a str)> for Args {
@@ -98,14 +335,54 @@ impl<'k, 'v> ArgValue<'k, 'v> {
     fn float(&self) -> Result<f64> {
         self.str()
             .parse()
+            .ok()
+            .filter(|f: &f64| f.is_finite())
             .with_context(|| format!("{}を数値で入力してください", self.key()))
     }
+    fn length(&self) -> Result<f64> {
+        let len = self.float()?;
+        ensure!(len >= 0.0, "{}に0以上の値を入力してください", self.key());
+        Ok(len)
+    }
     fn radius(&self) -> Result<Option<f64>> {
         let r = self.float()?;
         ensure!(r != 0.0, "{}に0を指定できません", self.key());
         Ok(Some(r))
     }
 
+    fn point(&self) -> Result<Point> {
+        self.str()
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+            .filter(|(x, y): &(f64, f64)| x.is_finite() && y.is_finite())
+            .map(Point::from)
+            .with_context(|| format!("{}に x,y を入力してください", self.key()))
+    }
+
+    fn flag(&self) -> Result<bool> {
+        match self.str() {
+            "" | "0" => Ok(false),
+            "1" => Ok(true),
+            _ => bail!("{}に0か1を入力してください", self.key()),
+        }
+    }
+    fn number(&self, range: RangeInclusive<u16>) -> Result<u16> {
+        self.str()
+            .parse()
+            .ok()
+            .filter(|n| range.contains(n))
+            .with_context(|| {
+                let (min, max) = (range.start(), range.end());
+                format!("{}に{min}～{max}の整数を入力してください", self.key())
+            })
+    }
+    fn hex(&self) -> Result<u8> {
+        u8::from_str_radix(self.str(), 16)
+            .ok()
+            .filter(|n| *n < 16)
+            .with_context(|| format!("{}に0～Fを入力してください", self.key()))
+    }
+
     fn key(&self) -> &'k str {
         self.0
     }
@@ -0,0 +1,16 @@
+//! テスト用の出力先
+
+use std::{cell::RefCell, io, rc::Rc};
+
+/// 書き込んだ内容を後から読める出力先
+#[derive(Clone, Default)]
+pub struct Buffer(pub Rc<RefCell<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
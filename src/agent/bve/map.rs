@@ -0,0 +1,713 @@
+//! BVEマップの構文木を評価した、距離程つきの構文
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use encoding_rs::{Encoding, UTF_8};
+
+use super::{
+    syntax::{self, Expr, Node, Span, Syntax},
+    Curve, Gradient,
+};
+
+/// include をたどる深さの上限
+const MAX_DEPTH: usize = 16;
+
+/// 読み込んだマップ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Map {
+    /// バージョン
+    pub version: String,
+
+    /// ヘッダーの文字コード
+    pub encoding: Option<String>,
+
+    /// 出現順の構文
+    pub entries: Vec<Entry>,
+}
+
+/// 距離程つきの構文
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// 距離程 (m)
+    pub z: f64,
+
+    /// 構文
+    pub statement: Statement,
+
+    /// 位置
+    pub span: Span,
+
+    /// 構文を書いたファイル
+    ///
+    /// 文字列から読み込んだ構文は `None`
+    pub file: Option<PathBuf>,
+}
+
+/// 値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// 数値
+    Number(f64),
+
+    /// 文字列
+    Text(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// 構文
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// 自軌道の平面曲線
+    ///
+    /// 省略した引数は直前の値で補う。
+    Curve(Curve),
+
+    /// 自軌道の勾配
+    Gradient(Gradient),
+
+    /// 他軌道
+    Track {
+        /// 軌道名
+        key: String,
+
+        /// 位置
+        track: Track,
+    },
+
+    /// ストラクチャーの設置
+    Structure {
+        /// ストラクチャー名
+        key: String,
+
+        /// 関数名
+        function: String,
+
+        /// 引数
+        args: Vec<Option<Value>>,
+    },
+
+    /// 停車場
+    Station {
+        /// 停車場名
+        key: String,
+
+        /// 関数名
+        function: String,
+
+        /// 引数
+        args: Vec<Option<Value>>,
+    },
+
+    /// リストファイルの読み込み `Structure.Load('file');`
+    Load {
+        /// マップ要素名
+        element: String,
+
+        /// ファイルのパス
+        path: String,
+    },
+
+    /// たどらなかった include
+    Include(String),
+
+    /// 上記以外の構文
+    Other {
+        /// マップ要素名・サブ要素名・関数名を . でつないだ名前
+        name: String,
+
+        /// キー
+        key: Option<String>,
+
+        /// 引数
+        args: Vec<Option<Value>>,
+    },
+}
+
+/// 他軌道の位置の構文
+///
+/// 省略した引数は、同じ軌道の直前の値で補う。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Track {
+    /// 水平方向 (X座標, 相対半径)
+    X(f64, f64),
+
+    /// 鉛直方向 (Y座標, 相対半径)
+    Y(f64, f64),
+
+    /// 両方向 (X座標, Y座標, 水平の相対半径, 鉛直の相対半径)
+    Position(f64, f64, f64, f64),
+}
+
+impl Map {
+    /// マップファイルを読む。
+    ///
+    /// ヘッダーの文字コードで読み、include はファイルからの相対パスでたどる。
+    pub fn open(path: &(impl AsRef<Path> + ?Sized)) -> Result<Self> {
+        let path = path.as_ref();
+        let (header, nodes) = load(path)?;
+        Self::read(header, &nodes, Some(path))
+    }
+
+    /// 文を読み、ヘッダーとあわせてマップにする。
+    fn read(header: syntax::Header, nodes: &[Node], path: Option<&Path>) -> Result<Self> {
+        let mut reader = Reader::default();
+        reader.read(nodes, path, 0)?;
+        Ok(Self {
+            version: header.version,
+            encoding: header.encoding,
+            entries: reader.entries,
+        })
+    }
+}
+
+/// ファイルを読み、ヘッダーと文に分ける。
+fn load(path: &Path) -> Result<(syntax::Header, Vec<Node>)> {
+    let (text, _) = decode(path)?;
+    syntax::parse(&text).map_err(|e| anyhow!("{} {e}", path.display()))
+}
+
+/// ファイルをヘッダーの文字コードで読む。
+///
+/// 文字コードの指定がなければ UTF-8
+pub(super) fn decode(path: &Path) -> Result<(String, &'static Encoding)> {
+    let bytes = fs::read(path).with_context(|| format!("{} を読み込めません", path.display()))?;
+    let first = bytes.split(|b| *b == b'\n').next().unwrap_or_default();
+    let encoding = syntax::header(
+        String::from_utf8_lossy(first)
+            .trim_start_matches('\u{feff}')
+            .trim(),
+    )
+    .and_then(|header| header.encoding)
+    .and_then(|label| Encoding::for_label(label.as_bytes()))
+    .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(&bytes);
+    Ok((text.into_owned(), encoding))
+}
+
+/// 1文ずつ評価したファイル
+#[derive(Debug)]
+pub(super) struct Evaluated {
+    /// ファイルのパス
+    pub file: PathBuf,
+
+    /// 読み始めたときの距離程 (m)
+    pub z0: f64,
+
+    /// 文ごとの、評価した後の距離程と構文
+    ///
+    /// 距離程・変数・include の文は構文が `None`
+    pub nodes: Vec<(f64, Option<Statement>)>,
+}
+
+/// マップファイル `path` を include をたどって1文ずつ評価する。
+///
+/// 読み込んだ順に、ファイルごとの評価を返す。include したファイルは親のマップの変数と距離程を引き継ぐ。
+pub(super) fn evaluate(path: &Path) -> Result<Vec<Evaluated>> {
+    let (_, nodes) = load(path)?;
+    let mut reader = Reader {
+        trace: Some(vec![]),
+        ..Default::default()
+    };
+    reader.read(&nodes, Some(path), 0)?;
+    Ok(reader.trace.unwrap_or_default())
+}
+
+/// 文を順に評価する。
+#[derive(Default)]
+struct Reader {
+    /// 現在の距離程
+    z: f64,
+
+    /// 変数
+    vars: HashMap<String, Value>,
+
+    /// 直前の半径とカント
+    curve: (f64, f64),
+
+    /// 直前の勾配
+    gradient: f64,
+
+    /// 軌道ごとの直前の (X座標, Y座標, 水平の相対半径, 鉛直の相対半径)
+    tracks: HashMap<String, (f64, f64, f64, f64)>,
+
+    /// 評価した構文
+    entries: Vec<Entry>,
+
+    /// ファイルごとの1文ずつの評価
+    ///
+    /// 記録しないなら `None`
+    trace: Option<Vec<Evaluated>>,
+}
+
+impl Reader {
+    /// ファイル `file` の文 `nodes` を評価する。
+    fn read(&mut self, nodes: &[Node], file: Option<&Path>, depth: usize) -> Result<()> {
+        let traced = match (&mut self.trace, file) {
+            (Some(trace), Some(file)) => {
+                trace.push(Evaluated {
+                    file: file.to_path_buf(),
+                    z0: self.z,
+                    nodes: vec![],
+                });
+                Some(trace.len() - 1)
+            }
+            _ => None,
+        };
+        for node in nodes {
+            let error = |e: anyhow::Error| match file {
+                Some(file) => anyhow!("{} {}: {e}", file.display(), node.span),
+                None => anyhow!("{}: {e}", node.span),
+            };
+            match &node.syntax {
+                Syntax::Distance(expr) => self.z = self.number(expr).map_err(error)?,
+                Syntax::Let(name, expr) => {
+                    let value = self.eval(expr).map_err(error)?;
+                    self.vars.insert(name.clone(), value);
+                }
+                Syntax::Include(expr) => {
+                    let path = self.text(expr).map_err(error)?;
+                    match file {
+                        Some(file) => {
+                            ensure!(depth < MAX_DEPTH, error(anyhow!("include が深すぎます")));
+                            let path = file.parent().unwrap_or(Path::new("")).join(path);
+                            let (_, nodes) = load(&path)?;
+                            self.read(&nodes, Some(&path), depth + 1)?;
+                        }
+                        None => self.push(Statement::Include(path), node.span, file),
+                    }
+                }
+                Syntax::Call {
+                    element,
+                    key,
+                    path,
+                    args,
+                } => {
+                    let statement = self.call(element, key, path, args).map_err(error)?;
+                    self.push(statement, node.span, file);
+                }
+            }
+            if let (Some(trace), Some(i)) = (&mut self.trace, traced) {
+                let statement = match node.syntax {
+                    Syntax::Call { .. } => self.entries.last().map(|entry| entry.statement.clone()),
+                    _ => None,
+                };
+                trace[i].nodes.push((self.z, statement));
+            }
+        }
+        Ok(())
+    }
+
+    /// 構文を現在の距離程で追加する。
+    fn push(&mut self, statement: Statement, span: Span, file: Option<&Path>) {
+        self.entries.push(Entry {
+            z: self.z,
+            statement,
+            span,
+            file: file.map(Path::to_path_buf),
+        });
+    }
+
+    /// マップ要素の関数を評価する。
+    fn call(
+        &mut self,
+        element: &str,
+        key: &Option<Expr>,
+        path: &[String],
+        args: &[Option<Expr>],
+    ) -> Result<Statement> {
+        let key = key.as_ref().map(|key| self.eval(key)).transpose()?;
+        let key = key.map(|key| key.to_string());
+        let values = args
+            .iter()
+            .map(|arg| arg.as_ref().map(|arg| self.eval(arg)).transpose())
+            .collect::<Result<Vec<_>>>()?;
+        let number = |i: usize| -> Result<Option<f64>> {
+            match values.get(i) {
+                Some(Some(Value::Number(n))) => Ok(Some(*n)),
+                Some(Some(Value::Text(s))) => {
+                    bail!("{}番目の引数 '{s}' が数値ではありません", i + 1)
+                }
+                _ => Ok(None),
+            }
+        };
+        let required = |i: usize| -> Result<f64> {
+            number(i)?.with_context(|| format!("{}番目の引数を省略できません", i + 1))
+        };
+
+        let element_lower = element.to_ascii_lowercase();
+        let path_lower = path
+            .iter()
+            .map(|s| s.to_ascii_lowercase())
+            .collect::<Vec<_>>();
+        let path_lower = path_lower.iter().map(String::as_str).collect::<Vec<_>>();
+        let statement = match (element_lower.as_str(), &key, path_lower.as_slice()) {
+            ("curve", None, ["begintransition"]) => Statement::Curve(Curve::BeginTransition),
+            ("curve", None, ["begin" | "begincircular"]) => {
+                self.curve = (required(0)?, number(1)?.unwrap_or(0.0));
+                Statement::Curve(Curve::Begin(self.curve.0, self.curve.1))
+            }
+            ("curve", None, ["change"]) => {
+                self.curve = (required(0)?, 0.0);
+                Statement::Curve(Curve::Begin(self.curve.0, self.curve.1))
+            }
+            ("curve", None, ["end"]) => {
+                self.curve = (0.0, 0.0);
+                Statement::Curve(Curve::End)
+            }
+            ("curve", None, ["interpolate"]) => {
+                self.curve = (
+                    number(0)?.unwrap_or(self.curve.0),
+                    number(1)?.unwrap_or(self.curve.1),
+                );
+                Statement::Curve(Curve::Interpolate(self.curve.0, self.curve.1))
+            }
+            ("gradient", None, ["begintransition"]) => {
+                Statement::Gradient(Gradient::BeginTransition)
+            }
+            ("gradient", None, ["begin" | "beginconst"]) => {
+                self.gradient = required(0)?;
+                Statement::Gradient(Gradient::Begin(self.gradient))
+            }
+            ("gradient", None, ["end"]) => {
+                self.gradient = 0.0;
+                Statement::Gradient(Gradient::End)
+            }
+            ("gradient", None, ["interpolate"]) => {
+                self.gradient = number(0)?.unwrap_or(self.gradient);
+                Statement::Gradient(Gradient::Interpolate(self.gradient))
+            }
+            ("track", Some(key), [sub, "interpolate"] | [sub @ "position"])
+                if matches!(*sub, "x" | "y" | "position") =>
+            {
+                let (x, y, rx, ry) = self.tracks.entry(key.clone()).or_default();
+                let track = match *sub {
+                    "x" => {
+                        (*x, *rx) = (number(0)?.unwrap_or(*x), number(1)?.unwrap_or(*rx));
+                        Track::X(*x, *rx)
+                    }
+                    "y" => {
+                        (*y, *ry) = (number(0)?.unwrap_or(*y), number(1)?.unwrap_or(*ry));
+                        Track::Y(*y, *ry)
+                    }
+                    _ => {
+                        (*x, *y, *rx, *ry) = (
+                            number(0)?.unwrap_or(*x),
+                            number(1)?.unwrap_or(*y),
+                            number(2)?.unwrap_or(*rx),
+                            number(3)?.unwrap_or(*ry),
+                        );
+                        Track::Position(*x, *y, *rx, *ry)
+                    }
+                };
+                Statement::Track {
+                    key: key.clone(),
+                    track,
+                }
+            }
+            ("track", None, _) => bail!("Track にキーがありません"),
+            (_, None, ["load"]) => {
+                let path = match values.first() {
+                    Some(Some(Value::Text(path))) => path.clone(),
+                    _ => bail!("{element}.Load にファイルのパスがありません"),
+                };
+                Statement::Load {
+                    element: element.to_string(),
+                    path,
+                }
+            }
+            ("structure", Some(key), [_]) => Statement::Structure {
+                key: key.clone(),
+                function: path[0].clone(),
+                args: values,
+            },
+            ("station", Some(key), [_]) => Statement::Station {
+                key: key.clone(),
+                function: path[0].clone(),
+                args: values,
+            },
+            _ => Statement::Other {
+                name: [element]
+                    .into_iter()
+                    .chain(path.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join("."),
+                key,
+                args: values,
+            },
+        };
+        Ok(statement)
+    }
+
+    /// 式を数値として評価する。
+    fn number(&self, expr: &Expr) -> Result<f64> {
+        match self.eval(expr)? {
+            Value::Number(n) => Ok(n),
+            Value::Text(s) => bail!("'{s}' が数値ではありません"),
+        }
+    }
+
+    /// 式を文字列として評価する。
+    fn text(&self, expr: &Expr) -> Result<String> {
+        match self.eval(expr)? {
+            Value::Text(s) => Ok(s),
+            Value::Number(n) => bail!("{n} が文字列ではありません"),
+        }
+    }
+
+    /// 式を評価する。
+    fn eval(&self, expr: &Expr) -> Result<Value> {
+        let value = match expr {
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Text(s) => Value::Text(s.clone()),
+            Expr::Distance => Value::Number(self.z),
+            Expr::Var(name) => self
+                .vars
+                .get(name)
+                .cloned()
+                .with_context(|| format!("変数 ${name} が定義されていません"))?,
+            Expr::Neg(expr) => Value::Number(-self.number(expr)?),
+            Expr::Binary(op, lhs, rhs) => match (op, self.eval(lhs)?, self.eval(rhs)?) {
+                ('+', Value::Text(a), b) => Value::Text(format!("{a}{b}")),
+                ('+', a, Value::Text(b)) => Value::Text(format!("{a}{b}")),
+                (op, Value::Number(a), Value::Number(b)) => Value::Number(match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    _ => a % b,
+                }),
+                (op, _, _) => bail!("文字列に {op} を使えません"),
+            },
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.number(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                let arity = match name.as_str() {
+                    "atan2" | "pow" => 2,
+                    "rand" => bail!("rand は使えません"),
+                    _ => 1,
+                };
+                ensure!(args.len() == arity, "{name} の引数は{arity}個です");
+                Value::Number(match name.as_str() {
+                    "abs" => args[0].abs(),
+                    "atan2" => args[0].atan2(args[1]),
+                    "ceil" => args[0].ceil(),
+                    "cos" => args[0].cos(),
+                    "exp" => args[0].exp(),
+                    "floor" => args[0].floor(),
+                    "log" => args[0].ln(),
+                    "pow" => args[0].powf(args[1]),
+                    "sin" => args[0].sin(),
+                    "sqrt" => args[0].sqrt(),
+                    _ => bail!("関数 {name} はありません"),
+                })
+            }
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{super::MapFile, *};
+    use crate::agent::bve::Format;
+
+    impl Map {
+        /// マップの文字列を読む。
+        ///
+        /// include はたどらずに [`Statement::Include`] とする。
+        pub(crate) fn parse(text: &str) -> Result<Self> {
+            let (header, nodes) = syntax::parse(text)?;
+            Self::read(header, &nodes, None)
+        }
+    }
+
+    /// 距離程と構文の組
+    fn statements(map: &Map) -> Vec<(f64, Statement)> {
+        map.entries
+            .iter()
+            .map(|entry| (entry.z, entry.statement.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn 構文を評価する() {
+        let text = "\
+BveTs Map 2.02
+$r = 400;
+Structure.Load('structures.txt');
+100;
+Curve.BeginTransition();
+Track['b'].X.Interpolate(3.8);
+distance + 20;
+Curve.Begin($r, 0.105);
+Track['b'].X.Interpolate(, -$r);
+Gradient.Begin(10);
+Structure['架線柱' + 1].Put('b', 0, 0, 0, 0, 0, 0, 0, 25);
+Station['駅'].Put(1, -5, 5);
+include 'other.txt';
+150;
+Curve.Interpolate(, 0);
+Curve.SetGauge(1.067);
+";
+        let map = Map::parse(text).unwrap();
+        assert_eq!(map.version, "2.02");
+        let number = |n| Some(Value::Number(n));
+        assert_eq!(
+            statements(&map),
+            [
+                (
+                    0.0,
+                    Statement::Load {
+                        element: "Structure".to_string(),
+                        path: "structures.txt".to_string()
+                    }
+                ),
+                (100.0, Statement::Curve(Curve::BeginTransition)),
+                (
+                    100.0,
+                    Statement::Track {
+                        key: "b".to_string(),
+                        track: Track::X(3.8, 0.0)
+                    }
+                ),
+                (120.0, Statement::Curve(Curve::Begin(400.0, 0.105))),
+                (
+                    120.0,
+                    Statement::Track {
+                        key: "b".to_string(),
+                        track: Track::X(3.8, -400.0)
+                    }
+                ),
+                (120.0, Statement::Gradient(Gradient::Begin(10.0))),
+                (
+                    120.0,
+                    Statement::Structure {
+                        key: "架線柱1".to_string(),
+                        function: "Put".to_string(),
+                        args: [Some(Value::Text("b".to_string()))]
+                            .into_iter()
+                            .chain([0.0; 7].map(number))
+                            .chain([number(25.0)])
+                            .collect()
+                    }
+                ),
+                (
+                    120.0,
+                    Statement::Station {
+                        key: "駅".to_string(),
+                        function: "Put".to_string(),
+                        args: vec![number(1.0), number(-5.0), number(5.0)]
+                    }
+                ),
+                (120.0, Statement::Include("other.txt".to_string())),
+                (150.0, Statement::Curve(Curve::Interpolate(400.0, 0.0))),
+                (
+                    150.0,
+                    Statement::Other {
+                        name: "Curve.SetGauge".to_string(),
+                        key: None,
+                        args: vec![number(1.067)]
+                    }
+                ),
+            ]
+        );
+        assert_eq!(map.entries[3].span.line, 8);
+    }
+
+    #[test]
+    fn 評価のエラーに位置をつける() {
+        let e = Map::parse("BveTs Map 2.02\n100;\n  Curve.Begin($r);\n").unwrap_err();
+        assert_eq!(e.to_string(), "3行3列: 変数 $r が定義されていません");
+        let e = Map::parse("BveTs Map 2.02\nCurve.Begin('a');\n").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "2行1列: 1番目の引数 'a' が数値ではありません"
+        );
+    }
+
+    #[test]
+    fn 書き込んだマップを読む() {
+        let dir = TempDir::new_in(".").unwrap();
+        let path = dir.path().join("map.txt");
+        let curve = [
+            (100.0, Curve::BeginTransition),
+            (130.0, Curve::Begin(-600.0, 0.09)),
+            (200.0, Curve::End),
+        ];
+        let mut map_file = MapFile::create(&path, Format::default()).unwrap();
+        map_file.write_curve(&curve).unwrap();
+        drop(map_file);
+
+        let map = Map::open(&path).unwrap();
+        assert_eq!(map.encoding.as_deref(), Some("utf-8"));
+        let expected = curve.map(|(z, curve)| (z, Statement::Curve(curve)));
+        assert_eq!(statements(&map), expected);
+    }
+
+    #[test]
+    fn includeをたどる() {
+        let dir = TempDir::new_in(".").unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let main = dir.path().join("main.txt");
+        fs::write(
+            &main,
+            "BveTs Map 2.02\n$z = 50;\ninclude 'sub/track.txt';\nCurve.End();\n",
+        )
+        .unwrap();
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS
+            .encode("BveTs Map 2.02:shift_jis\n$z;\nTrack['上り'].X.Interpolate(4);\n");
+        fs::write(dir.path().join("sub").join("track.txt"), bytes).unwrap();
+
+        let map = Map::open(&main).unwrap();
+        assert_eq!(
+            statements(&map),
+            [
+                (
+                    50.0,
+                    Statement::Track {
+                        key: "上り".to_string(),
+                        track: Track::X(4.0, 0.0)
+                    }
+                ),
+                (50.0, Statement::Curve(Curve::End)),
+            ]
+        );
+        assert_eq!(
+            map.entries[0].file,
+            Some(dir.path().join("sub").join("track.txt"))
+        );
+        assert_eq!(map.entries[0].span.line, 3);
+
+        fs::write(
+            dir.path().join("sub").join("track.txt"),
+            "BveTs Map 2.02\nCurve.Begin(;\n",
+        )
+        .unwrap();
+        let e = Map::open(&main).unwrap_err();
+        assert!(
+            e.to_string()
+                .ends_with("track.txt 2行13列: ; の前に式がありません"),
+            "{e}"
+        );
+    }
+}
@@ -0,0 +1,569 @@
+//! 既存のマップファイルへの他軌道の構文の差し込み
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use encoding_rs::UTF_8;
+
+use crate::track::relative::Relative;
+
+use super::{
+    map::{self, Evaluated, Statement, Track},
+    syntax::{self, Expr, Node, Syntax},
+    Format,
+};
+
+/// UTF-8 の BOM
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// 距離程の文から次の距離程の文の前までのまとまり
+#[derive(Debug, Default)]
+struct Block {
+    /// 距離程 (m)
+    z: f64,
+
+    /// 距離程の文の番号
+    ///
+    /// 最初の距離程の文より前は `None`
+    distance: Option<usize>,
+
+    /// 距離程の文に続く文の番号
+    nodes: Vec<usize>,
+}
+
+/// 置き換える範囲と文字列
+type Edit = (usize, usize, String);
+
+/// マップファイル `path` の他軌道 `name` の `X.Interpolate` 構文を `relative` で置き換える。
+///
+/// include したファイルに他軌道の構文があれば、そのファイルを親のマップの変数と距離程で評価して書き換える。
+/// その他の文とコメントは元のまま残す。`Position` 構文は `Y.Interpolate` 構文に書き換えて鉛直方向の位置だけを残す。
+/// ファイルの文字コードと改行はそのままにする。
+/// 書き換える前のファイルを控えとして残し、書き換えたファイルと控えのパスを返す。
+pub fn merge_track(
+    path: &Path,
+    format: &Format,
+    name: &str,
+    relative: &[Relative],
+) -> Result<(PathBuf, PathBuf)> {
+    let evaluated = target(path, name)?;
+    let path = evaluated.file.as_path();
+    let (text, encoding) = map::decode(path)?;
+    let merged = merge(&text, &evaluated, format, name, relative)
+        .map_err(|e| anyhow!("{} {e}", path.display()))?;
+    let (bytes, _, had_errors) = encoding.encode(&merged);
+    ensure!(
+        !had_errors,
+        "{}で表せない文字があります: {name}",
+        encoding.name()
+    );
+
+    let backup = backup_path(path);
+    fs::copy(path, &backup)
+        .with_context(|| format!("{} を作成できませんでした", backup.display()))?;
+    let bom = match encoding == UTF_8 && fs::read(&backup)?.starts_with(BOM) {
+        true => BOM,
+        false => &[],
+    };
+    fs::write(path, [bom, &bytes].concat())
+        .with_context(|| format!("{} に書き込めませんでした", path.display()))?;
+    Ok((path.to_path_buf(), backup))
+}
+
+/// 他軌道 `name` の構文を書き換えるファイルの評価
+///
+/// 他軌道の構文があるファイル、なければマップファイル `path`
+fn target(path: &Path, name: &str) -> Result<Evaluated> {
+    let files = map::evaluate(path)?;
+    let mut found = files
+        .iter()
+        .filter(|evaluated| {
+            evaluated.nodes.iter().any(|(_, statement)| {
+                matches!(statement, Some(Statement::Track { key, .. }) if key == name)
+            })
+        })
+        .map(|evaluated| &evaluated.file);
+    let target = found.next().unwrap_or(&files[0].file).clone();
+    if let Some(other) = found.find(|file| **file != target) {
+        bail!(
+            "他軌道 {name} の構文が {} と {} にあります",
+            target.display(),
+            other.display()
+        );
+    }
+
+    let mut evaluated = files
+        .into_iter()
+        .filter(|evaluated| evaluated.file == target);
+    let first = evaluated.next().unwrap();
+    ensure!(
+        evaluated.next().is_none(),
+        "{} は何度も include されているので差し込めません",
+        target.display()
+    );
+    Ok(first)
+}
+
+/// 評価 `evaluated` のマップの文字列 `text` に他軌道の構文を差し込む。
+///
+/// 差し込む距離程の文があればその最後の文の行の後に、なければ距離程の順になる位置に新しく距離程の文を書く。
+/// 他軌道の構文を取り除いて空になった距離程の文は、前の空行とともに取り除く。
+fn merge(
+    text: &str,
+    evaluated: &Evaluated,
+    format: &Format,
+    name: &str,
+    relative: &[Relative],
+) -> Result<String> {
+    let (_, nodes) = syntax::parse(text)?;
+    let newline = match text.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+
+    // 差し込む構文を距離程ごとにまとめる
+    let mut statements = format
+        .track(name, relative)
+        .into_iter()
+        .map(|(z, statement)| (format.round(z), statement))
+        .collect::<Vec<_>>();
+    statements.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut inserts: Vec<(f64, Vec<String>)> = vec![];
+    for (z, statement) in statements {
+        match inserts.last_mut() {
+            Some((last, group)) if *last == z => group.push(statement),
+            _ => inserts.push((z, vec![statement])),
+        }
+    }
+
+    // 距離程の文ごとにまとめる
+    let mut blocks = vec![Block {
+        z: format.round(evaluated.z0),
+        ..Default::default()
+    }];
+    for (i, node) in nodes.iter().enumerate() {
+        match node.syntax {
+            Syntax::Distance(_) => blocks.push(Block {
+                z: format.round(evaluated.nodes[i].0),
+                distance: Some(i),
+                nodes: vec![],
+            }),
+            _ => blocks.last_mut().unwrap().nodes.push(i),
+        }
+    }
+
+    // 他軌道の構文を取り除く
+    let mut edits: Vec<Edit> = vec![];
+    let mut removed = vec![false; nodes.len()];
+    for (i, (node, (_, statement))) in nodes.iter().zip(&evaluated.nodes).enumerate() {
+        let span = node.span;
+        match statement {
+            Some(Statement::Track {
+                key,
+                track: Track::X(..),
+            }) if key == name => {
+                let (start, end) = whole_line(text, span.start, span.end);
+                edits.push((start, end, String::new()));
+                removed[i] = true;
+            }
+            Some(Statement::Track {
+                key,
+                track: Track::Position(_, y, _, ry),
+            }) if key == name => {
+                let (y, ry) = (format.round(*y), format.round(*ry));
+                let statement = format!("Track['{name}'].Y.Interpolate({y}, {ry});");
+                edits.push((span.start, span.end, statement));
+            }
+            _ => (),
+        }
+    }
+
+    // 差し込む距離程の文を決める
+    let target = |z: f64| {
+        blocks
+            .iter()
+            .rposition(|block| block.distance.is_some() && block.z == z)
+    };
+    let targets = inserts.iter().map(|(z, _)| target(*z)).collect::<Vec<_>>();
+
+    // 空になった距離程の文を取り除く
+    let mut emptied = vec![false; blocks.len()];
+    for (b, block) in blocks.iter().enumerate() {
+        let Some(distance) = block.distance else {
+            continue;
+        };
+        // 次の距離程の文が distance を使うなら、取り除くと距離程がずれる
+        let next_relative = blocks
+            .get(b + 1)
+            .and_then(|next| next.distance)
+            .is_some_and(|next| is_relative(&nodes[next]));
+        if block.nodes.is_empty()
+            || !block.nodes.iter().all(|i| removed[*i])
+            || targets.contains(&Some(b))
+            || next_relative
+        {
+            continue;
+        }
+        let span = nodes[distance].span;
+        let (mut start, end) = whole_line(text, span.start, span.end);
+        let previous = text[..start.saturating_sub(1)]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let expanded = (start, end) != (span.start, span.end);
+        if expanded && start > 0 && text[previous..start].trim().is_empty() {
+            start = previous;
+        }
+        edits.push((start, end, String::new()));
+        emptied[b] = true;
+    }
+
+    // 構文を差し込む
+    for ((z, group), target) in inserts.iter().zip(targets) {
+        let edit = match target {
+            Some(b) => {
+                let block = &blocks[b];
+                let anchor = block
+                    .nodes
+                    .iter()
+                    .rev()
+                    .find(|i| !removed[**i])
+                    .or(block.distance.as_ref())
+                    .unwrap();
+                let at = line_end(text, nodes[*anchor].span.end);
+                let mut s = String::new();
+                for statement in group {
+                    s += newline;
+                    s += statement;
+                }
+                (at, at, s)
+            }
+            None => {
+                let block = blocks.iter().enumerate().find_map(|(b, block)| {
+                    let distance = block.distance?;
+                    (block.z > *z && !emptied[b]).then_some(distance)
+                });
+                let mut s = String::new();
+                let at = match block {
+                    Some(distance) => {
+                        let start = nodes[distance].span.start;
+                        text[..start].rfind('\n').map_or(0, |i| i + 1)
+                    }
+                    None => {
+                        if !text.ends_with('\n') {
+                            s += newline;
+                        }
+                        s += newline;
+                        text.len()
+                    }
+                };
+                s += &format!("{z};{newline}");
+                for statement in group {
+                    s += statement;
+                    s += newline;
+                }
+                if let Some(distance) = block {
+                    // distance を使う次の距離程の文のために、差し込む前の距離程に戻す
+                    let before = format.round(match distance {
+                        0 => evaluated.z0,
+                        _ => evaluated.nodes[distance - 1].0,
+                    });
+                    if is_relative(&nodes[distance]) && before != *z {
+                        s += &format!("{before};{newline}");
+                    }
+                    s += newline;
+                }
+                (at, at, s)
+            }
+        };
+        edits.push(edit);
+    }
+
+    // 前から順に置き換える
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut merged = String::new();
+    let mut cursor = 0;
+    for (start, end, s) in edits {
+        merged += &text[cursor..start];
+        merged += &s;
+        cursor = end;
+    }
+    merged += &text[cursor..];
+    Ok(merged)
+}
+
+/// 距離程の文 `node` が現在の距離程 `distance` を使うか
+fn is_relative(node: &Node) -> bool {
+    match &node.syntax {
+        Syntax::Distance(expr) => uses_distance(expr),
+        _ => false,
+    }
+}
+
+/// 式 `expr` が現在の距離程 `distance` を使うか
+fn uses_distance(expr: &Expr) -> bool {
+    match expr {
+        Expr::Distance => true,
+        Expr::Neg(expr) => uses_distance(expr),
+        Expr::Binary(_, lhs, rhs) => uses_distance(lhs) || uses_distance(rhs),
+        Expr::Call(_, args) => args.iter().any(uses_distance),
+        Expr::Number(_) | Expr::Text(_) | Expr::Var(_) => false,
+    }
+}
+
+/// 範囲 `start..end` の行に他の文字がなければ、範囲を行全体に広げる。
+fn whole_line(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i + 1);
+    match text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty() {
+        true => (line_start, line_end),
+        false => (start, end),
+    }
+}
+
+/// 位置 `at` の行の改行の手前
+fn line_end(text: &str, at: usize) -> usize {
+    let end = text[at..].find('\n').map_or(text.len(), |i| at + i);
+    match text[..end].ends_with('\r') {
+        true => end - 1,
+        false => end,
+    }
+}
+
+/// 書き換える前のファイルの控えのパス
+///
+/// `map.txt` なら `map.txt.bak`、すでにあれば `map.txt-1.bak` のように番号をつける。
+fn backup_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    let with = |suffix: String| {
+        let mut s = OsString::from(name);
+        s.push(suffix);
+        path.with_file_name(s)
+    };
+    let mut backup = with(".bak".to_string());
+    let mut i = 1;
+    while backup.exists() {
+        backup = with(format!("-{i}.bak"));
+        i += 1;
+    }
+    backup
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn 他軌道(z: f64, x: f64) -> Relative {
+        Relative { z, x, r: 0.0 }
+    }
+
+    /// マップの文字列 `text` を評価して、他軌道の構文を差し込む。
+    fn 差し込む(text: &str, name: &str, relative: &[Relative]) -> Result<String> {
+        let dir = TempDir::new_in(".").unwrap();
+        let path = dir.path().join("map.txt");
+        fs::write(&path, text).unwrap();
+        let evaluated = map::evaluate(&path)?.remove(0);
+        merge(text, &evaluated, &Format::default(), name, relative)
+    }
+
+    /// `dir` にマップファイルを作る。
+    fn 書く(dir: &TempDir, files: &[(&str, &[&str])]) -> PathBuf {
+        for (name, lines) in files {
+            let text = [&["BveTs Map 2.02"], *lines, &[""]].concat().join("\n");
+            fs::write(dir.path().join(name), text).unwrap();
+        }
+        dir.path().join(files[0].0)
+    }
+
+    #[test]
+    fn 他軌道の構文を置き換える() {
+        let text = [
+            "BveTs Map 2.02",
+            "// 既存の線路",
+            "0;",
+            "Curve.Begin(400); // 円曲線",
+            "Track['1'].X.Interpolate(3.8, 0);",
+            "Track['2'].X.Interpolate(-3.8, 0);",
+            "",
+            "10;",
+            "Track['1'].X.Interpolate(4, 0);",
+            "",
+            "20;",
+            "Track['1'].Position(4, 1);",
+            "Curve.End();",
+            "",
+        ]
+        .join("\r\n");
+        let relative = [他軌道(0.0, 3.5), 他軌道(5.0, 3.6), 他軌道(30.0, 3.7)];
+        let merged = 差し込む(&text, "1", &relative).unwrap();
+        let expected = [
+            "BveTs Map 2.02",
+            "// 既存の線路",
+            "0;",
+            "Curve.Begin(400); // 円曲線",
+            "Track['2'].X.Interpolate(-3.8, 0);",
+            "Track['1'].X.Interpolate(3.5, 0);",
+            "",
+            "5;",
+            "Track['1'].X.Interpolate(3.6, 0);",
+            "",
+            "20;",
+            "Track['1'].Y.Interpolate(1, 0);",
+            "Curve.End();",
+            "",
+            "30;",
+            "Track['1'].X.Interpolate(3.7, 0);",
+            "",
+        ]
+        .join("\r\n");
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn 控えを残して書き換える() {
+        let dir = TempDir::new_in(".").unwrap();
+        let path = dir.path().join("map.txt");
+        let text = "BveTs Map 2.02:shift_jis\n0;\n";
+        fs::write(&path, text).unwrap();
+        fs::write(dir.path().join("map.txt.bak"), "").unwrap();
+
+        let (merged, backup) =
+            merge_track(&path, &Format::default(), "上り", &[他軌道(0.0, 1.0)]).unwrap();
+        assert_eq!(merged, path);
+        assert_eq!(backup, dir.path().join("map.txt-1.bak"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("map.txt-1.bak")).unwrap(),
+            text
+        );
+
+        let bytes = fs::read(&path).unwrap();
+        let (merged, _, _) = encoding_rs::SHIFT_JIS.decode(&bytes);
+        assert_eq!(
+            merged,
+            format!("{text}Track['上り'].X.Interpolate(1, 0);\n")
+        );
+    }
+
+    #[test]
+    fn include先に親のマップの変数と距離程で差し込む() {
+        let dir = TempDir::new_in(".").unwrap();
+        let main = 書く(
+            &dir,
+            &[
+                (
+                    "main.txt",
+                    &["$x = 3.8;", "100;", "include 'track.txt';", "200;"],
+                ),
+                (
+                    "track.txt",
+                    &[
+                        "Track['1'].X.Interpolate($x, 0);",
+                        "distance + 10;",
+                        "Track['1'].X.Interpolate(4, 0);",
+                        "distance + 10;",
+                        "Track['2'].X.Interpolate(1, 0);",
+                    ],
+                ),
+            ],
+        );
+        let relative = [他軌道(100.0, 3.5), 他軌道(105.0, 3.6), 他軌道(110.0, 3.7)];
+        let (merged, backup) = merge_track(&main, &Format::default(), "1", &relative).unwrap();
+        assert_eq!(merged, dir.path().join("track.txt"));
+        assert_eq!(backup, dir.path().join("track.txt.bak"));
+        assert!(!dir.path().join("main.txt.bak").exists());
+
+        let expected = [
+            "BveTs Map 2.02",
+            "100;",
+            "Track['1'].X.Interpolate(3.5, 0);",
+            "",
+            "105;",
+            "Track['1'].X.Interpolate(3.6, 0);",
+            "100;",
+            "",
+            "distance + 10;",
+            "Track['1'].X.Interpolate(3.7, 0);",
+            "distance + 10;",
+            "Track['2'].X.Interpolate(1, 0);",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(fs::read_to_string(&merged).unwrap(), expected);
+
+        // 親のマップから読んだ距離程が変わらない
+        let tracks = map::Map::open(&main)
+            .unwrap()
+            .entries
+            .into_iter()
+            .filter_map(|entry| match entry.statement {
+                Statement::Track { key, track } => Some((entry.z, key, track)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tracks,
+            [
+                (100.0, "1".to_string(), Track::X(3.5, 0.0)),
+                (105.0, "1".to_string(), Track::X(3.6, 0.0)),
+                (110.0, "1".to_string(), Track::X(3.7, 0.0)),
+                (120.0, "2".to_string(), Track::X(1.0, 0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn 他軌道の構文が複数のファイルにあればエラー() {
+        let dir = TempDir::new_in(".").unwrap();
+        let main = 書く(
+            &dir,
+            &[
+                (
+                    "main.txt",
+                    &[
+                        "0;",
+                        "Track['1'].X.Interpolate(1, 0);",
+                        "include 'track.txt';",
+                    ],
+                ),
+                ("track.txt", &["10;", "Track['1'].X.Interpolate(2, 0);"]),
+            ],
+        );
+        let e = merge_track(&main, &Format::default(), "1", &[他軌道(0.0, 1.0)]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "他軌道 1 の構文が {} と {} にあります",
+                main.display(),
+                dir.path().join("track.txt").display()
+            )
+        );
+    }
+
+    #[test]
+    fn 何度もincludeされたファイルはエラー() {
+        let dir = TempDir::new_in(".").unwrap();
+        let main = 書く(
+            &dir,
+            &[
+                (
+                    "main.txt",
+                    &["0;", "include 'track.txt';", "50;", "include 'track.txt';"],
+                ),
+                ("track.txt", &["Track['1'].X.Interpolate(2, 0);"]),
+            ],
+        );
+        let e = merge_track(&main, &Format::default(), "1", &[他軌道(0.0, 1.0)]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "{} は何度も include されているので差し込めません",
+                dir.path().join("track.txt").display()
+            )
+        );
+    }
+}
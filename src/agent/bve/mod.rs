@@ -0,0 +1,469 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context, Result};
+use derive_more::{Deref, DerefMut};
+use encoding_rs::SHIFT_JIS;
+
+use crate::track::relative::Relative;
+
+pub mod map;
+pub mod merge;
+pub mod syntax;
+
+/// BVEマップファイル
+pub struct MapFile {
+    file: BufWriter<Box<dyn Write>>,
+
+    /// 書式
+    format: Format,
+}
+
+/// マップファイルの書式
+#[derive(Debug, Clone, PartialEq)]
+pub struct Format {
+    /// 文字コード
+    pub encoding: Encoding,
+
+    /// 数値の小数点以下の桁数
+    pub precision: u16,
+
+    /// 作図元のファイル
+    ///
+    /// コメントとして書き込む。
+    pub source: Option<PathBuf>,
+}
+
+/// マップファイルの文字コード
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    ShiftJis,
+}
+
+impl Format {
+    /// 小数点以下を書式の桁数に丸める。
+    fn round(&self, f: f64) -> f64 {
+        let scale = 10f64.powi(self.precision.into());
+        let f = (f * scale).round() / scale;
+        // -0 を 0 にする
+        f + 0.0
+    }
+
+    /// 他軌道の距離程と構文
+    fn track(&self, name: &str, relative: &[Relative]) -> Vec<(f64, String)> {
+        relative
+            .iter()
+            .map(|Relative { z, x, r }| {
+                let (x, r) = (self.round(*x), self.round(*r));
+                (*z, format!("Track['{name}'].X.Interpolate({x}, {r});"))
+            })
+            .collect()
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            encoding: Encoding::default(),
+            precision: 3,
+            source: None,
+        }
+    }
+}
+
+impl MapFile {
+    /// ファイルシステムにマップファイルを作成する
+    pub fn create(path: &(impl AsRef<Path> + ?Sized), format: Format) -> Result<Self> {
+        let path = path.as_ref();
+        ensure!(!path.exists(), "{} はすでに存在しています", path.display());
+        Self::new(File::create(path)?, format)
+    }
+
+    /// マップファイルの形式で `out` に出力する。
+    ///
+    /// ヘッダーと、作成したプログラムのコメントを書き込む。
+    pub fn new(out: impl Write + 'static, format: Format) -> Result<Self> {
+        let file = BufWriter::new(Box::new(out) as Box<dyn Write>);
+        let mut map_file = Self { file, format };
+        let encoding = match map_file.format.encoding {
+            Encoding::Utf8 => "utf-8",
+            Encoding::ShiftJis => "shift_jis",
+        };
+        map_file.puts(format!("BveTs Map 2.02:{encoding}"))?;
+        map_file.puts(format!("// f5rail {} で作成", env!("CARGO_PKG_VERSION")))?;
+        if let Some(source) = map_file.format.source.clone() {
+            map_file.puts(format!("// 作図元: {}", source.display()))?;
+        }
+        Ok(map_file)
+    }
+
+    /// トラック名と相対座標をマップファイルに書き込む
+    pub fn write_track(&mut self, name: &str, relative: &[Relative]) -> Result<()> {
+        let statements = self.format.track(name, relative);
+        self.write(statements)
+    }
+
+    /// 自軌道の曲線をマップファイルに書き込む
+    pub fn write_curve(&mut self, curve: &[(f64, Curve)]) -> Result<()> {
+        let statements = curve
+            .iter()
+            .map(|(z, statement)| {
+                let statement = match *statement {
+                    Curve::BeginTransition => "Curve.BeginTransition();".to_string(),
+                    Curve::Begin(r, cant) => {
+                        format!("Curve.Begin({}, {});", self.round(r), self.round(cant))
+                    }
+                    Curve::End => "Curve.End();".to_string(),
+                    Curve::Interpolate(r, cant) => {
+                        format!(
+                            "Curve.Interpolate({}, {});",
+                            self.round(r),
+                            self.round(cant)
+                        )
+                    }
+                };
+                (*z, statement)
+            })
+            .collect();
+        self.write(statements)
+    }
+
+    /// 自軌道の勾配と他軌道の高さをマップファイルに書き込む
+    ///
+    /// 他軌道の高さは軌道名と (距離程, 自軌道との高さの差) で、鉛直の相対半径は0とする。
+    pub fn write_vertical(
+        &mut self,
+        gradient: &[(f64, Gradient)],
+        tracks: &[(&str, Vec<(f64, f64)>)],
+    ) -> Result<()> {
+        let mut statements = gradient
+            .iter()
+            .map(|(z, statement)| {
+                let statement = match *statement {
+                    Gradient::BeginTransition => "Gradient.BeginTransition();".to_string(),
+                    Gradient::Begin(g) => format!("Gradient.Begin({});", self.round(g)),
+                    Gradient::End => "Gradient.End();".to_string(),
+                    Gradient::Interpolate(g) => {
+                        format!("Gradient.Interpolate({});", self.round(g))
+                    }
+                };
+                (*z, statement)
+            })
+            .collect::<Vec<_>>();
+        for (name, heights) in tracks {
+            statements.extend(heights.iter().map(|(z, y)| {
+                let y = self.round(*y);
+                (*z, format!("Track['{name}'].Y.Interpolate({y}, 0);"))
+            }));
+        }
+        self.write(statements)
+    }
+
+    /// 距離程と構文を書き込む。
+    ///
+    /// 距離程の順に並べ、同じ距離程の構文は元の順にまとめる。
+    fn write(&mut self, statements: Vec<(f64, String)>) -> Result<()> {
+        let mut statements = statements
+            .into_iter()
+            .map(|(z, statement)| (self.round(z), statement))
+            .collect::<Vec<_>>();
+        statements.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut last_z = None;
+        for (z, statement) in statements {
+            if last_z != Some(z) {
+                self.puts("")?;
+                self.puts(format!("{z};"))?;
+                last_z = Some(z);
+            }
+            self.puts(statement)?;
+        }
+        self.file
+            .flush()
+            .context("マップファイルへの書き込みに失敗しました")
+    }
+
+    /// 小数点以下を書式の桁数に丸める。
+    fn round(&self, f: f64) -> f64 {
+        self.format.round(f)
+    }
+
+    /// 文字列と改行を出力する。
+    fn puts<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
+        let line = format!("{}\r\n", s.as_ref());
+        let bytes = match self.format.encoding {
+            Encoding::Utf8 => line.into_bytes(),
+            Encoding::ShiftJis => {
+                let (bytes, _, had_errors) = SHIFT_JIS.encode(&line);
+                ensure!(
+                    !had_errors,
+                    "Shift_JISで表せない文字があります: {}",
+                    s.as_ref()
+                );
+                bytes.into_owned()
+            }
+        };
+        self.file
+            .write_all(&bytes)
+            .context("マップファイルへの書き込みに失敗しました")
+    }
+}
+
+/// 自軌道の曲線の構文
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    /// 緩和曲線を開始する。
+    BeginTransition,
+
+    /// 円曲線を開始する (半径, カント)
+    ///
+    /// 右カーブが正。
+    Begin(f64, f64),
+
+    /// 直線を開始する。
+    End,
+
+    /// 前の点との間を補間する (半径, カント)
+    Interpolate(f64, f64),
+}
+
+/// 自軌道の勾配の構文 (‰)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gradient {
+    /// 縦曲線を開始する。
+    BeginTransition,
+
+    /// 勾配を開始する。
+    ///
+    /// 上り勾配が正。
+    Begin(f64),
+
+    /// 水平を開始する。
+    End,
+
+    /// 前の点との間を補間する。
+    Interpolate(f64),
+}
+
+#[derive(Debug, PartialEq, Deref, DerefMut)]
+pub struct MapPath {
+    buf: PathBuf,
+}
+
+// 「～～に作成しました」みたいにしたい。
+// パスを決めてからファイルを作成するまでにタイムラグがある問題は残る。
+impl MapPath {
+    pub fn build<T>(
+        given: &(impl AsRef<Path> + ?Sized),
+        proj_dir: impl FnMut() -> Result<T>,
+    ) -> Result<Self>
+    where
+        PathBuf: From<T>,
+    {
+        let mut path = Self::resolve(given, proj_dir)?;
+        if path.exists() {
+            path.add_number();
+        }
+        Ok(path)
+    }
+
+    /// 差し込み先の既存のマップファイル
+    ///
+    /// パスの決め方は [`MapPath::build`] と同じで、ファイルがなければエラー
+    pub fn existing<T>(
+        given: &(impl AsRef<Path> + ?Sized),
+        proj_dir: impl FnMut() -> Result<T>,
+    ) -> Result<Self>
+    where
+        PathBuf: From<T>,
+    {
+        let path = Self::resolve(given, proj_dir)?;
+        ensure!(path.is_file(), "{} がありません", path.display());
+        Ok(path)
+    }
+
+    /// 相対パスは図面のフォルダから、ファイル名と拡張子を補う。
+    fn resolve<T>(
+        given: &(impl AsRef<Path> + ?Sized),
+        mut proj_dir: impl FnMut() -> Result<T>,
+    ) -> Result<Self>
+    where
+        PathBuf: From<T>,
+    {
+        let given = given.as_ref();
+        let buf = match given.is_absolute() {
+            true => PathBuf::new(),
+            false => PathBuf::from(proj_dir()?),
+        };
+        let mut path = Self { buf };
+        path.push(given);
+        if given.file_name().is_none() || path.is_dir() {
+            path.push("map");
+        }
+        if path.extension().is_none() {
+            path.set_extension("txt");
+        }
+        Ok(path)
+    }
+
+    fn add_number(&mut self) {
+        let stem = self.file_stem().unwrap().to_os_string();
+        let ext = self.extension().unwrap().to_os_string();
+        let mut i = 1;
+        while self.exists() {
+            let mut name = OsString::new();
+            name.push(&stem);
+            name.push(format!("-{i}."));
+            name.push(&ext);
+
+            self.pop();
+            self.push(name);
+            i += 1;
+        }
+    }
+}
+
+impl AsRef<Path> for MapPath {
+    fn as_ref(&self) -> &Path {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    use crate::agent::buffer::Buffer;
+
+    use super::*;
+
+    #[rstest]
+    #[case::empty("", vec!["map.txt"])]
+    #[case::extension("a", vec!["a.txt"])]
+    #[case::numbering("b", vec!["b-1.txt"])]
+    #[case::numbering2("c", vec!["c-2.txt"])]
+    #[case::numbering3("c-1", vec!["c-1-1.txt"])]
+    #[case::dir("d", vec!["d", "map.txt"])]
+    #[case::dir2("e", vec!["e", "map-1.txt"])]
+    fn 相対パス判断(#[case] given: &str, #[case] relative: Vec<&str>) {
+        let proj = TestDir::new().unwrap();
+        let mut expected = proj.path().to_path_buf();
+        expected.push(relative.iter().collect::<PathBuf>());
+        let actual = MapPath::build(given, || Ok(proj.path())).unwrap();
+        assert_eq!(actual.as_ref(), expected);
+    }
+
+    #[test]
+    fn 既存のファイルは番号をつけない() {
+        let proj = TestDir::new().unwrap();
+        let path = MapPath::existing("b", || Ok(proj.path())).unwrap();
+        assert_eq!(path.as_ref(), proj.path_with(vec!["b.txt"]));
+        let e = MapPath::existing("f", || Ok(proj.path())).unwrap_err();
+        assert!(e.to_string().ends_with("f.txt がありません"), "{e}");
+    }
+
+    #[test]
+    #[cfg(target_os = "windows")]
+    fn 絶対パス判断() {
+        let given = r"C:\dir\file.txt";
+        let path = MapPath::build(given, || Ok("dir")).unwrap();
+        assert_eq!(path.as_ref(), Path::new(given));
+    }
+
+    #[test]
+    fn 距離程の順にまとめて書き込む() {
+        let buffer = Buffer::default();
+        let format = Format {
+            precision: 1,
+            ..Default::default()
+        };
+        let mut map_file = MapFile::new(buffer.clone(), format).unwrap();
+        let curve = [
+            (102.0, Curve::Begin(400.04, 0.0)),
+            (100.0, Curve::BeginTransition),
+            (101.96, Curve::End),
+            (101.0, Curve::Interpolate(-800.0, -0.049)),
+        ];
+        map_file.write_curve(&curve).unwrap();
+
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        let version = format!("// f5rail {} で作成", env!("CARGO_PKG_VERSION"));
+        let expected = [
+            "BveTs Map 2.02:utf-8",
+            &version,
+            "",
+            "100;",
+            "Curve.BeginTransition();",
+            "",
+            "101;",
+            "Curve.Interpolate(-800, 0);",
+            "",
+            "102;",
+            "Curve.Begin(400, 0);",
+            "Curve.End();",
+            "",
+        ];
+        assert_eq!(text, expected.join("\r\n"));
+    }
+
+    #[test]
+    fn shift_jisで書き込む() {
+        let buffer = Buffer::default();
+        let format = Format {
+            encoding: Encoding::ShiftJis,
+            source: Some(PathBuf::from("線形.jww")),
+            ..Default::default()
+        };
+        let mut map_file = MapFile::new(buffer.clone(), format).unwrap();
+        let relative = [Relative {
+            z: 0.0,
+            x: 3.8,
+            r: 0.0,
+        }];
+        map_file.write_track("上り", &relative).unwrap();
+
+        let bytes = buffer.0.take();
+        let (text, _, had_errors) = SHIFT_JIS.decode(&bytes);
+        assert!(!had_errors);
+        assert!(text.starts_with("BveTs Map 2.02:shift_jis\r\n"));
+        assert!(text.contains("// 作図元: 線形.jww\r\n"));
+        assert!(text.ends_with("0;\r\nTrack['上り'].X.Interpolate(3.8, 0);\r\n"));
+
+        let e = map_file.write_track("🚃", &relative).unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("Shift_JISで表せない文字があります"));
+    }
+
+    #[derive(Deref)]
+    struct TestDir(TempDir);
+
+    impl TestDir {
+        fn new() -> Result<Self> {
+            let dir = Self(TempDir::new_in(".")?);
+            File::create(dir.path_with(vec!["a"]))?;
+            File::create(dir.path_with(vec!["b.txt"]))?;
+            File::create(dir.path_with(vec!["c.txt"]))?;
+            File::create(dir.path_with(vec!["c-1.txt"]))?;
+            fs::create_dir(dir.path_with(vec!["d"]))?;
+            fs::create_dir(dir.path_with(vec!["e"]))?;
+            File::create(dir.path_with(vec!["e", "map.txt"]))?;
+            Ok(dir)
+        }
+
+        fn path_with(&self, s: Vec<&str>) -> PathBuf {
+            let mut path = self.0.path().to_path_buf();
+            path.push(s.iter().collect::<PathBuf>());
+            path
+        }
+    }
+}
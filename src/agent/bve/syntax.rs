@@ -0,0 +1,543 @@
+//! BVEマップの構文
+//!
+//! (参考) マップ構文 2.02
+//! https://bvets.net/jp/edit/formats/route/map.html
+
+use std::fmt::Display;
+
+use anyhow::{bail, ensure, Context, Result};
+
+/// ソースコード中の位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    /// 開始位置 (バイト)
+    pub start: usize,
+
+    /// 終了位置 (バイト)
+    pub end: usize,
+
+    /// 行番号
+    pub line: usize,
+
+    /// 列番号 (文字数)
+    pub column: usize,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}行{}列", self.line, self.column)
+    }
+}
+
+/// ヘッダー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// バージョン
+    pub version: String,
+
+    /// 文字コード
+    ///
+    /// 無指定なら `None`
+    pub encoding: Option<String>,
+}
+
+/// 式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// 数値
+    Number(f64),
+
+    /// 文字列
+    Text(String),
+
+    /// 現在の距離程 `distance`
+    Distance,
+
+    /// 変数 `$name`
+    Var(String),
+
+    /// 符号の反転
+    Neg(Box<Expr>),
+
+    /// 二項演算 (+, -, *, /, %)
+    Binary(char, Box<Expr>, Box<Expr>),
+
+    /// 関数呼び出し `abs(x)`
+    Call(String, Vec<Expr>),
+}
+
+/// ひとつの文
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    /// 位置
+    pub span: Span,
+
+    /// 文の種類
+    pub syntax: Syntax,
+}
+
+/// 文の種類
+#[derive(Debug, Clone, PartialEq)]
+pub enum Syntax {
+    /// 距離程 `100;`
+    Distance(Expr),
+
+    /// 変数への代入 `$name = 1;`
+    Let(String, Expr),
+
+    /// 別のファイルの読み込み `include 'file';`
+    Include(Expr),
+
+    /// マップ要素の関数 `Track['a'].X.Interpolate(1, 2);`
+    Call {
+        /// マップ要素名
+        element: String,
+
+        /// キー
+        key: Option<Expr>,
+
+        /// サブ要素名と関数名
+        path: Vec<String>,
+
+        /// 引数
+        ///
+        /// 省略した引数は `None`
+        args: Vec<Option<Expr>>,
+    },
+}
+
+/// 字句
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Var(String),
+    Punct(char),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{n}"),
+            Token::Text(s) => write!(f, "'{s}'"),
+            Token::Ident(s) => write!(f, "{s}"),
+            Token::Var(s) => write!(f, "${s}"),
+            Token::Punct(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+/// マップファイルをヘッダーと文に分ける。
+pub fn parse(text: &str) -> Result<(Header, Vec<Node>)> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let header = header(first.trim()).context("1行目に BveTs Map のヘッダーがありません")?;
+    let tokens = tokenize(rest, first.len() + 1, 2)?;
+    let nodes = Parser { tokens, i: 0 }.nodes()?;
+    Ok((header, nodes))
+}
+
+/// ヘッダー行 `BveTs Map 2.02:utf-8`
+pub(super) fn header(line: &str) -> Option<Header> {
+    let rest = line
+        .get(..9)
+        .filter(|s| s.eq_ignore_ascii_case("BveTs Map"))
+        .map(|_| line[9..].trim())?;
+    let (version, encoding) = match rest.split_once(':') {
+        Some((version, encoding)) => (version, Some(encoding.trim().to_string())),
+        None => (rest, None),
+    };
+    let version = version.trim();
+    (version.starts_with("2.")).then(|| Header {
+        version: version.to_string(),
+        encoding,
+    })
+}
+
+/// 字句に分ける。
+///
+/// `offset` は `text` の先頭のバイト位置、`line` はその行番号。
+fn tokenize(text: &str, offset: usize, line: usize) -> Result<Vec<(Token, Span)>> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    let (mut line, mut line_start) = (line, 0);
+    while let Some(&(i, c)) = chars.peek() {
+        let span = |end: usize, line_start: usize| Span {
+            start: offset + i,
+            end: offset + end,
+            line,
+            column: text[line_start..i].chars().count() + 1,
+        };
+        let take_while = |chars: &mut std::iter::Peekable<std::str::CharIndices>,
+                          f: fn(char) -> bool| {
+            let mut end = i;
+            while let Some(&(j, c)) = chars.peek() {
+                if !f(c) {
+                    break;
+                }
+                end = j + c.len_utf8();
+                chars.next();
+            }
+            end
+        };
+        match c {
+            '\n' => {
+                chars.next();
+                line += 1;
+                line_start = i + 1;
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                take_while(&mut chars, |c| c != '\n');
+            }
+            '/' if text[i..].starts_with("//") => {
+                take_while(&mut chars, |c| c != '\n');
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && text[i + 1..].starts_with(|c: char| c.is_ascii_digit())) =>
+            {
+                let end = take_while(&mut chars, |c| c.is_ascii_digit() || c == '.');
+                let number = text[i..end].parse().ok().with_context(|| {
+                    format!(
+                        "{}: 数値 {} を読み取れません",
+                        span(end, line_start),
+                        &text[i..end]
+                    )
+                })?;
+                tokens.push((Token::Number(number), span(end, line_start)));
+            }
+            '\'' => {
+                chars.next();
+                // 空の文字列なら開きの引用符の直後
+                let end = take_while(&mut chars, |c| c != '\'' && c != '\n').max(i + 1);
+                ensure!(
+                    chars.next_if(|(_, c)| *c == '\'').is_some(),
+                    "{}: 文字列が ' で閉じられていません",
+                    span(end, line_start)
+                );
+                let s = text[i + 1..end].to_string();
+                tokens.push((Token::Text(s), span(end + 1, line_start)));
+            }
+            '$' => {
+                chars.next();
+                let end = take_while(&mut chars, is_ident);
+                ensure!(
+                    end > i + 1,
+                    "{}: $ の後に変数名がありません",
+                    span(end, line_start)
+                );
+                let name = text[i + 1..end].to_ascii_lowercase();
+                tokens.push((Token::Var(name), span(end, line_start)));
+            }
+            c if is_ident(c) => {
+                let end = take_while(&mut chars, is_ident);
+                tokens.push((
+                    Token::Ident(text[i..end].to_string()),
+                    span(end, line_start),
+                ));
+            }
+            ';' | '.' | ',' | '(' | ')' | '[' | ']' | '=' | '+' | '-' | '*' | '/' | '%' => {
+                chars.next();
+                tokens.push((Token::Punct(c), span(i + 1, line_start)));
+            }
+            _ => bail!(
+                "{}: 使えない文字 {c} があります",
+                span(i + c.len_utf8(), line_start)
+            ),
+        }
+    }
+    Ok(tokens)
+}
+
+/// 識別子に使える文字
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 字句から文を組み立てる。
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    i: usize,
+}
+
+impl Parser {
+    /// 全ての文
+    fn nodes(&mut self) -> Result<Vec<Node>> {
+        let mut nodes = vec![];
+        while let Some(start) = self.span() {
+            if self.eat(';') {
+                continue;
+            }
+            let syntax = self.statement()?;
+            let end = self.expect(';')?;
+            let span = Span {
+                end: end.end,
+                ..start
+            };
+            nodes.push(Node { span, syntax });
+        }
+        Ok(nodes)
+    }
+
+    /// セミコロンを除いた文
+    fn statement(&mut self) -> Result<Syntax> {
+        match (self.peek(0), self.peek(1)) {
+            (Some(Token::Ident(name)), _) if name.eq_ignore_ascii_case("include") => {
+                self.i += 1;
+                Ok(Syntax::Include(self.expr()?))
+            }
+            (Some(Token::Var(name)), Some(Token::Punct('='))) => {
+                let name = name.clone();
+                self.i += 2;
+                Ok(Syntax::Let(name, self.expr()?))
+            }
+            (Some(Token::Ident(element)), Some(Token::Punct('[' | '.'))) => {
+                let element = element.clone();
+                self.i += 1;
+                let key = match self.eat('[') {
+                    true => {
+                        let key = self.expr()?;
+                        self.expect(']')?;
+                        Some(key)
+                    }
+                    false => None,
+                };
+                let mut path = vec![];
+                while self.eat('.') {
+                    path.push(self.ident()?);
+                }
+                ensure!(
+                    !path.is_empty(),
+                    "{}: . の後に関数名がありません",
+                    self.here()
+                );
+                self.expect('(')?;
+                let args = self.args()?;
+                Ok(Syntax::Call {
+                    element,
+                    key,
+                    path,
+                    args,
+                })
+            }
+            _ => Ok(Syntax::Distance(self.expr()?)),
+        }
+    }
+
+    /// 閉じ括弧までの引数
+    ///
+    /// 省略した引数は `None`
+    fn args(&mut self) -> Result<Vec<Option<Expr>>> {
+        let mut args = vec![];
+        if self.eat(')') {
+            return Ok(args);
+        }
+        loop {
+            match self.peek(0) {
+                Some(Token::Punct(',' | ')')) => args.push(None),
+                _ => args.push(Some(self.expr()?)),
+            }
+            if self.eat(')') {
+                return Ok(args);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    /// 加減算
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Punct(op @ ('+' | '-'))) = self.peek(0) {
+            let op = *op;
+            self.i += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    /// 乗除算
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Punct(op @ ('*' | '/' | '%'))) = self.peek(0) {
+            let op = *op;
+            self.i += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    /// 符号
+    fn unary(&mut self) -> Result<Expr> {
+        match self.eat('-') {
+            true => Ok(Expr::Neg(Box::new(self.unary()?))),
+            false => {
+                self.eat('+');
+                self.primary()
+            }
+        }
+    }
+
+    /// 数値・文字列・変数・関数・括弧
+    fn primary(&mut self) -> Result<Expr> {
+        let here = self.here();
+        let Some((token, _)) = self.tokens.get(self.i).cloned() else {
+            bail!("{here}: 式がありません");
+        };
+        self.i += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Text(s) => Ok(Expr::Text(s)),
+            Token::Var(name) => Ok(Expr::Var(name)),
+            Token::Ident(name) if name.eq_ignore_ascii_case("distance") => Ok(Expr::Distance),
+            Token::Ident(name) => {
+                self.expect('(')?;
+                let args = self
+                    .args()?
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .with_context(|| format!("{here}: {name} の引数を省略できません"))?;
+                Ok(Expr::Call(name.to_ascii_lowercase(), args))
+            }
+            Token::Punct('(') => {
+                let expr = self.expr()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Token::Punct(c) => bail!("{here}: {c} の前に式がありません"),
+        }
+    }
+
+    /// 識別子
+    fn ident(&mut self) -> Result<String> {
+        match self.peek(0) {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.i += 1;
+                Ok(name)
+            }
+            _ => bail!("{}: 名前がありません", self.here()),
+        }
+    }
+
+    /// 記号 `c` があれば読み進めて `true`
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek(0) == Some(&Token::Punct(c));
+        if found {
+            self.i += 1;
+        }
+        found
+    }
+
+    /// 記号 `c` を読み進める。
+    ///
+    /// なければエラー。
+    fn expect(&mut self, c: char) -> Result<Span> {
+        let here = self.here();
+        match self.tokens.get(self.i) {
+            Some((Token::Punct(p), span)) if *p == c => {
+                self.i += 1;
+                Ok(*span)
+            }
+            Some((token, _)) => bail!("{here}: {token} の前に {c} がありません"),
+            None => bail!("{here}: 最後に {c} がありません"),
+        }
+    }
+
+    /// `n` 個先の字句
+    fn peek(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.i + n).map(|(token, _)| token)
+    }
+
+    /// 現在の字句の位置
+    fn span(&self) -> Option<Span> {
+        self.tokens.get(self.i).map(|(_, span)| *span)
+    }
+
+    /// エラーを報告する位置
+    ///
+    /// 最後まで読んでいれば、最後の字句の終わり。
+    fn here(&self) -> Span {
+        self.span().unwrap_or_else(|| match self.tokens.last() {
+            Some((_, span)) => Span {
+                start: span.end,
+                column: span.column + 1,
+                ..*span
+            },
+            None => Span::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn ヘッダーと文に分ける() {
+        let text = "BveTs Map 2.02:utf-8\r\n$a = 1 + 2 * -3; // 代入\r\n100;\r\ninclude 'b.txt';\r\nTrack['1'].X.Interpolate(, $a);\r\n$b = '';\r\n";
+        let (header, nodes) = parse(text).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                version: "2.02".to_string(),
+                encoding: Some("utf-8".to_string())
+            }
+        );
+        let syntax = nodes.iter().map(|n| n.syntax.clone()).collect::<Vec<_>>();
+        let number = |n| Box::new(Expr::Number(n));
+        assert_eq!(
+            syntax,
+            [
+                Syntax::Let(
+                    "a".to_string(),
+                    Expr::Binary(
+                        '+',
+                        number(1.0),
+                        Box::new(Expr::Binary(
+                            '*',
+                            number(2.0),
+                            Box::new(Expr::Neg(number(3.0)))
+                        ))
+                    )
+                ),
+                Syntax::Distance(Expr::Number(100.0)),
+                Syntax::Include(Expr::Text("b.txt".to_string())),
+                Syntax::Call {
+                    element: "Track".to_string(),
+                    key: Some(Expr::Text("1".to_string())),
+                    path: vec!["X".to_string(), "Interpolate".to_string()],
+                    args: vec![None, Some(Expr::Var("a".to_string()))],
+                },
+                Syntax::Let("b".to_string(), Expr::Text(String::new())),
+            ]
+        );
+        assert_eq!((nodes[1].span.line, nodes[1].span.column), (3, 1));
+        assert_eq!(
+            &text[nodes[3].span.start..nodes[3].span.end],
+            "Track['1'].X.Interpolate(, $a);"
+        );
+    }
+
+    #[rstest]
+    #[case("Curve.Begin(400)\n", "2行17列: 最後に ; がありません")]
+    #[case("100;\n  Curve.Begin(400;\n", "3行18列: ; の前に , がありません")]
+    #[case("'abc;\n", "2行1列: 文字列が ' で閉じられていません")]
+    #[case("Curve.Begin(400) @;\n", "2行18列: 使えない文字 @ があります")]
+    #[case("Track[].X(1);\n", "2行7列: ] の前に式がありません")]
+    fn 位置つきのエラー(#[case] body: &str, #[case] expected: &str) {
+        let text = format!("BveTs Map 2.02\n{body}");
+        let e = parse(&text).unwrap_err();
+        assert_eq!(e.to_string(), expected);
+    }
+
+    #[test]
+    fn ヘッダーがなければエラー() {
+        let e = parse("100;\n").unwrap_err();
+        assert_eq!(e.to_string(), "1行目に BveTs Map のヘッダーがありません");
+    }
+}
@@ -0,0 +1,711 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fmt::{Display, Write as _},
+    fs::File,
+    io::{self, BufWriter},
+    path::PathBuf,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::{
+    track::relative::Relative,
+    transition::{
+        canvas::Stroke,
+        unit::{Deg, Meter, Vector},
+    },
+};
+
+use super::{
+    bat::Args,
+    bve::{self, merge, MapFile},
+    csv, dxf, jww,
+    sheet::Sheet,
+    svg,
+};
+
+/// サブコマンド
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sub {
+    /// 緩和曲線を作図する
+    Transition,
+
+    /// 2本の接線の間に円曲線を作図する
+    Curve,
+
+    /// 他線座標をBVEマップに出力する
+    Track,
+
+    /// BVEマップの自軌道を作図する
+    Route,
+
+    /// 勾配と縦曲線の縦断線形を作図する
+    Vertical,
+}
+
+/// 図形の出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Jw_cadの座標ファイル
+    Jww,
+
+    /// CSV
+    Csv,
+
+    /// DXF
+    Dxf,
+
+    /// SVG (プレビュー)
+    Svg,
+}
+
+/// オプションの値の型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// 数値
+    Number,
+
+    /// 値をとらない
+    Flag,
+
+    /// 逓減関数の名前
+    Diminish,
+
+    /// 逓減関数の名前を "入口,出口" で並べたもの
+    Diminishes,
+
+    /// 文字列
+    Text,
+
+    /// 座標 "x0,y0,x1,y1"
+    Line,
+
+    /// 座標 "x,y"
+    Point,
+}
+
+/// オプション
+struct Opt {
+    /// `--` に続く名前
+    long: &'static str,
+
+    /// 外部変形の引数のキー
+    ///
+    /// `None` ならコマンドラインのみのオプション。
+    key: Option<&'static str>,
+
+    /// 値の型
+    value: Value,
+
+    /// 説明
+    help: &'static str,
+}
+
+const fn opt(long: &'static str, key: &'static str, value: Value, help: &'static str) -> Opt {
+    let key = match key.is_empty() {
+        true => None,
+        false => Some(key),
+    };
+    Opt {
+        long,
+        key,
+        value,
+        help,
+    }
+}
+
+/// 図形を出力するサブコマンドに共通のオプション
+#[rustfmt::skip]
+const SHEET: [Opt; 2] = [
+    opt("format", "", Value::Text, "出力形式 jww, csv, dxf, svg (既定: jww)"),
+    opt("output", "", Value::Text, "出力ファイル (既定: 標準出力)"),
+];
+
+#[rustfmt::skip]
+const TRANSITION: [Opt; 27] = [
+    opt("diminish", "TRANSITION", Value::Diminish, "逓減関数 (既定: sine)"),
+    opt("r0", "R0", Value::Number, "開始半径 (m) マイナスは左曲線, 無指定は直線"),
+    opt("r1", "R1", Value::Number, "終了半径 (m) マイナスは左曲線, 無指定は直線"),
+    opt("tcl", "TCL", Value::Number, "緩和曲線長 (m) 無指定は設計速度から決める"),
+    opt("l0", "L0", Value::Number, "始点距離程 (m)"),
+    opt("chain", "CHAIN", Value::Text, "区間の並び 半径/長さ,半径/長さ,…"),
+    opt("speed", "V", Value::Number, "設計速度 (km/h) カントを設計する"),
+    opt("gauge", "G", Value::Number, "軌間 (mm) 既定: 1067"),
+    opt("max-cant", "CM", Value::Number, "最大カント (mm) 既定: 105"),
+    opt("cant-deficiency", "CD", Value::Number, "許容カント不足量 (mm) 既定: 60"),
+    opt("cant-diminish", "CANT", Value::Diminish, "カントの逓減関数 (既定: 曲率と同じ)"),
+    opt("tolerance", "TOL", Value::Number, "横方向の許容誤差 (m) 無指定は1mごとに分割"),
+    opt("snap", "SNAP", Value::Flag, "分割を1mごとの距離程に揃える"),
+    opt("annotate", "注記", Value::Flag, "注記を作図する"),
+    opt("bve", "出力ファイル名", Value::Text, "曲線を出力するBVEマップファイル"),
+    opt("encoding", "文字コード", Value::Text, "BVEマップの文字コード utf-8, shift_jis (既定: utf-8)"),
+    opt("precision", "桁数", Value::Number, "BVEマップの小数点以下の桁数 (既定: 3)"),
+    opt("diagram", "線形図", Value::Flag, "曲率とカントの線形図を作図する"),
+    opt("diagram-origin", "線形図原点", Value::Point, "線形図の始点距離程の位置 x,y (既定: 始点の50m下)"),
+    opt("diagram-scale", "線形図縮尺", Value::Number, "線形図の距離程1mあたりの長さ (既定: 1)"),
+    opt("curvature-scale", "曲率倍率", Value::Number, "曲率1/mあたりの高さ (既定: 10000)"),
+    opt("cant-scale", "カント倍率", Value::Number, "カント1mmあたりの高さ (既定: 0.1)"),
+    opt("tick", "目盛り間隔", Value::Number, "線形図の目盛りの間隔 (m) 既定: 20"),
+    opt("x", "", Value::Number, "始点のX座標 (m)"),
+    opt("y", "", Value::Number, "始点のY座標 (m)"),
+    opt("angle", "", Value::Number, "始点の接線方向 (度) 反時計回りが正"),
+    opt("help", "", Value::Flag, "使い方を表示する"),
+];
+
+#[rustfmt::skip]
+const CURVE: [Opt; 9] = [
+    opt("diminish", "CURVE", Value::Diminishes, "逓減関数 入口[,出口] (既定: sine)"),
+    opt("r", "R", Value::Number, "円曲線半径 (m)"),
+    opt("tcl0", "TCL0", Value::Number, "入口緩和曲線長 (m)"),
+    opt("tcl1", "TCL1", Value::Number, "出口緩和曲線長 (m) 無指定は入口と同じ"),
+    opt("l0", "L0", Value::Number, "TS距離程 (m)"),
+    opt("entry", "", Value::Line, "入口側の接線 x0,y0,x1,y1"),
+    opt("exit", "", Value::Line, "出口側の接線 x0,y0,x1,y1"),
+    opt("annotate", "注記", Value::Flag, "注記を作図する"),
+    opt("help", "", Value::Flag, "使い方を表示する"),
+];
+
+#[rustfmt::skip]
+const TRACK: [Opt; 14] = [
+    opt("own", "TEMP_0", Value::Text, "自軌道の座標ファイル (出力始点 hp1 を含む) または DXF"),
+    opt("other", "TEMP_X", Value::Text, "他軌道の座標ファイル (トラック名を含む) または DXF"),
+    opt("interval", "出力間隔", Value::Number, "出力間隔 (m)"),
+    opt("start", "始点", Value::Point, "DXFの出力始点 x,y 無指定は自軌道の点 (POINT)"),
+    opt("z0", "始点距離程", Value::Number, "DXFの出力始点の距離程 (m) 既定: 0"),
+    opt("name", "トラック名", Value::Text, "DXFの他軌道のトラック名"),
+    opt("own-layer", "自軌道画層", Value::Text, "DXFの自軌道の画層 (既定: すべて)"),
+    opt("other-layer", "他軌道画層", Value::Text, "DXFの他軌道の画層 (既定: すべて)"),
+    opt("output", "", Value::Text, "出力するBVEマップファイル (既定: 標準出力)"),
+    opt("merge", "差し込み", Value::Flag, "--output の既存のBVEマップの他軌道の構文を置き換える (控えを .bak に残す)"),
+    opt("preview", "", Value::Text, "自軌道・他軌道を描くSVGファイル"),
+    opt("encoding", "文字コード", Value::Text, "BVEマップの文字コード utf-8, shift_jis (既定: utf-8)"),
+    opt("precision", "桁数", Value::Number, "BVEマップの小数点以下の桁数 (既定: 3)"),
+    opt("help", "", Value::Flag, "使い方を表示する"),
+];
+
+#[rustfmt::skip]
+const ROUTE: [Opt; 8] = [
+    opt("map", "ROUTE", Value::Text, "BVEマップファイル"),
+    opt("l0", "L0", Value::Number, "始点距離程 (m) 既定: 0"),
+    opt("l1", "終点距離程", Value::Number, "終点距離程 (m) 無指定はマップの最後の構文まで"),
+    opt("tick", "目盛り間隔", Value::Number, "距離程の目盛りの間隔 (m) 既定: 20"),
+    opt("x", "", Value::Number, "始点のX座標 (m)"),
+    opt("y", "", Value::Number, "始点のY座標 (m)"),
+    opt("angle", "", Value::Number, "始点の接線方向 (度) 反時計回りが正"),
+    opt("help", "", Value::Flag, "使い方を表示する"),
+];
+
+#[rustfmt::skip]
+const VERTICAL: [Opt; 12] = [
+    opt("profile", "VERTICAL", Value::Text, "勾配の変化点の表 (CSV) または縦断面図の座標ファイル"),
+    opt("radius", "縦曲線半径", Value::Number, "縦曲線半径 (m) 0は縦曲線なし, 既定: 3000"),
+    opt("l0", "L0", Value::Number, "基準点の距離程 (m) 既定: 0"),
+    opt("height", "基準高さ", Value::Number, "基準点の高さ (m) 既定: 0"),
+    opt("scale", "縦倍率", Value::Number, "高さ1mあたりの長さ (既定: 1)"),
+    opt("interval", "出力間隔", Value::Number, "他軌道の高さの出力間隔 (m) 既定: 5"),
+    opt("bve", "出力ファイル名", Value::Text, "勾配を出力するBVEマップファイル"),
+    opt("encoding", "文字コード", Value::Text, "BVEマップの文字コード utf-8, shift_jis (既定: utf-8)"),
+    opt("precision", "桁数", Value::Number, "BVEマップの小数点以下の桁数 (既定: 3)"),
+    opt("x", "", Value::Number, "基準点のX座標"),
+    opt("y", "", Value::Number, "基準点のY座標"),
+    opt("help", "", Value::Flag, "使い方を表示する"),
+];
+
+/// 逓減関数の名前と、外部変形の引数の値
+const DIMINISHES: [(&str, &str); 6] = [
+    ("sine", "1"),
+    ("linear", "2"),
+    ("cubic", "3"),
+    ("bloss", "4"),
+    ("klein", "5"),
+    ("schramm", "6"),
+];
+
+/// コマンドラインから実行するときの引数
+#[derive(Debug)]
+pub struct Command {
+    /// サブコマンド
+    pub sub: Sub,
+
+    /// 外部変形と共通の引数
+    pub args: Args,
+
+    /// コマンドラインのみのオプション
+    extra: HashMap<&'static str, String>,
+}
+
+impl Command {
+    /// コマンドライン引数 (プログラム名を除く) をパースする。
+    ///
+    /// 使い方を表示するだけなら `Ok(None)`
+    pub fn parse(args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Result<Option<Self>> {
+        let args = args
+            .into_iter()
+            .map(|os| os.as_ref().to_str().map(str::to_owned))
+            .collect::<Option<Vec<String>>>()
+            .context("非UTF-8文字は使えません")?;
+        let mut args = args.iter().map(String::as_str);
+
+        let sub = match args.next() {
+            Some("transition") => Sub::Transition,
+            Some("curve") => Sub::Curve,
+            Some("track") => Sub::Track,
+            Some("route") => Sub::Route,
+            Some("vertical") => Sub::Vertical,
+            None | Some("help" | "--help" | "-h") => {
+                print!("{}", usage());
+                return Ok(None);
+            }
+            Some(s) => bail!("不明なサブコマンド {s} です\n\n{}", usage()),
+        };
+
+        let mut buf = HashMap::new();
+        let mut extra = HashMap::new();
+        while let Some(arg) = args.next() {
+            let Some(long) = arg.strip_prefix("--") else {
+                bail!("不明な引数 {arg} です");
+            };
+            let (long, inline) = match long.split_once('=') {
+                Some((long, value)) => (long, Some(value)),
+                None => (long, None),
+            };
+            let opt = sub
+                .options()
+                .find(|opt| opt.long == long)
+                .with_context(|| format!("不明なオプション --{long} です"))?;
+            let value = match (opt.value, inline) {
+                (Value::Flag, None) => "1".to_string(),
+                (_, Some(value)) => value.to_string(),
+                (_, None) => args
+                    .next()
+                    .with_context(|| format!("--{long} に値を指定してください"))?
+                    .to_string(),
+            };
+            let value = opt.check(&value)?;
+            match opt.key {
+                Some(key) => {
+                    // BVEマップは出力ファイル名を指定したときだけ出力する
+                    if key == "出力ファイル名" {
+                        buf.insert("BVE", "1".to_string());
+                    }
+                    buf.insert(key, value);
+                }
+                None => {
+                    extra.insert(opt.long, value);
+                }
+            }
+        }
+
+        if extra.contains_key("help") {
+            print!("{}", sub.usage());
+            return Ok(None);
+        }
+
+        // 逓減関数を省略したらサイン半波長逓減
+        match sub {
+            Sub::Transition => buf.entry("TRANSITION").or_insert_with(|| "1".to_string()),
+            Sub::Curve => buf.entry("CURVE").or_insert_with(|| "1".to_string()),
+            Sub::Track => buf.entry("TRACK").or_insert_with(|| "X".to_string()),
+            // マップファイルを省略してもエラーは作図するときに返す
+            Sub::Route => buf.entry("ROUTE").or_default(),
+            Sub::Vertical => buf.entry("VERTICAL").or_default(),
+        };
+
+        let args = buf.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        Ok(Some(Self { sub, args, extra }))
+    }
+
+    /// 数値のオプション
+    ///
+    /// 無指定なら `default`
+    pub fn number(&self, long: &str, default: f64) -> f64 {
+        self.extra
+            .get(long)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// 座標のオプション
+    pub fn line(&self, long: &str) -> Result<[f64; 4]> {
+        let s = self
+            .extra
+            .get(long)
+            .with_context(|| format!("--{long} を指定してください"))?;
+        numbers(s)
+            .and_then(|v| v.try_into().ok())
+            .with_context(|| format!("--{long} に x0,y0,x1,y1 を指定してください"))
+    }
+
+    /// 図形の出力先
+    pub fn sheet(&self) -> Result<Output> {
+        let format = match self.extra.get("format").map(String::as_str) {
+            None | Some("jww") => Format::Jww,
+            Some("csv") => Format::Csv,
+            Some("dxf") => Format::Dxf,
+            Some("svg") => Format::Svg,
+            Some(s) => bail!("--format に jww, csv, dxf, svg のいずれかを指定してください: {s}"),
+        };
+        let out = self.out()?;
+        Ok(match format {
+            Format::Jww => Output::Jww(jww::Write::new(out)),
+            Format::Csv => Output::Csv(csv::Write::new(out)),
+            Format::Dxf => Output::Dxf(dxf::Write::new(out)),
+            Format::Svg => {
+                // 目盛りは始点距離程から数える
+                let mut svg = svg::Write::new(out);
+                svg.start(self.args.l0()?);
+                Output::Svg(svg)
+            }
+        })
+    }
+
+    /// 軌道のプレビューの出力先
+    ///
+    /// 指定されていなければ `None`
+    pub fn preview(&self) -> Result<Option<svg::Write>> {
+        let Some(path) = self.extra.get("preview") else {
+            return Ok(None);
+        };
+        let file = File::create(path).with_context(|| format!("{path} を作成できませんでした"))?;
+        Ok(Some(svg::Write::new(BufWriter::new(file))))
+    }
+
+    /// 他軌道の構文をBVEマップに出力する。
+    ///
+    /// 差し込みを指定したら、出力先の既存のマップファイルの構文を置き換える。
+    pub fn write_track(&self, name: &str, relative: &[Relative]) -> Result<()> {
+        if !self.args.merge()? {
+            return self.map_file()?.write_track(name, relative);
+        }
+        let path = self
+            .output()
+            .context("差し込むマップファイルを --output で指定してください")?;
+        ensure!(path.is_file(), "{} がありません", path.display());
+        merge::merge_track(&path, &self.args.map_format()?, name, relative)?;
+        Ok(())
+    }
+
+    /// BVEマップの出力先
+    ///
+    /// 自軌道のファイルを作図元とする。
+    fn map_file(&self) -> Result<MapFile> {
+        let format = bve::Format {
+            source: self.args.temp_0_path().ok().map(PathBuf::from),
+            ..self.args.map_format()?
+        };
+        match self.output() {
+            Some(path) => MapFile::create(&path, format),
+            None => MapFile::new(io::stdout(), format),
+        }
+    }
+
+    /// 出力ファイル
+    ///
+    /// 無指定なら標準出力
+    fn output(&self) -> Option<PathBuf> {
+        self.extra.get("output").map(PathBuf::from)
+    }
+
+    fn out(&self) -> Result<Box<dyn io::Write>> {
+        Ok(match self.output() {
+            Some(path) => {
+                let file = File::create(&path)
+                    .with_context(|| format!("{} を作成できませんでした", path.display()))?;
+                Box::new(BufWriter::new(file))
+            }
+            None => Box::new(io::stdout()),
+        })
+    }
+}
+
+impl Sub {
+    /// 名前
+    fn name(&self) -> &'static str {
+        match self {
+            Sub::Transition => "transition",
+            Sub::Curve => "curve",
+            Sub::Track => "track",
+            Sub::Route => "route",
+            Sub::Vertical => "vertical",
+        }
+    }
+
+    /// 説明
+    fn about(&self) -> &'static str {
+        match self {
+            Sub::Transition => "緩和曲線を作図する",
+            Sub::Curve => "2本の接線の間に、緩和曲線つきの円曲線を作図する",
+            Sub::Track => "他線座標をBVEマップに出力する",
+            Sub::Route => "BVEマップの自軌道を作図する",
+            Sub::Vertical => "勾配と縦曲線の縦断線形を作図する",
+        }
+    }
+
+    /// 受けつけるオプション
+    fn options(&self) -> impl Iterator<Item = &'static Opt> {
+        let (own, sheet): (&'static [Opt], &'static [Opt]) = match self {
+            Sub::Transition => (&TRANSITION, &SHEET),
+            Sub::Curve => (&CURVE, &SHEET),
+            Sub::Track => (&TRACK, &[]),
+            Sub::Route => (&ROUTE, &SHEET),
+            Sub::Vertical => (&VERTICAL, &SHEET),
+        };
+        own.iter().chain(sheet)
+    }
+
+    /// 使い方
+    fn usage(&self) -> String {
+        let mut s = format!(
+            "{}\n\n使い方: f5rail {} [オプション]\n\nオプション:\n",
+            self.about(),
+            self.name()
+        );
+        for opt in self.options() {
+            let value = match opt.value {
+                Value::Number => " <数値>",
+                Value::Flag => "",
+                Value::Diminish => " <関数>",
+                Value::Diminishes => " <関数>[,<関数>]",
+                Value::Text => " <値>",
+                Value::Line => " <x0,y0,x1,y1>",
+                Value::Point => " <x,y>",
+            };
+            // 全角文字は2桁として揃える。
+            let name = format!("--{}{value}", opt.long);
+            let width = name
+                .chars()
+                .map(|c| if c.is_ascii() { 1 } else { 2 })
+                .sum::<usize>();
+            let pad = " ".repeat(30usize.saturating_sub(width).max(1));
+            let _ = writeln!(s, "  {name}{pad}{}", opt.help);
+        }
+        if self
+            .options()
+            .any(|opt| matches!(opt.value, Value::Diminish | Value::Diminishes))
+        {
+            let names = DIMINISHES.map(|(name, _)| name).join(", ");
+            let _ = writeln!(s, "\n関数: {names}");
+        }
+        s
+    }
+}
+
+impl Opt {
+    /// 値の型を確かめて、外部変形の引数の値に変換する。
+    fn check(&self, value: &str) -> Result<String> {
+        let long = self.long;
+        match self.value {
+            Value::Number => {
+                ensure!(
+                    value.parse::<f64>().is_ok_and(f64::is_finite),
+                    "--{long} に数値を指定してください: {value}"
+                );
+                Ok(value.to_string())
+            }
+            Value::Flag => Ok(value.to_string()),
+            Value::Diminish => diminish(long, value),
+            Value::Diminishes => value
+                .split(',')
+                .map(|name| diminish(long, name))
+                .collect::<Result<Vec<_>>>()
+                .map(|v| v.join(",")),
+            Value::Text => Ok(value.to_string()),
+            Value::Line => {
+                ensure!(
+                    numbers(value).is_some_and(|v| v.len() == 4),
+                    "--{long} に x0,y0,x1,y1 を指定してください: {value}"
+                );
+                Ok(value.to_string())
+            }
+            Value::Point => {
+                ensure!(
+                    numbers(value).is_some_and(|v| v.len() == 2),
+                    "--{long} に x,y を指定してください: {value}"
+                );
+                Ok(value.to_string())
+            }
+        }
+    }
+}
+
+/// 逓減関数の名前を外部変形の引数の値に変換する。
+fn diminish(long: &str, name: &str) -> Result<String> {
+    DIMINISHES
+        .iter()
+        .find(|(n, v)| *n == name || *v == name)
+        .map(|(_, v)| v.to_string())
+        .with_context(|| format!("--{long} に正しい逓減関数を指定してください: {name}"))
+}
+
+/// カンマ区切りの数値
+fn numbers(s: &str) -> Option<Vec<f64>> {
+    s.split(',')
+        .map(|f| f.trim().parse().ok().filter(|f: &f64| f.is_finite()))
+        .collect()
+}
+
+/// 全体の使い方
+fn usage() -> String {
+    let mut s = "BVEの配線作業をCADで楽しくする。\n\n使い方: f5rail <サブコマンド> [オプション]\n\nサブコマンド:\n".to_string();
+    for sub in [
+        Sub::Transition,
+        Sub::Curve,
+        Sub::Track,
+        Sub::Route,
+        Sub::Vertical,
+    ] {
+        let _ = writeln!(s, "  {:<12} {}", sub.name(), sub.about());
+    }
+    let _ = writeln!(s, "\n各サブコマンドの使い方は f5rail <サブコマンド> --help");
+    s
+}
+
+/// コマンドラインから実行したときの図形の出力先
+///
+/// エラーは返し、注意は標準エラー出力に表示する。
+pub enum Output {
+    Jww(jww::Write),
+    Csv(csv::Write),
+    Dxf(dxf::Write),
+    Svg(svg::Write),
+}
+
+impl Sheet for Output {
+    fn error(&mut self, e: &impl Display) -> Result<()> {
+        bail!("{e}")
+    }
+
+    fn notice<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
+        eprintln!("{}", s.as_ref());
+        Ok(())
+    }
+
+    fn style(&mut self, style: &super::jww::Style) -> Result<()> {
+        match self {
+            Output::Jww(w) => w.style(style),
+            Output::Csv(w) => w.style(style),
+            Output::Dxf(w) => w.style(style),
+            Output::Svg(w) => w.style(style),
+        }
+    }
+
+    fn curve(
+        &mut self,
+        c: &impl Vector,
+        r: &impl Meter,
+        a0: &impl Deg,
+        a1: &impl Deg,
+    ) -> Result<()> {
+        match self {
+            Output::Jww(w) => w.curve(c, r, a0, a1),
+            Output::Csv(w) => w.curve(c, r, a0, a1),
+            Output::Dxf(w) => w.curve(c, r, a0, a1),
+            Output::Svg(w) => w.curve(c, r, a0, a1),
+        }
+    }
+
+    fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()> {
+        match self {
+            Output::Jww(w) => w.straight(p0, p1),
+            Output::Csv(w) => w.straight(p0, p1),
+            Output::Dxf(w) => w.straight(p0, p1),
+            Output::Svg(w) => w.straight(p0, p1),
+        }
+    }
+
+    fn text<T: AsRef<str>>(&mut self, p: &impl Vector, dir: &impl Deg, s: T) -> Result<()> {
+        match self {
+            Output::Jww(w) => w.text(p, dir, s),
+            Output::Csv(w) => w.text(p, dir, s),
+            Output::Dxf(w) => w.text(p, dir, s),
+            Output::Svg(w) => w.text(p, dir, s),
+        }
+    }
+
+    fn strokes(&mut self, strokes: &[Stroke]) -> Result<()> {
+        match self {
+            Output::Jww(w) => w.strokes(strokes),
+            Output::Csv(w) => w.strokes(strokes),
+            Output::Dxf(w) => w.strokes(strokes),
+            Output::Svg(w) => w.strokes(strokes),
+        }
+    }
+
+    fn close(&mut self) -> Result<()> {
+        match self {
+            Output::Jww(w) => w.close(),
+            Output::Csv(w) => w.close(),
+            Output::Dxf(w) => w.close(),
+            Output::Svg(w) => w.close(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> Result<Option<Command>> {
+        Command::parse(argv)
+    }
+
+    #[test]
+    fn オプションを外部変形の引数に変換する() {
+        let command = parse(&[
+            "transition",
+            "--diminish",
+            "linear",
+            "--r1=-400",
+            "--tcl",
+            "60",
+            "--annotate",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(command.sub, Sub::Transition);
+        assert_eq!(command.args.transition().unwrap(), "2");
+        assert_eq!(command.args.r1().unwrap(), Some(-400.0));
+        assert_eq!(command.args.tcl().unwrap(), Some(60.0));
+        assert!(command.args.annotate().unwrap());
+        assert!(!command.args.bve().unwrap());
+    }
+
+    #[test]
+    fn 逓減関数の既定はサイン() {
+        let command = parse(&["curve", "--diminish", "sine,cubic"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(command.args.curve().unwrap(), "1,3");
+        let command = parse(&["transition"]).unwrap().unwrap();
+        assert_eq!(command.args.transition().unwrap(), "1");
+    }
+
+    #[test]
+    fn 座標のオプション() {
+        let command = parse(&["curve", "--entry", "0,0,100,0"]).unwrap().unwrap();
+        assert_eq!(command.line("entry").unwrap(), [0.0, 0.0, 100.0, 0.0]);
+        assert_eq!(
+            command.line("exit").unwrap_err().to_string(),
+            "--exit を指定してください"
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(&["transition", "--tcl", "abc"], "--tcl に数値を指定してください: abc")]
+    #[case(&["transition", "--tcl"], "--tcl に値を指定してください")]
+    #[case(&["transition", "--r"], "不明なオプション --r です")]
+    #[case(&["transition", "r1"], "不明な引数 r1 です")]
+    #[case(&["curve", "--diminish", "euler"], "--diminish に正しい逓減関数を指定してください: euler")]
+    #[case(&["track", "--format", "csv"], "不明なオプション --format です")]
+    fn 不正なオプションはエラー(#[case] argv: &[&str], #[case] expected: &str) {
+        assert_eq!(parse(argv).unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn 使い方を表示する() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&["track", "--help"]).unwrap().is_none());
+        assert!(Sub::Transition.usage().contains("--tcl <数値>"));
+    }
+}
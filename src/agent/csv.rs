@@ -0,0 +1,103 @@
+use std::{fmt::Display, io};
+
+use anyhow::{bail, Context, Result};
+
+use crate::transition::unit::{Deg, Meter, Vector};
+
+use super::{jww::Style, sheet::Sheet};
+
+/// CSV形式の図形
+///
+/// 1行に1つの図形を出力する。
+/// - 直線: `line,x0,y0,x1,y1`
+/// - 円弧: `arc,cx,cy,r,a0,a1` (始点角から終点角まで反時計回り)
+/// - 文字: `text,x,y,方向,"文字列"`
+pub struct Write {
+    file: Box<dyn io::Write>,
+}
+
+impl Write {
+    /// CSV形式で `out` に出力する。
+    pub fn new(out: impl io::Write + 'static) -> Self {
+        Self {
+            file: Box::new(out),
+        }
+    }
+
+    /// 文字列と改行を出力する。
+    fn puts<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
+        writeln!(self.file, "{}", s.as_ref()).context("CSVの書き込みに失敗しました")
+    }
+}
+
+impl Sheet for Write {
+    /// エラーは図形ではないので、出力せずに返す。
+    fn error(&mut self, e: &impl Display) -> Result<()> {
+        bail!("{e}")
+    }
+
+    /// 注意は図形ではないので出力しない。
+    fn notice<T: AsRef<str>>(&mut self, _: T) -> Result<()> {
+        Ok(())
+    }
+
+    /// 線の属性は出力しない。
+    fn style(&mut self, _: &Style) -> Result<()> {
+        Ok(())
+    }
+
+    fn curve(
+        &mut self,
+        c: &impl Vector,
+        r: &impl Meter,
+        a0: &impl Deg,
+        a1: &impl Deg,
+    ) -> Result<()> {
+        let (a0, a1) = (a0.deg(), a1.deg());
+        let (a0, a1) = if a0 < a1 { (a0, a1) } else { (a1, a0) };
+        let r = r.meter().abs();
+        self.puts(format!("arc,{},{},{r},{a0},{a1}", c.x(), c.y()))
+    }
+
+    fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()> {
+        self.puts(format!("line,{},{},{},{}", p0.x(), p0.y(), p1.x(), p1.y()))
+    }
+
+    fn text<T: AsRef<str>>(&mut self, p: &impl Vector, dir: &impl Deg, s: T) -> Result<()> {
+        let s = s.as_ref().replace('"', "\"\"");
+        self.puts(format!("text,{},{},{},\"{s}\"", p.x(), p.y(), dir.deg()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        agent::buffer::Buffer,
+        transition::{
+            canvas::Point,
+            curve::{Degree, Radius, Tangential},
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn 図形を1行ずつ出力する() {
+        let buffer = Buffer::default();
+        let mut csv = Write::new(buffer.clone());
+        let (p0, p1) = (Point::from((0.0, 0.0)), Point::from((1.0, 2.0)));
+        csv.straight(&p0, &p1).unwrap();
+        let deg = |d: f64| Tangential::from(Degree(d));
+        csv.curve(&p1, &Radius(-3.0), &deg(90.0), &deg(0.0))
+            .unwrap();
+        csv.text(&p0, &deg(45.0), "BTC \"A\"").unwrap();
+        csv.notice("無視される").unwrap();
+        assert!(csv.error(&"エラー").is_err());
+
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        assert_eq!(
+            text,
+            "line,0,0,1,2\narc,1,2,3,0,90\ntext,0,0,45,\"BTC \"\"A\"\"\"\n"
+        );
+    }
+}
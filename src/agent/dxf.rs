@@ -0,0 +1,660 @@
+use std::{
+    f64::consts::PI,
+    fmt::{Display, Write as _},
+    fs, io,
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use encoding_rs::SHIFT_JIS;
+
+use crate::transition::{
+    canvas::{Point, Stroke},
+    curve::Degree,
+    unit::{Deg, Meter, Rad, Vector},
+};
+
+use super::{
+    jww::{Figure, Style},
+    sheet::Sheet,
+};
+
+/// 文字の高さ (m)
+const TEXT_HEIGHT: f64 = 2.5;
+
+/// DXF形式の図形
+///
+/// 直線は LINE、円弧は ARC、連続した線は POLYLINE (ふくらみつき) で出力する。
+/// ハンドルのいらない AutoCAD R12 形式で、使った画層を LAYER テーブルに書く。
+/// LWPOLYLINE はハンドルのいる R2000 以降にしかないので、R12 の POLYLINE と VERTEX で書く。
+/// ASCII でない文字は `\U+XXXX` で書く。
+pub struct Write {
+    file: Box<dyn io::Write>,
+
+    /// 画層名
+    layer: String,
+
+    /// 使った画層名
+    layers: Vec<String>,
+
+    /// ENTITIES セクションの中身
+    ///
+    /// 画層のテーブルを先に書くため、閉じるときまでためておく。
+    entities: String,
+}
+
+impl Write {
+    /// DXF形式で `out` に出力する。
+    pub fn new(out: impl io::Write + 'static) -> Self {
+        Self {
+            file: Box::new(out),
+            layer: "0".to_string(),
+            layers: vec!["0".to_string()],
+            entities: String::new(),
+        }
+    }
+
+    /// 図形の種類と画層を出力する。
+    fn entity(&mut self, kind: &str) {
+        self.group(0, kind);
+        let layer = self.layer.clone();
+        self.group(8, layer);
+    }
+
+    /// 座標を出力する。
+    fn point(&mut self, code: u16, p: &impl Vector) {
+        self.group(code, p.x());
+        self.group(code + 10, p.y());
+        self.group(code + 20, 0.0);
+    }
+
+    /// グループコードと値を出力する。
+    fn group(&mut self, code: u16, value: impl Display) {
+        let _ = write!(self.entities, "{code:>3}\r\n{value}\r\n");
+    }
+
+    /// ヘッダ、テーブル、ためておいた図形を `file` に書き出す。
+    fn flush(&mut self) -> Result<()> {
+        let mut head = String::new();
+        let mut group = |code: u16, value: &dyn Display| {
+            let _ = write!(head, "{code:>3}\r\n{value}\r\n");
+        };
+        group(0, &"SECTION");
+        group(2, &"HEADER");
+        group(9, &"$ACADVER");
+        group(1, &"AC1009");
+        group(0, &"ENDSEC");
+        group(0, &"SECTION");
+        group(2, &"TABLES");
+        group(0, &"TABLE");
+        group(2, &"LTYPE");
+        group(70, &1);
+        group(0, &"LTYPE");
+        group(2, &"CONTINUOUS");
+        group(70, &0);
+        group(3, &"Solid line");
+        group(72, &65);
+        group(73, &0);
+        group(40, &0.0);
+        group(0, &"ENDTAB");
+        group(0, &"TABLE");
+        group(2, &"LAYER");
+        group(70, &self.layers.len());
+        for layer in &self.layers {
+            group(0, &"LAYER");
+            group(2, layer);
+            group(70, &0);
+            group(62, &7);
+            group(6, &"CONTINUOUS");
+        }
+        group(0, &"ENDTAB");
+        group(0, &"ENDSEC");
+        group(0, &"SECTION");
+        group(2, &"ENTITIES");
+        let entities = std::mem::take(&mut self.entities);
+        write!(self.file, "{head}{entities}  0\r\nENDSEC\r\n  0\r\nEOF\r\n")
+            .and_then(|_| self.file.flush())
+            .context("DXFの書き込みに失敗しました")
+    }
+}
+
+impl Sheet for Write {
+    /// エラーは図形ではないので、出力せずに返す。
+    fn error(&mut self, e: &impl Display) -> Result<()> {
+        bail!("{e}")
+    }
+
+    /// 注意は図形ではないので出力しない。
+    fn notice<T: AsRef<str>>(&mut self, _: T) -> Result<()> {
+        Ok(())
+    }
+
+    /// レイヤを画層名にする。線色・線種は出力しない。
+    fn style(&mut self, style: &Style) -> Result<()> {
+        if let Some(layer) = style.layer {
+            self.layer = format!("{:X}-{layer:X}", style.group.unwrap_or(0));
+            if !self.layers.contains(&self.layer) {
+                self.layers.push(self.layer.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn curve(
+        &mut self,
+        c: &impl Vector,
+        r: &impl Meter,
+        a0: &impl Deg,
+        a1: &impl Deg,
+    ) -> Result<()> {
+        let (a0, a1) = (a0.deg(), a1.deg());
+        let (a0, a1) = if a0 < a1 { (a0, a1) } else { (a1, a0) };
+        self.entity("ARC");
+        self.point(10, c);
+        self.group(40, r.meter().abs());
+        self.group(50, a0);
+        self.group(51, a1);
+        Ok(())
+    }
+
+    fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()> {
+        self.entity("LINE");
+        self.point(10, p0);
+        self.point(11, p1);
+        Ok(())
+    }
+
+    fn text<T: AsRef<str>>(&mut self, p: &impl Vector, dir: &impl Deg, s: T) -> Result<()> {
+        self.entity("TEXT");
+        self.point(10, p);
+        self.group(40, TEXT_HEIGHT);
+        self.group(1, escape(s.as_ref()));
+        self.group(50, dir.deg());
+        Ok(())
+    }
+
+    /// 連続した線を1本の POLYLINE で出力する。
+    ///
+    /// 円弧は、中心角の1/4の正接 (ふくらみ) で表す。反時計回りが正。
+    fn strokes(&mut self, strokes: &[Stroke]) -> Result<()> {
+        let Some(first) = strokes.first() else {
+            return Ok(());
+        };
+        self.entity("POLYLINE");
+        self.group(66, 1);
+        self.point(10, &Point::from((0.0, 0.0)));
+        self.group(70, 0);
+        let mut vertex = |p: &Point, bulge: f64| {
+            self.entity("VERTEX");
+            self.point(10, p);
+            self.group(42, bulge);
+        };
+        let mut p0 = first.p0();
+        for stroke in strokes {
+            let sweep = stroke.t1().rad() - stroke.t0().rad();
+            vertex(&p0, bulge(sweep));
+            p0 = stroke.p1();
+        }
+        vertex(&p0, 0.0);
+        self.entity("SEQEND");
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.flush()
+    }
+}
+
+/// ASCII でない文字を `\U+XXXX` にする。
+fn escape(s: &str) -> String {
+    s.encode_utf16()
+        .map(|unit| match unit < 0x80 {
+            true => char::from(unit as u8).to_string(),
+            false => format!("\\U+{unit:04X}"),
+        })
+        .collect()
+}
+
+/// 中心角 `sweep` (rad) の円弧のふくらみ
+fn bulge(sweep: f64) -> f64 {
+    debug_assert!(sweep.abs() < 2.0 * PI);
+    (sweep / 4.0).tan()
+}
+
+/// DXFファイル中の図形
+///
+/// ENTITIES セクションの LINE、ARC、CIRCLE、POINT、LWPOLYLINE、POLYLINE を読み取る。
+/// それ以外の図形は無視する。
+pub struct Read {
+    entities: Vec<Entity>,
+}
+
+/// DXFファイル中の図形
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    /// 図形の種類が書かれた行番号
+    pub line: usize,
+
+    /// 画層名
+    pub layer: String,
+
+    /// 図形
+    ///
+    /// LWPOLYLINE と POLYLINE は線分ごとの直線・円弧に分ける。
+    pub figure: Figure,
+}
+
+impl Read {
+    /// DXFファイルを読み込む。
+    ///
+    /// UTF-8 でなければ Shift_JIS とみなす (AutoCAD 2004 以前の形式)
+    pub fn open(path: &(impl AsRef<Path> + ?Sized)) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("ファイル {} を開けませんでした", path.display()))?;
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => SHIFT_JIS.decode(e.as_bytes()).0.into_owned(),
+        };
+        Self::parse(&text).with_context(|| format!("{} を読み込めませんでした", path.display()))
+    }
+
+    /// DXF形式の文字列を読み込む。
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().map(str::trim).enumerate();
+        let mut groups = vec![];
+        while let Some((n, code)) = lines.next() {
+            let Some((_, value)) = lines.next() else {
+                bail!("{}行目のグループコードに値がありません", n + 1)
+            };
+            let code = code
+                .parse::<u16>()
+                .ok()
+                .with_context(|| format!("{}行目のグループコードが整数ではありません", n + 1))?;
+            groups.push((n + 2, code, value));
+        }
+
+        // ENTITIES セクションを図形ごとに区切る
+        let mut entities = vec![];
+        let mut section = "";
+        let mut iter = groups.iter().peekable();
+        while let Some(&(line, code, value)) = iter.next() {
+            match (code, value) {
+                (0, "SECTION") => {
+                    section = iter
+                        .next_if(|(_, code, _)| *code == 2)
+                        .map_or("", |(_, _, name)| name);
+                }
+                (0, "ENDSEC") => section = "",
+                (0, kind) if section == "ENTITIES" => {
+                    let mut body = vec![];
+                    while let Some(&(line, code, value)) = iter.next_if(|(_, code, _)| *code != 0) {
+                        body.push((line, code, value));
+                    }
+                    // POLYLINE は続く VERTEX を SEQEND まで頂点とする。POLYLINE 自身の座標は使わない。
+                    if kind == "POLYLINE" {
+                        body.retain(|(_, code, _)| !matches!(code, 10 | 20 | 30));
+                        while let Some(&(_, _, next)) = iter.next_if(|(_, code, value)| {
+                            *code == 0 && matches!(*value, "VERTEX" | "SEQEND")
+                        }) {
+                            while let Some(&group) = iter.next_if(|(_, code, _)| *code != 0) {
+                                if next == "VERTEX" {
+                                    body.push(group);
+                                }
+                            }
+                            if next == "SEQEND" {
+                                break;
+                            }
+                        }
+                    }
+                    entities.extend(Entity::parse(line, kind, &body)?);
+                }
+                _ => (),
+            }
+        }
+        Ok(Self { entities })
+    }
+
+    /// 画層 `layer` の図形
+    ///
+    /// `None` なら全ての画層。
+    pub fn entities<'a>(&'a self, layer: Option<&'a str>) -> impl Iterator<Item = &'a Entity> {
+        self.entities
+            .iter()
+            .filter(move |entity| layer.is_none_or(|layer| entity.layer == layer))
+    }
+}
+
+impl Entity {
+    /// 図形の種類 `kind` と、それに続くグループ `body` から図形を作る。
+    ///
+    /// `line` は図形の種類が書かれた行番号。
+    fn parse(line: usize, kind: &str, body: &[(usize, u16, &str)]) -> Result<Vec<Self>> {
+        let number = |code: u16| -> Result<Option<f64>> {
+            body.iter()
+                .find(|(_, c, _)| *c == code)
+                .map(|(line, _, value)| {
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|f: &f64| f.is_finite())
+                        .with_context(|| format!("{line}行目を数値で入力してください"))
+                })
+                .transpose()
+        };
+        let required = |code: u16| -> Result<f64> {
+            number(code)?
+                .with_context(|| format!("{line}行目の{kind}にグループコード{code}がありません"))
+        };
+        let layer = body
+            .iter()
+            .find(|(_, code, _)| *code == 8)
+            .map_or("0", |(_, _, value)| value)
+            .to_string();
+        // 押し出し方向が -Z なら、X座標が反転した座標系 (OCS) で書かれている
+        let mirror = number(230)?.is_some_and(|z| z < 0.0);
+        let point = |x: f64, y: f64| match mirror {
+            true => Point::from((-x, y)),
+            false => Point::from((x, y)),
+        };
+
+        let figures = match kind {
+            "LINE" => vec![Figure::Straight(
+                Point::from((required(10)?, required(20)?)),
+                Point::from((required(11)?, required(21)?)),
+            )],
+            "POINT" => vec![Figure::Dot(Point::from((required(10)?, required(20)?)))],
+            "CIRCLE" => vec![Figure::Circle {
+                c: point(required(10)?, required(20)?),
+                r: required(40)?,
+            }],
+            "ARC" => {
+                let (a0, a1) = (required(50)?, required(51)?);
+                let (a0, a1) = match mirror {
+                    true => (180.0 - a1, 180.0 - a0),
+                    false => (a0, a1),
+                };
+                vec![Figure::Curve {
+                    c: point(required(10)?, required(20)?),
+                    r: required(40)?,
+                    a0: Degree(a0),
+                    a1: Degree(a1),
+                }]
+            }
+            "LWPOLYLINE" | "POLYLINE" => {
+                // 頂点とふくらみ
+                let mut vertices: Vec<(Point, f64)> = vec![];
+                let mut x = None;
+                for &(line, code, value) in body {
+                    let value = || {
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|f| f.is_finite())
+                            .with_context(|| format!("{line}行目を数値で入力してください"))
+                    };
+                    match code {
+                        10 => x = Some(value()?),
+                        20 => {
+                            let x = x.take().with_context(|| {
+                                format!("{line}行目のY座標に対応するX座標がありません")
+                            })?;
+                            vertices.push((point(x, value()?), 0.0));
+                        }
+                        42 => {
+                            let bulge = vertices.last_mut().with_context(|| {
+                                format!("{line}行目のふくらみに対応する頂点がありません")
+                            })?;
+                            bulge.1 = if mirror { -value()? } else { value()? };
+                        }
+                        _ => (),
+                    }
+                }
+                let closed = number(70)?.is_some_and(|flag| flag as u16 & 1 == 1);
+                let ends = vertices
+                    .iter()
+                    .skip(1)
+                    .chain(vertices.first().filter(|_| closed && vertices.len() > 2));
+                vertices
+                    .iter()
+                    .zip(ends)
+                    .map(|(&(p0, bulge), &(p1, _))| segment(p0, p1, bulge))
+                    .collect()
+            }
+            _ => vec![],
+        };
+        Ok(figures
+            .into_iter()
+            .map(|figure| Self {
+                line,
+                layer: layer.clone(),
+                figure,
+            })
+            .collect())
+    }
+}
+
+/// 点 `p0` から `p1` まで、ふくらみ `bulge` の線分
+fn segment(p0: Point, p1: Point, bulge: f64) -> Figure {
+    if bulge == 0.0 {
+        return Figure::Straight(p0, p1);
+    }
+    // 弦の中点から、弦の左側に中心がある (ふくらみが負なら右側)
+    let (dx, dy) = (p1.x() - p0.x(), p1.y() - p0.y());
+    let offset = (1.0 - bulge * bulge) / (4.0 * bulge);
+    let c = Point::from((
+        (p0.x() + p1.x()) / 2.0 - dy * offset,
+        (p0.y() + p1.y()) / 2.0 + dx * offset,
+    ));
+    let r = dx.hypot(dy) * (1.0 + bulge * bulge) / (4.0 * bulge.abs());
+    let angle = |p: Point| Degree((p.y() - c.y()).atan2(p.x() - c.x()).to_degrees());
+    let (a0, a1) = match bulge > 0.0 {
+        true => (angle(p0), angle(p1)),
+        false => (angle(p1), angle(p0)),
+    };
+    Figure::Curve { c, r, a0, a1 }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        agent::buffer::Buffer,
+        transition::{
+            canvas::Point,
+            curve::{Radius, Subtension, Tangential, STRAIGHT},
+        },
+    };
+
+    use super::*;
+
+    /// グループコードと値の組
+    fn groups(buffer: &Buffer) -> Vec<(String, String)> {
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        let lines = text.lines().map(str::trim).collect::<Vec<_>>();
+        lines
+            .chunks(2)
+            .map(|pair| (pair[0].to_string(), pair[1].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn 連続した線をふくらみつきの折れ線にする() {
+        let buffer = Buffer::default();
+        let mut dxf = Write::new(buffer.clone());
+
+        // 直線1mと、半径-10m (左) の半円
+        let straight = Stroke::new(
+            STRAIGHT,
+            Subtension::from(1.0),
+            Point::from((0.0, 0.0)),
+            Tangential::from(0.0),
+        );
+        let arc = Stroke::new(
+            Radius(-10.0).into(),
+            Subtension::from(10.0 * PI),
+            straight.p1(),
+            straight.t1(),
+        );
+        dxf.strokes(&[straight, arc]).unwrap();
+        dxf.close().unwrap();
+
+        let groups = groups(&buffer);
+        let start = groups.iter().position(|(_, v)| v == "POLYLINE").unwrap();
+        let end = groups.iter().position(|(_, v)| v == "SEQEND").unwrap();
+        let vertices = groups[start..end]
+            .split(|(c, v)| c == "0" && v == "VERTEX")
+            .skip(1)
+            .map(|vertex| {
+                let value = |code: &str| {
+                    let (_, v) = vertex.iter().find(|(c, _)| c == code).unwrap();
+                    v.parse::<f64>().unwrap()
+                };
+                (value("10"), value("20"), value("42"))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(vertices[0], (0.0, 0.0, 0.0));
+        let (x, y, bulge) = vertices[1];
+        assert_eq!((x, y), (1.0, 0.0));
+        assert!((bulge - 1.0).abs() < 1e-9);
+        let (x, y, bulge) = vertices[2];
+        assert!((x - 1.0).abs() < 1e-9 && (y - 20.0).abs() < 1e-9);
+        assert_eq!(bulge, 0.0);
+    }
+
+    #[test]
+    fn 画層とファイルの終わり() {
+        let buffer = Buffer::default();
+        let mut dxf = Write::new(buffer.clone());
+        let style = Style {
+            layer: Some(10),
+            group: Some(1),
+            ..Default::default()
+        };
+        dxf.style(&style).unwrap();
+        dxf.straight(&Point::from((0.0, 0.0)), &Point::from((1.0, 2.0)))
+            .unwrap();
+        dxf.close().unwrap();
+
+        let groups = groups(&buffer);
+        let sections = groups
+            .windows(2)
+            .filter(|pair| pair[0] == ("0".to_string(), "SECTION".to_string()))
+            .map(|pair| pair[1].1.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(sections, ["HEADER", "TABLES", "ENTITIES"]);
+        assert_eq!(groups[3], ("1".to_string(), "AC1009".to_string()));
+        let layers = groups
+            .windows(2)
+            .filter(|pair| pair[0] == ("0".to_string(), "LAYER".to_string()))
+            .map(|pair| pair[1].1.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(layers, ["0", "1-A"]);
+        let line = groups.iter().position(|(_, v)| v == "LINE").unwrap();
+        assert_eq!(groups[line + 1], ("8".to_string(), "1-A".to_string()));
+        assert_eq!(
+            groups[groups.len() - 2..],
+            [
+                ("0".to_string(), "ENDSEC".to_string()),
+                ("0".to_string(), "EOF".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn 文字をエスケープする() {
+        assert_eq!(escape("R=600"), "R=600");
+        assert_eq!(escape("10‰ 上り"), "10\\U+2030 \\U+4E0A\\U+308A");
+    }
+
+    /// グループコードと値の組をDXF形式の文字列にする。
+    fn dxf(entities: &[(u16, &str)]) -> String {
+        [(0, "SECTION"), (2, "ENTITIES")]
+            .iter()
+            .chain(entities)
+            .chain(&[(0, "ENDSEC"), (0, "EOF")])
+            .map(|(code, value)| format!("{code:>3}\r\n{value}\r\n"))
+            .collect()
+    }
+
+    #[test]
+    fn ふくらみつきの折れ線を直線と円弧に分ける() {
+        // 直線1mと、半径-10m (左) の半円
+        let text = dxf(&[
+            (0, "LWPOLYLINE"),
+            (8, "自線"),
+            (90, "3"),
+            (70, "0"),
+            (10, "0"),
+            (20, "0"),
+            (10, "1"),
+            (20, "0"),
+            (42, "1"),
+            (10, "1"),
+            (20, "20"),
+            (0, "POINT"),
+            (8, "自線"),
+            (10, "0"),
+            (20, "0"),
+            (0, "LINE"),
+            (8, "他線"),
+            (10, "0"),
+            (20, "3.8"),
+            (11, "1"),
+            (21, "3.8"),
+        ]);
+        let read = Read::parse(&text).unwrap();
+
+        let figures = read
+            .entities(Some("自線"))
+            .map(|entity| entity.figure.clone())
+            .collect::<Vec<_>>();
+        let p = |x: f64, y: f64| Point::from((x, y));
+        assert_eq!(figures.len(), 3);
+        assert_eq!(figures[0], Figure::Straight(p(0.0, 0.0), p(1.0, 0.0)));
+        let Figure::Curve { c, r, a0, a1 } = figures[1] else {
+            panic!("{:?}", figures[1]);
+        };
+        assert!((c.x() - 1.0).abs() < 1e-9 && (c.y() - 10.0).abs() < 1e-9);
+        assert!((r - 10.0).abs() < 1e-9);
+        assert!((a0.0 + 90.0).abs() < 1e-9 && (a1.0 - 90.0).abs() < 1e-9);
+        assert_eq!(figures[2], Figure::Dot(p(0.0, 0.0)));
+
+        let other = read.entities(Some("他線")).collect::<Vec<_>>();
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].line, 36);
+        assert_eq!(read.entities(None).count(), 4);
+    }
+
+    #[test]
+    fn 書き出した折れ線を読み込む() {
+        let buffer = Buffer::default();
+        let mut dxf = Write::new(buffer.clone());
+        // 半径10m (右) の1/4円
+        let arc = Stroke::new(
+            Radius(10.0).into(),
+            Subtension::from(5.0 * PI),
+            Point::from((0.0, 0.0)),
+            Tangential::from(0.0),
+        );
+        dxf.strokes(&[arc]).unwrap();
+        dxf.close().unwrap();
+
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        let read = Read::parse(&text).unwrap();
+        let entities = read.entities(None).collect::<Vec<_>>();
+        assert_eq!(entities.len(), 1);
+        let Figure::Curve { c, r, a0, a1 } = entities[0].figure else {
+            panic!("{:?}", entities[0].figure);
+        };
+        assert!(c.x().abs() < 1e-9 && (c.y() + 10.0).abs() < 1e-9);
+        assert!((r - 10.0).abs() < 1e-9);
+        assert!(a0.0.abs() < 1e-9 && (a1.0 - 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn グループコードが整数でなければエラー() {
+        let e = Read::parse("  0\nSECTION\nfoo\nENTITIES\n").err().unwrap();
+        assert_eq!(e.to_string(), "3行目のグループコードが整数ではありません");
+    }
+}
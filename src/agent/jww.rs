@@ -1,15 +1,21 @@
 use std::{
+    collections::HashMap,
     fmt::Display,
     fs::{File, OpenOptions},
     io::{self, BufRead, BufReader},
     path::{Path, PathBuf},
 };
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use encoding_rs::SHIFT_JIS;
 use encoding_rs_io::DecodeReaderBytesBuilder;
 
-use crate::transition::unit::{Deg, Meter, Vector};
+use super::sheet::Sheet;
+use crate::transition::{
+    canvas::Point,
+    curve::Degree,
+    unit::{Deg, Meter, Vector},
+};
 
 /// 入出力用の座標ファイル。
 ///
@@ -37,7 +43,7 @@ impl JwcTemp {
                 path.as_ref().display()
             )
         })?;
-        Ok(Write { file })
+        Ok(Write::new(file))
     }
 }
 
@@ -48,9 +54,41 @@ pub struct Read {
 
 impl Read {
     /// トラック名
-    pub fn track_name(&mut self) -> &str {
-        let given = self.cache().track_name.as_ref();
-        given.map_or(" ", |s| s.as_str())
+    pub fn track_name(&mut self) -> Result<&str> {
+        Ok(self.value("トラック名")?.unwrap_or(" "))
+    }
+
+    /// 出力始点の距離程
+    ///
+    /// 無指定なら0とする。
+    pub fn start_distance(&mut self) -> Result<f64> {
+        match self.value("始点距離程")? {
+            None | Some("") => Ok(0.0),
+            Some(s) => s
+                .parse()
+                .ok()
+                .filter(|z: &f64| z.is_finite())
+                .context("始点距離程を数値で入力してください"),
+        }
+    }
+
+    /// ダイアログで入力された値
+    ///
+    /// 前後の空白は取り除かれる。
+    pub fn value(&mut self, key: &str) -> Result<Option<&str>> {
+        Ok(self.cache()?.values.get(key).map(String::as_str))
+    }
+
+    /// `n` 番目に指示された点
+    pub fn point(&mut self, n: u8) -> Result<Option<Point>> {
+        Ok(self.cache()?.points.get(&n).copied())
+    }
+
+    /// 選択された図形
+    ///
+    /// 読み取れない図形の行があればエラー。
+    pub fn shapes(&mut self) -> Result<&[Shape]> {
+        self.cache()?.shapes()
     }
 
     /// 作業中のファイルがあるディレクトリ
@@ -59,15 +97,15 @@ impl Read {
 
         let dir = Path::new(path)
             .parent()
-            .with_context(|| format!("{} と同じフォルダに出力できません", path))?;
+            .with_context(|| format!("{path} と同じフォルダに出力できません"))?;
 
         Ok(dir.to_path_buf())
     }
 
     /// 作業中のファイルパス
-    fn project_path(&mut self) -> Result<&String> {
+    pub fn project_path(&mut self) -> Result<&String> {
         let path = self
-            .cache()
+            .cache()?
             .project_path
             .as_ref()
             .context("JWC_TEMPファイルにパスが出力されていません")?;
@@ -80,64 +118,353 @@ impl Read {
         Ok(path)
     }
 
-    fn cache(&mut self) -> &Cache {
+    fn cache(&mut self) -> Result<&Cache> {
         if self.cache.is_none() {
-            self.cache = Some(self.read());
+            self.cache = Some(self.read()?);
         }
-        self.cache.as_ref().unwrap()
+        Ok(self.cache.as_ref().unwrap())
     }
 
-    fn read(&self) -> Cache {
+    fn read(&self) -> Result<Cache> {
         let decoder = DecodeReaderBytesBuilder::new()
             .encoding(Some(SHIFT_JIS))
             .build(&self.file);
-        BufReader::new(decoder)
-            .lines()
-            .filter_map(|l| l.ok())
-            .collect::<Cache>()
+        Cache::parse(BufReader::new(decoder).lines())
+    }
+}
+
+/// 座標ファイル中の図形
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    /// 行番号
+    pub line: usize,
+
+    /// 線の属性
+    pub pen: Pen,
+
+    /// 図形
+    pub figure: Figure,
+}
+
+/// 図形
+#[derive(Debug, Clone, PartialEq)]
+pub enum Figure {
+    /// 直線
+    Straight(Point, Point),
+
+    /// 円弧
+    ///
+    /// 始点角から終点角まで反時計回りに描かれる。
+    Curve {
+        /// 中心点
+        c: Point,
+
+        /// 半径
+        r: f64,
+
+        /// 始点角
+        a0: Degree,
+
+        /// 終点角
+        a1: Degree,
+    },
+
+    /// 円全周
+    Circle {
+        /// 中心点
+        c: Point,
+
+        /// 半径
+        r: f64,
+    },
+
+    /// 楕円弧
+    ///
+    /// 始点角・終点角は傾きを含まない。
+    Ellipse {
+        /// 中心点
+        c: Point,
+
+        /// 長軸の半径
+        r: f64,
+
+        /// 始点角
+        a0: Degree,
+
+        /// 終点角
+        a1: Degree,
+
+        /// 扁平率 (短軸 / 長軸)
+        flat: f64,
+
+        /// 長軸の傾き
+        tilt: Degree,
+    },
+
+    /// 点
+    Dot(Point),
+
+    /// 文字
+    Text {
+        /// 始点
+        p: Point,
+
+        /// 文字列の方向
+        dir: Degree,
+
+        /// 文字列
+        s: String,
+    },
+}
+
+/// 線の属性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pen {
+    /// 線色 (`lc`)
+    pub color: u16,
+
+    /// 線種 (`lt`)
+    pub line_type: u16,
+
+    /// レイヤ (`ly`) 0～15
+    pub layer: u8,
+
+    /// レイヤグループ (`lg`) 0～15
+    pub group: u8,
+}
+
+impl Default for Pen {
+    fn default() -> Self {
+        Self {
+            color: 1,
+            line_type: 1,
+            layer: 0,
+            group: 0,
+        }
     }
 }
 
-#[derive(Default)]
+/// 書き込む線の属性
+///
+/// `None` の属性は Jw_cad の現在の設定のまま。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    /// 線色 (`lc`)
+    pub color: Option<u16>,
+
+    /// 線種 (`lt`)
+    pub line_type: Option<u16>,
+
+    /// レイヤ (`ly`) 0～15
+    pub layer: Option<u8>,
+
+    /// レイヤグループ (`lg`) 0～15
+    pub group: Option<u8>,
+}
+
+impl Style {
+    /// 他軌道 `n` 本を1本ずつ描く属性
+    ///
+    /// 自軌道のレイヤの次から順にレイヤを変え、自軌道のレイヤには戻らない。
+    /// レイヤが足りなければエラー。
+    pub fn others(&self, n: usize) -> Result<Vec<Self>> {
+        ensure!(n < 16, "他軌道が{n}本あり、レイヤが足りません (15本まで)");
+        let own = self.layer.unwrap_or(0);
+        Ok((1..=n as u8)
+            .map(|i| Self {
+                layer: Some((own + i) % 16),
+                ..*self
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Cache {
-    track_name: Option<String>,
     project_path: Option<String>,
+    values: HashMap<String, String>,
+    points: HashMap<u8, Point>,
+    shapes: Vec<Shape>,
+
+    /// 最初の読み取れない図形の行番号と行
+    malformed: Option<(usize, String)>,
 }
 
-impl FromIterator<String> for Cache {
-    fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
+impl Cache {
+    /// 座標ファイルの各行を読み取る。
+    ///
+    /// 読み取れない行は飛ばす。図形の行なら、図形を使うときにエラーにする。
+    fn parse(lines: impl IntoIterator<Item = io::Result<String>>) -> Result<Self> {
         let mut cache = Self::default();
-        for line in iter {
-            if let Some(s) = line.strip_prefix("file=") {
-                cache.project_path = Some(s.to_string());
-            } else if let Some(a) = line.strip_prefix("ci ") {
-                let v = a.split(" ").collect::<Vec<_>>();
-                if let [_a, _b, _c, _d, _e, _f, _g] = v.as_slice() {
-                    // cache.curve.push()
-                }
-            } else if let Some(_straight) = line.strip_prefix(" ") {
-                //
-            } else if let Some(s) = line.strip_prefix("/トラック名:") {
-                cache.track_name = Some(s.to_string());
-            } else if let Some(_z0) = line.strip_prefix("/始点距離程:") {
-                //
+        let mut pen = Pen::default();
+        for (i, line) in lines.into_iter().enumerate() {
+            let line = line.context("JWC_TEMPファイルを読み込めませんでした")?;
+            let number = i + 1;
+            if cache.parse_line(&line, number, &mut pen).is_none()
+                && is_figure(&line)
+                && cache.malformed.is_none()
+            {
+                cache.malformed = Some((number, line));
+            }
+        }
+        Ok(cache)
+    }
+
+    /// 図形
+    ///
+    /// 読み取れない図形の行があれば、行番号つきのエラー。
+    fn shapes(&self) -> Result<&[Shape]> {
+        if let Some((number, line)) = &self.malformed {
+            bail!("JWC_TEMPファイル {number}行目を読み取れません: {line}");
+        }
+        Ok(&self.shapes)
+    }
+
+    /// 1行を読み取る。
+    ///
+    /// 数値が不正なら `None`
+    fn parse_line(&mut self, line: &str, number: usize, pen: &mut Pen) -> Option<()> {
+        let figure = if let Some(s) = line.strip_prefix("file=") {
+            self.project_path = Some(s.to_string());
+            None
+        } else if let Some((key, value)) = line.strip_prefix('/').and_then(|s| s.split_once(':')) {
+            self.values
+                .insert(key.to_string(), value.trim().to_string());
+            None
+        } else if let Some(s) = line.strip_prefix("ci ") {
+            Some(match floats(s)?.as_slice() {
+                [cx, cy, r] => Figure::Circle {
+                    c: Point::from((*cx, *cy)),
+                    r: *r,
+                },
+                [cx, cy, r, a0, a1] | [cx, cy, r, a0, a1, 1.0, 0.0] => Figure::Curve {
+                    c: Point::from((*cx, *cy)),
+                    r: *r,
+                    a0: Degree(*a0),
+                    a1: Degree(*a1),
+                },
+                [cx, cy, r, a0, a1, 1.0, tilt] => Figure::Curve {
+                    c: Point::from((*cx, *cy)),
+                    r: *r,
+                    a0: Degree(a0 + tilt),
+                    a1: Degree(a1 + tilt),
+                },
+                [cx, cy, r, a0, a1, flat, tilt] => Figure::Ellipse {
+                    c: Point::from((*cx, *cy)),
+                    r: *r,
+                    a0: Degree(*a0),
+                    a1: Degree(*a1),
+                    flat: *flat,
+                    tilt: Degree(*tilt),
+                },
+                _ => return None,
+            })
+        } else if let Some(s) = line.strip_prefix("pt ") {
+            match floats(s)?.as_slice() {
+                [x, y, ..] => Some(Figure::Dot(Point::from((*x, *y)))),
+                _ => return None,
+            }
+        } else if let Some(s) = line.strip_prefix("ch ") {
+            let mut v = s.trim_start().splitn(5, ' ');
+            let [x, y, dx, dy] = [(); 4].map(|_| v.next().and_then(|f| f.parse::<f64>().ok()));
+            let s = v.next().unwrap_or("").to_string();
+            let (x, y, dx, dy) = (x?, y?, dx?, dy?);
+            let dir = Degree(dy.atan2(dx).to_degrees());
+            Some(Figure::Text {
+                p: Point::from((x, y)),
+                dir,
+                s,
+            })
+        } else if let Some(s) = line.strip_prefix("hp") {
+            let (n, xy) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+            match floats(xy.trim_start_matches(['-', '@']))?.as_slice() {
+                [x, y] => self.points.insert(n.parse().ok()?, Point::from((*x, *y))),
+                _ => return None,
+            };
+            None
+        } else if let Some(s) = line.strip_prefix("lc") {
+            pen.color = s.trim().parse().ok()?;
+            None
+        } else if let Some(s) = line.strip_prefix("lt") {
+            pen.line_type = s.trim().parse().ok()?;
+            None
+        } else if let Some(s) = line.strip_prefix("ly") {
+            pen.layer = u8::from_str_radix(s.trim(), 16).ok().filter(|n| *n < 16)?;
+            None
+        } else if let Some(s) = line.strip_prefix("lg") {
+            pen.group = u8::from_str_radix(s.trim(), 16).ok().filter(|n| *n < 16)?;
+            None
+        } else if line.starts_with(' ') && !line.trim().is_empty() {
+            match floats(line)?.as_slice() {
+                [x0, y0, x1, y1] => Some(Figure::Straight(
+                    Point::from((*x0, *y0)),
+                    Point::from((*x1, *y1)),
+                )),
+                _ => return None,
             }
+        } else {
+            // 読み取る必要のない行
+            None
+        };
+
+        if let Some(figure) = figure {
+            self.shapes.push(Shape {
+                line: number,
+                pen: *pen,
+                figure,
+            });
         }
-        cache
+        Some(())
     }
 }
 
+/// 図形 (直線・円・点・文字) の行なら `true`
+fn is_figure(line: &str) -> bool {
+    ["ci ", "pt ", "ch "].iter().any(|p| line.starts_with(p))
+        || line.starts_with(' ') && !line.trim().is_empty()
+}
+
+/// 空白区切りの数値を読み取る。
+///
+/// 数値でないものがあれば `None`
+fn floats(s: &str) -> Option<Vec<f64>> {
+    s.split_whitespace().map(|f| f.parse().ok()).collect()
+}
+
 pub struct Write {
-    file: File,
+    file: Box<dyn io::Write>,
 }
 
 impl Write {
+    /// 座標ファイルの形式で `out` に出力する。
+    pub fn new(out: impl io::Write + 'static) -> Self {
+        Self {
+            file: Box::new(out),
+        }
+    }
+
+    /// 文字列と改行を出力する。
+    fn puts<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
+        // TODO:
+        // SHIFT_JISではなくCP932にしたほうがいい。
+        // - https://crates.io/crates/codepage
+        // - https://crates.io/search?q=windows%20encoding&sort=downloads
+        let (sjis, _, _) = SHIFT_JIS.encode(s.as_ref());
+        for bytes in [&sjis[..], b"\r\n"] {
+            io::Write::write_all(&mut self.file, bytes)
+                .context("JWC_TEMP.TXTへの書き込みに失敗しました。")?;
+        }
+        Ok(())
+    }
+}
+
+impl Sheet for Write {
     /// エラー `e` を書き込む。
     ///
     /// - 最初のエラーのみが表示される。
     /// - エラーがあれば、エラー以外の座標などはすべて無視される。
-    pub fn error(&mut self, e: &impl Display) -> Result<()> {
-        self.puts(&format!("he{}", e))
+    fn error(&mut self, e: &impl Display) -> Result<()> {
+        self.puts(format!("he{e}"))
     }
 
     /// 注意を出力する。
@@ -145,12 +472,29 @@ impl Write {
     /// 最後の注意のみ表示される。
     ///
     /// 座標の間に出力すると、座標が途切れてしまう。
-    pub fn notice<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
+    fn notice<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
         self.puts(format!("h#{}", s.as_ref()))
     }
 
+    /// 以降に出力する線の属性を変更する。
+    fn style(&mut self, style: &Style) -> Result<()> {
+        if let Some(group) = style.group {
+            self.puts(format!("lg{group:x}"))?;
+        }
+        if let Some(layer) = style.layer {
+            self.puts(format!("ly{layer:x}"))?;
+        }
+        if let Some(color) = style.color {
+            self.puts(format!("lc{color}"))?;
+        }
+        if let Some(line_type) = style.line_type {
+            self.puts(format!("lt{line_type}"))?;
+        }
+        Ok(())
+    }
+
     /// 曲線を出力する。
-    pub fn curve(
+    fn curve(
         &mut self,
         c: &impl Vector,
         r: &impl Meter,
@@ -165,21 +509,142 @@ impl Write {
     }
 
     /// 直線を出力する。
-    pub fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()> {
+    fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()> {
         self.puts(format!("{} {} {} {}", p0.x(), p0.y(), p1.x(), p1.y()))
     }
 
-    /// 文字列と改行を出力する。
-    fn puts<T: AsRef<str>>(&mut self, s: T) -> Result<()> {
-        // TODO:
-        // SHIFT_JISではなくCP932にしたほうがいい。
-        // - https://crates.io/crates/codepage
-        // - https://crates.io/search?q=windows%20encoding&sort=downloads
-        let (sjis, _, _) = SHIFT_JIS.encode(s.as_ref());
-        for bytes in [&sjis[..], b"\r\n"] {
-            io::Write::write_all(&mut self.file, bytes)
-                .context("JWC_TEMP.TXTへの書き込みに失敗しました。")?;
-        }
-        Ok(())
+    /// 文字を出力する。
+    ///
+    /// 点 `p` から方向 `dir` に向かって書かれる。
+    fn text<T: AsRef<str>>(&mut self, p: &impl Vector, dir: &impl Deg, s: T) -> Result<()> {
+        let dir = dir.deg().to_radians();
+        let (dx, dy) = (dir.cos(), dir.sin());
+        self.puts(format!("ch {} {} {dx} {dy} {}", p.x(), p.y(), s.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn 他軌道は自軌道のレイヤを飛ばす() {
+        let style = Style {
+            layer: Some(14),
+            ..Default::default()
+        };
+        let layers = style.others(15).unwrap();
+        let layers = layers.iter().map(|s| s.layer.unwrap()).collect::<Vec<_>>();
+        assert_eq!(layers[..3], [15, 0, 1]);
+        assert!(!layers.contains(&14));
+
+        let e = style.others(16).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "他軌道が16本あり、レイヤが足りません (15本まで)"
+        );
+    }
+
+    fn parse(text: &str) -> Result<Cache> {
+        Cache::parse(text.lines().map(|l| Ok(l.to_string())))
+    }
+
+    #[test]
+    fn 図形と線の属性を読み取る() {
+        let cache = parse(
+            "hq\n\
+             file=C:\\jww\\foo.jww\n\
+             lg1\n\
+             lyf\n\
+             lc3\n\
+             lt2\n \
+             0 0 10 0\n\
+             ci 5 5 2\n\
+             ci 0 0 10 0 90\n\
+             ci 0 0 10 0 90 1 30\n\
+             ci 0 0 10 0 90 0.5 30\n\
+             pt 1 2\n\
+             ch 0 0 3 4 BTC 100m\n\
+             hp1- 7 8\n\
+             /トラック名:上り1 \n",
+        )
+        .unwrap();
+
+        assert_eq!(cache.project_path.as_deref(), Some("C:\\jww\\foo.jww"));
+        assert_eq!(cache.values["トラック名"], "上り1");
+        assert_eq!(cache.points[&1], Point::from((7.0, 8.0)));
+
+        let pen = Pen {
+            color: 3,
+            line_type: 2,
+            layer: 15,
+            group: 1,
+        };
+        assert!(cache.shapes.iter().all(|shape| shape.pen == pen));
+        assert_eq!(cache.shapes[0].line, 7);
+
+        let figures = cache
+            .shapes
+            .into_iter()
+            .map(|s| s.figure)
+            .collect::<Vec<_>>();
+        let c = Point::from((0.0, 0.0));
+        assert_eq!(
+            figures,
+            vec![
+                Figure::Straight(c, Point::from((10.0, 0.0))),
+                Figure::Circle {
+                    c: Point::from((5.0, 5.0)),
+                    r: 2.0
+                },
+                Figure::Curve {
+                    c,
+                    r: 10.0,
+                    a0: Degree(0.0),
+                    a1: Degree(90.0)
+                },
+                Figure::Curve {
+                    c,
+                    r: 10.0,
+                    a0: Degree(30.0),
+                    a1: Degree(120.0)
+                },
+                Figure::Ellipse {
+                    c,
+                    r: 10.0,
+                    a0: Degree(0.0),
+                    a1: Degree(90.0),
+                    flat: 0.5,
+                    tilt: Degree(30.0)
+                },
+                Figure::Dot(Point::from((1.0, 2.0))),
+                Figure::Text {
+                    p: c,
+                    dir: Degree(4.0_f64.atan2(3.0).to_degrees()),
+                    s: "BTC 100m".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn 読み取れない図形は行番号つきのエラー() {
+        let cache = parse("file=foo.jww\nci 0 0 x\n 0 0 1 y\n").unwrap();
+        assert_eq!(cache.project_path.as_deref(), Some("foo.jww"));
+        let e = cache.shapes().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "JWC_TEMPファイル 2行目を読み取れません: ci 0 0 x"
+        );
+    }
+
+    #[test]
+    fn 図形でない読み取れない行は飛ばす() {
+        let cache = parse("lyZ\nhp1 x\nzz 1 2\n/L0:100\n 0 0 1 0\n").unwrap();
+        assert_eq!(cache.values.get("L0").map(String::as_str), Some("100"));
+        assert!(cache.points.is_empty());
+        let shapes = cache.shapes().unwrap();
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].pen, Pen::default());
     }
 }
@@ -1,3 +1,10 @@
 pub mod bat;
+#[cfg(test)]
+pub mod buffer;
 pub mod bve;
+pub mod cli;
+pub mod csv;
+pub mod dxf;
 pub mod jww;
+pub mod sheet;
+pub mod svg;
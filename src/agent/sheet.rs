@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+
+use crate::transition::{
+    canvas::Stroke,
+    unit::{Deg, Meter, Vector},
+};
+
+use super::jww::Style;
+
+/// 図形の出力先
+///
+/// 座標ファイルのほか、コマンドラインから実行したときの標準出力など。
+pub trait Sheet {
+    /// エラー `e` を出力する。
+    fn error(&mut self, e: &impl Display) -> Result<()>;
+
+    /// 注意を出力する。
+    fn notice<T: AsRef<str>>(&mut self, s: T) -> Result<()>;
+
+    /// 以降に出力する線の属性を変更する。
+    fn style(&mut self, style: &Style) -> Result<()>;
+
+    /// 曲線を出力する。
+    fn curve(
+        &mut self,
+        c: &impl Vector,
+        r: &impl Meter,
+        a0: &impl Deg,
+        a1: &impl Deg,
+    ) -> Result<()>;
+
+    /// 直線を出力する。
+    fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()>;
+
+    /// 文字を出力する。
+    ///
+    /// 点 `p` から方向 `dir` に向かって書かれる。
+    fn text<T: AsRef<str>>(&mut self, p: &impl Vector, dir: &impl Deg, s: T) -> Result<()>;
+
+    /// 連続した線を出力する。
+    ///
+    /// 既定では1本ずつ出力する。半径が非常に大きい円弧は直線で出力する。
+    fn strokes(&mut self, strokes: &[Stroke]) -> Result<()> {
+        for stroke in strokes {
+            match stroke
+                .center()
+                .zip(stroke.r().filter(|r| r.meter().abs() < 100_000.0))
+            {
+                Some((c, r)) => self.curve(&c, &r, &stroke.a0(), &stroke.a1())?,
+                None => self.straight(&stroke.p0(), &stroke.p1())?,
+            }
+        }
+        Ok(())
+    }
+
+    /// 出力を終える。
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,682 @@
+use std::{
+    f64::consts::{PI, TAU},
+    fmt::{Display, Write as _},
+    io,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    track::polyline::{Polyline, Stroke},
+    transition::{
+        canvas::{self, Point},
+        unit::{Deg, Meter, Rad, Vector},
+    },
+};
+
+use super::{jww::Style, sheet::Sheet};
+
+/// 図の幅 (px)
+const WIDTH: f64 = 800.0;
+
+/// 図の余白 (px)
+const MARGIN: f64 = 40.0;
+
+/// 平面図の最大の高さ (px)
+const PLAN_HEIGHT: f64 = 480.0;
+
+/// 曲率図の高さ (px)
+const DIAGRAM_HEIGHT: f64 = 160.0;
+
+/// 目盛りの長さ (px)
+const TICK: f64 = 6.0;
+
+/// 軌道ごとの色
+const COLORS: [&str; 5] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd", "#ff7f0e"];
+
+/// 同一点とみなす距離 (m)
+const TOLERANCE: f64 = 0.001;
+
+/// SVG形式のプレビュー
+///
+/// 上段に平面図 (線形と距離程の目盛り)、下段に曲率図 (距離程に対する曲率) を描く。
+/// 図の範囲が決まらないと書けないので、閉じるときにまとめて出力する。
+pub struct Write {
+    file: Box<dyn io::Write>,
+
+    /// 距離程をもつ軌道
+    tracks: Vec<Track>,
+
+    /// 単独の線 (平面図のみ)
+    lines: Vec<Stroke>,
+
+    /// 文字 (位置, 方向 (度), 文字列)
+    texts: Vec<(Point, f64, String)>,
+
+    /// 次に出力する連続した線の始点距離程 (m)
+    z0: f64,
+}
+
+/// 距離程をもつ軌道
+struct Track {
+    /// 凡例の名前
+    name: String,
+
+    /// 始点距離程 (m)
+    ///
+    /// `None` なら曲率図と目盛りを描かない。
+    z0: Option<f64>,
+
+    /// 線
+    strokes: Vec<Stroke>,
+}
+
+impl Write {
+    /// SVG形式で `out` に出力する。
+    pub fn new(out: impl io::Write + 'static) -> Self {
+        Self {
+            file: Box::new(out),
+            tracks: vec![],
+            lines: vec![],
+            texts: vec![],
+            z0: 0.0,
+        }
+    }
+
+    /// 以降に出力する連続した線の始点距離程を `z0` (m) とする。
+    pub fn start(&mut self, z0: f64) {
+        self.z0 = z0;
+    }
+
+    /// 軌道 `polyline` を名前 `name` で追加する。
+    ///
+    /// 始点距離程 `z0` が `None` なら平面図だけに描く。
+    pub fn polyline(&mut self, name: &str, polyline: &Polyline, z0: Option<f64>) {
+        self.tracks.push(Track {
+            name: name.to_string(),
+            z0,
+            strokes: polyline.iter().copied().collect(),
+        });
+    }
+
+    /// SVG文書
+    fn render(&self) -> String {
+        let plan = Plan::fit(self);
+        let diagram = Diagram::fit(&self.tracks, plan.height);
+        let height = plan.height
+            + diagram
+                .as_ref()
+                .map_or(0.0, |_| DIAGRAM_HEIGHT + 2.0 * MARGIN);
+
+        let mut s = String::new();
+        let _ = writeln!(
+            s,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{}" viewBox="0 0 {WIDTH} {}" font-family="sans-serif" font-size="10">"#,
+            px(height),
+            px(height)
+        );
+        let _ = writeln!(s, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+        // 平面図
+        for stroke in &self.lines {
+            let _ = writeln!(
+                s,
+                r#"<path d="{}" fill="none" stroke="black"/>"#,
+                plan.path(&[*stroke])
+            );
+        }
+        for (i, track) in self.tracks.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            let _ = writeln!(
+                s,
+                r#"<path d="{}" fill="none" stroke="{color}" stroke-width="1.5"/>"#,
+                plan.path(&track.strokes)
+            );
+            for (p, t, z) in ticks(track) {
+                let (x, y) = plan.map(p);
+                let (nx, ny) = (-t.sin(), -t.cos());
+                let _ = writeln!(
+                    s,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{color}"/><text x="{}" y="{}" fill="{color}">{}</text>"#,
+                    px(x - nx * TICK),
+                    px(y - ny * TICK),
+                    px(x + nx * TICK),
+                    px(y + ny * TICK),
+                    px(x + nx * TICK * 2.0),
+                    px(y + ny * TICK * 2.0),
+                    z
+                );
+            }
+            let _ = writeln!(
+                s,
+                r#"<text x="{}" y="{}" fill="{color}">{}</text>"#,
+                px(MARGIN),
+                px(MARGIN / 2.0 + 12.0 * i as f64),
+                escape(&track.name)
+            );
+        }
+        for (p, dir, text) in &self.texts {
+            let (x, y) = plan.map(*p);
+            let _ = writeln!(
+                s,
+                r#"<text x="{}" y="{}" transform="rotate({} {} {})">{}</text>"#,
+                px(x),
+                px(y),
+                px(-dir),
+                px(x),
+                px(y),
+                escape(text)
+            );
+        }
+
+        // 曲率図
+        if let Some(diagram) = diagram {
+            s.push_str(&diagram.render(&self.tracks));
+        }
+
+        s.push_str("</svg>\n");
+        s
+    }
+}
+
+impl Sheet for Write {
+    /// エラーは図形ではないので、出力せずに返す。
+    fn error(&mut self, e: &impl Display) -> Result<()> {
+        bail!("{e}")
+    }
+
+    /// 注意は図形ではないので出力しない。
+    fn notice<T: AsRef<str>>(&mut self, _: T) -> Result<()> {
+        Ok(())
+    }
+
+    /// 線の属性は出力しない。
+    fn style(&mut self, _: &Style) -> Result<()> {
+        Ok(())
+    }
+
+    fn curve(
+        &mut self,
+        c: &impl Vector,
+        r: &impl Meter,
+        a0: &impl Deg,
+        a1: &impl Deg,
+    ) -> Result<()> {
+        let a0 = a0.deg().to_radians();
+        self.lines.push(Stroke::Curve {
+            c: Point::from((c.x(), c.y())),
+            r: r.meter().abs(),
+            a0,
+            sweep: (a1.deg().to_radians() - a0).rem_euclid(TAU),
+        });
+        Ok(())
+    }
+
+    fn straight(&mut self, p0: &impl Vector, p1: &impl Vector) -> Result<()> {
+        self.lines.push(Stroke::Straight {
+            p0: Point::from((p0.x(), p0.y())),
+            p1: Point::from((p1.x(), p1.y())),
+        });
+        Ok(())
+    }
+
+    fn text<T: AsRef<str>>(&mut self, p: &impl Vector, dir: &impl Deg, s: T) -> Result<()> {
+        let p = Point::from((p.x(), p.y()));
+        self.texts.push((p, dir.deg(), s.as_ref().to_string()));
+        Ok(())
+    }
+
+    /// 連続した線を軌道として描く。
+    ///
+    /// 前の軌道の終点から続いていれば、同じ軌道として距離程を引き継ぐ。
+    fn strokes(&mut self, strokes: &[canvas::Stroke]) -> Result<()> {
+        let Some(first) = strokes.first() else {
+            return Ok(());
+        };
+        let strokes = strokes.iter().map(convert);
+        match self.tracks.last_mut() {
+            Some(track)
+                if track.z0.is_some()
+                    && track
+                        .strokes
+                        .last()
+                        .is_some_and(|last| last.p1().distance(first.p0()) < TOLERANCE) =>
+            {
+                track.strokes.extend(strokes)
+            }
+            _ => {
+                let name = format!("線形{}", self.tracks.len() + 1);
+                self.tracks.push(Track {
+                    name,
+                    z0: Some(self.z0),
+                    strokes: strokes.collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        let svg = self.render();
+        self.file
+            .write_all(svg.as_bytes())
+            .and_then(|_| self.file.flush())
+            .context("SVGの書き込みに失敗しました")
+    }
+}
+
+/// 平面図の座標変換
+struct Plan {
+    /// 図形の範囲の左上 (m)
+    x0: f64,
+    y1: f64,
+
+    /// 縮尺 (px/m)
+    scale: f64,
+
+    /// 高さ (px)
+    height: f64,
+}
+
+impl Plan {
+    /// 全ての図形が収まるように縮尺を決める。
+    fn fit(svg: &Write) -> Self {
+        let points = svg
+            .tracks
+            .iter()
+            .flat_map(|track| &track.strokes)
+            .chain(&svg.lines)
+            .flat_map(|stroke| (0..=16).map(|i| stroke.at(stroke.length() * i as f64 / 16.0).0))
+            .chain(svg.texts.iter().map(|(p, _, _)| *p))
+            .collect::<Vec<_>>();
+        let bound = |f: fn(&Point) -> f64| {
+            let v = points.iter().map(f);
+            let min = v.clone().fold(f64::INFINITY, f64::min);
+            let max = v.fold(f64::NEG_INFINITY, f64::max);
+            match min <= max {
+                true => (min, max),
+                false => (0.0, 0.0),
+            }
+        };
+        let (x0, x1) = bound(Point::x);
+        let (y0, y1) = bound(Point::y);
+        let width = WIDTH - 2.0 * MARGIN;
+        let scale = match (x1 - x0, y1 - y0) {
+            (w, h) if w > 0.0 && h > 0.0 => (width / w).min(PLAN_HEIGHT / h),
+            (w, _) if w > 0.0 => width / w,
+            (_, h) if h > 0.0 => PLAN_HEIGHT / h,
+            _ => 1.0,
+        };
+        Self {
+            x0,
+            y1,
+            scale,
+            height: (y1 - y0) * scale + 2.0 * MARGIN,
+        }
+    }
+
+    /// 図面上の点 `p` の画面上の位置 (px)
+    ///
+    /// 画面は下向きが正。
+    fn map(&self, p: Point) -> (f64, f64) {
+        (
+            MARGIN + (p.x() - self.x0) * self.scale,
+            MARGIN + (self.y1 - p.y()) * self.scale,
+        )
+    }
+
+    /// 線を繋いだパス
+    fn path(&self, strokes: &[Stroke]) -> String {
+        let mut d = String::new();
+        let mut pen = None;
+        for stroke in strokes {
+            let (x0, y0) = self.map(stroke.p0());
+            if pen.is_none_or(|p: Point| p.distance(stroke.p0()) >= TOLERANCE) {
+                let _ = write!(d, "M{} {}", px(x0), px(y0));
+            }
+            let (x1, y1) = self.map(stroke.p1());
+            match *stroke {
+                Stroke::Straight { .. } => {
+                    let _ = write!(d, "L{} {}", px(x1), px(y1));
+                }
+                Stroke::Curve { r, sweep, .. } => {
+                    // 画面は上下が反転するので、反時計回りは時計回り (1) になる
+                    let r = px(r * self.scale);
+                    let large = (sweep.abs() > PI) as u8;
+                    let clockwise = (sweep > 0.0) as u8;
+                    let _ = write!(d, "A{r} {r} 0 {large} {clockwise} {} {}", px(x1), px(y1));
+                }
+            }
+            pen = Some(stroke.p1());
+        }
+        d
+    }
+}
+
+/// 曲率図の座標変換
+struct Diagram {
+    /// 上端 (px)
+    top: f64,
+
+    /// 距離程の範囲 (m)
+    z0: f64,
+    z1: f64,
+
+    /// 曲率の絶対値の最大 (1/m)
+    k: f64,
+}
+
+impl Diagram {
+    /// 距離程をもつ軌道が全て収まるように範囲を決める。
+    ///
+    /// 距離程をもつ軌道がなければ `None`
+    fn fit(tracks: &[Track], top: f64) -> Option<Self> {
+        let ranges = tracks
+            .iter()
+            .filter_map(|track| track.z0.map(|z0| (z0, z0 + length(&track.strokes))))
+            .collect::<Vec<_>>();
+        let z0 = ranges.iter().map(|r| r.0).reduce(f64::min)?;
+        let z1 = ranges.iter().map(|r| r.1).fold(z0, f64::max);
+        let k = tracks
+            .iter()
+            .filter(|track| track.z0.is_some())
+            .flat_map(|track| &track.strokes)
+            .map(|stroke| stroke.k().abs())
+            .fold(0.0, f64::max);
+        Some(Self { top, z0, z1, k })
+    }
+
+    /// 距離程 `z` と曲率 `k` の画面上の位置 (px)
+    fn map(&self, z: f64, k: f64) -> (f64, f64) {
+        let x = match self.z1 > self.z0 {
+            true => MARGIN + (z - self.z0) / (self.z1 - self.z0) * (WIDTH - 2.0 * MARGIN),
+            false => MARGIN,
+        };
+        let k = match self.k > 0.0 {
+            true => k / self.k,
+            false => 0.0,
+        };
+        (x, self.top + MARGIN + (1.0 - k) * DIAGRAM_HEIGHT / 2.0)
+    }
+
+    /// 軸と目盛りと曲率のグラフ
+    fn render(&self, tracks: &[Track]) -> String {
+        let mut s = String::new();
+        let (left, zero) = self.map(self.z0, 0.0);
+        let (right, top) = self.map(self.z1, self.k);
+        let (_, bottom) = self.map(self.z0, -self.k);
+        let _ = writeln!(
+            s,
+            r#"<g stroke="gray"><line x1="{}" y1="{}" x2="{}" y2="{}"/><line x1="{}" y1="{}" x2="{}" y2="{}"/></g>"#,
+            px(left),
+            px(zero),
+            px(right),
+            px(zero),
+            px(left),
+            px(top),
+            px(left),
+            px(bottom)
+        );
+        let _ = writeln!(
+            s,
+            r#"<text x="{}" y="{}">曲率 (右が正)</text>"#,
+            px(left),
+            px(top - 8.0)
+        );
+        if self.k > 0.0 {
+            let r = format!("{:.0}", self.k.recip());
+            let _ = writeln!(
+                s,
+                r#"<text x="{}" y="{}" text-anchor="end">+1/{r}</text><text x="{}" y="{}" text-anchor="end">-1/{r}</text>"#,
+                px(left - 4.0),
+                px(top + 4.0),
+                px(left - 4.0),
+                px(bottom + 4.0)
+            );
+        }
+
+        // 距離程の目盛り
+        let step = tick(self.z1 - self.z0);
+        let mut n = (self.z0 / step).ceil();
+        while n * step <= self.z1 + TOLERANCE {
+            let (x, _) = self.map(n * step, 0.0);
+            let _ = writeln!(
+                s,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="gray"/><text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+                px(x),
+                px(bottom),
+                px(x),
+                px(bottom + TICK),
+                px(x),
+                px(bottom + TICK + 10.0),
+                n * step
+            );
+            n += 1.0;
+        }
+
+        // 線ごとの曲率を階段状に結ぶ
+        for (i, track) in tracks.iter().enumerate() {
+            let Some(z0) = track.z0 else { continue };
+            let mut d = String::new();
+            let mut z = z0;
+            for stroke in &track.strokes {
+                let k = stroke.k();
+                let (x0, y) = self.map(z, k);
+                z += stroke.length();
+                let (x1, _) = self.map(z, k);
+                let command = if d.is_empty() { 'M' } else { 'L' };
+                let _ = write!(d, "{command}{} {}L{} {}", px(x0), px(y), px(x1), px(y));
+            }
+            let _ = writeln!(
+                s,
+                r#"<path d="{d}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+                COLORS[i % COLORS.len()]
+            );
+        }
+        s
+    }
+}
+
+/// 距離程の目盛りの位置・接線方向 (rad) と、その距離程 (m)
+fn ticks(track: &Track) -> Vec<(Point, f64, f64)> {
+    let Some(z0) = track.z0 else {
+        return vec![];
+    };
+    let polyline = track.strokes.iter().copied().collect::<Polyline>();
+    let len = polyline.length();
+    let step = tick(len);
+    let mut ticks = vec![];
+    let mut n = (z0 / step).ceil();
+    while n * step <= z0 + len + TOLERANCE {
+        let z = n * step;
+        if let Some((stroke, s)) = polyline.locate(z - z0) {
+            let (p, t) = stroke.at(s);
+            ticks.push((p, t, z));
+        }
+        n += 1.0;
+    }
+    ticks
+}
+
+/// 長さ `span` (m) を10程度に区切る、きりのよい目盛りの間隔 (m)
+fn tick(span: f64) -> f64 {
+    if span <= 0.0 {
+        return 1.0;
+    }
+    let raw = span / 10.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// 緩和曲線の線を軌道の線にする。
+///
+/// 半径が非常に大きい円弧は直線とする。
+fn convert(stroke: &canvas::Stroke) -> Stroke {
+    match stroke
+        .center()
+        .zip(stroke.r().filter(|r| r.meter().abs() < 100_000.0))
+    {
+        Some((c, r)) => Stroke::Curve {
+            c,
+            r: r.meter().abs(),
+            a0: stroke.a0().rad(),
+            sweep: stroke.a1().rad() - stroke.a0().rad(),
+        },
+        None => Stroke::Straight {
+            p0: stroke.p0(),
+            p1: stroke.p1(),
+        },
+    }
+}
+
+/// 線の長さの合計
+fn length(strokes: &[Stroke]) -> f64 {
+    strokes.iter().map(Stroke::length).sum()
+}
+
+/// 画面上の長さを丸める。
+fn px(f: f64) -> String {
+    let s = format!("{f:.2}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" => "0".to_string(),
+        s => s.to_string(),
+    }
+}
+
+/// XMLの特殊文字を置き換える。
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use crate::{
+        agent::buffer::Buffer,
+        transition::curve::{Radius, Subtension, Tangential, STRAIGHT},
+    };
+
+    use super::*;
+
+    #[rstest]
+    #[case(60.0, 10.0)]
+    #[case(100.0, 10.0)]
+    #[case(101.0, 20.0)]
+    #[case(2400.0, 500.0)]
+    #[case(0.0, 1.0)]
+    fn きりのよい目盛り(#[case] span: f64, #[case] expected: f64) {
+        assert_eq!(tick(span), expected);
+    }
+
+    #[test]
+    fn 続いた線は同じ軌道にする() {
+        let buffer = Buffer::default();
+        let mut svg = Write::new(buffer.clone());
+        svg.start(100.0);
+
+        // 直線10mと、半径-20m (左) の1/4円
+        let straight = canvas::Stroke::new(
+            STRAIGHT,
+            Subtension::from(10.0),
+            Point::from((0.0, 0.0)),
+            Tangential::from(0.0),
+        );
+        let arc = canvas::Stroke::new(
+            Radius(-20.0).into(),
+            Subtension::from(10.0 * PI),
+            straight.p1(),
+            straight.t1(),
+        );
+        svg.strokes(&[straight]).unwrap();
+        svg.strokes(&[arc]).unwrap();
+        assert_eq!(svg.tracks.len(), 1);
+        assert_eq!(svg.tracks[0].z0, Some(100.0));
+        assert_eq!(svg.tracks[0].strokes[1].k(), -0.05);
+        svg.close().unwrap();
+
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        assert!(text.starts_with("<svg "));
+        assert!(text.ends_with("</svg>\n"));
+        // 平面図の軌道と曲率図
+        assert_eq!(text.matches("stroke-width=\"1.5\"").count(), 2);
+        // 左カーブは反時計回り (画面上は時計回り)
+        assert!(text.contains(" 0 0 1 "));
+        assert!(text.contains(">+1/20<"));
+        assert!(text.contains(">140<"));
+        assert_inside(&text);
+    }
+
+    #[test]
+    fn 目盛りと文字は図の中に描く() {
+        let buffer = Buffer::default();
+        let mut svg = Write::new(buffer.clone());
+        svg.start(0.0);
+
+        // 半径400mの円弧50m
+        let arc = canvas::Stroke::new(
+            Radius(400.0).into(),
+            Subtension::from(50.0),
+            Point::from((0.0, 0.0)),
+            Tangential::from(0.0),
+        );
+        svg.strokes(&[arc]).unwrap();
+        svg.close().unwrap();
+
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        assert!(text.contains(">+1/400<"));
+        assert!(text.contains(">50<"));
+        assert_inside(&text);
+    }
+
+    /// 全ての `y` `y1` `y2` が viewBox の高さに収まることを確かめる。
+    fn assert_inside(text: &str) {
+        let height = attribute(text, " viewBox=\"")
+            .next()
+            .and_then(|v| v.split(' ').nth(3))
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+        let ys = [" y=\"", " y1=\"", " y2=\""]
+            .iter()
+            .flat_map(|name| attribute(text, name))
+            .map(|v| v.parse::<f64>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(!ys.is_empty());
+        for y in ys {
+            assert!((0.0..=height).contains(&y), "y={y} height={height}");
+        }
+    }
+
+    /// 属性 `name` の値を全て取り出す。
+    fn attribute<'a>(text: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> {
+        text.split(name)
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+    }
+
+    #[test]
+    fn 距離程のない軌道は平面図だけに描く() {
+        let buffer = Buffer::default();
+        let mut svg = Write::new(buffer.clone());
+        let polyline = Polyline::from_iter([Stroke::Straight {
+            p0: Point::from((0.0, 0.0)),
+            p1: Point::from((30.0, 0.0)),
+        }]);
+        svg.polyline("<他線>", &polyline, None);
+        svg.close().unwrap();
+
+        let text = String::from_utf8(buffer.0.take()).unwrap();
+        assert!(text.contains(r#"d="M40 40L760 40""#));
+        assert!(text.contains("&lt;他線&gt;"));
+        assert!(!text.contains("曲率"));
+    }
+}
@@ -29,7 +29,7 @@ impl Package {
         })
     }
 
-    pub fn create_file(&mut self, path: impl AsRef<Path>) -> Result<File> {
+    pub fn create_file(&mut self, path: impl AsRef<Path>) -> Result<File<'_>> {
         let path = path.as_ref();
         let path = path
             .to_str()
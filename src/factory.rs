@@ -1,17 +1,41 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
 use anyhow::{bail, ensure, Result};
 
 use crate::{
-    agent::bat::Args,
+    agent::{
+        bat::Args,
+        bve::{map::Map, Format, MapPath},
+        cli::{Command, Sub},
+        jww::JwcTemp,
+    },
+    ip::{app::Curve, param::Param as IpParam, tangent::Tangents},
+    route::app::Route,
     track::app::Track,
     transition::{self, app::Transition},
+    vertical::{
+        app::Vertical,
+        source::{Frame, Source},
+    },
 };
 
-use transition::param::Param as TrParam;
+use transition::{
+    canvas::Point,
+    curve::{Degree, Tangential},
+    joint::Joint,
+    param::{Drawing, Param as TrParam},
+};
 
 #[derive(Debug)]
 pub enum App<'a> {
     Transition(Transition),
+    Curve(Curve),
     Track(Track<'a>),
+    Route(Route),
+    Vertical(Vertical),
 }
 
 impl<'a> App<'a> {
@@ -19,15 +43,238 @@ impl<'a> App<'a> {
     pub fn new(args: &'a Args) -> Result<Self> {
         if let Ok(formula) = args.transition() {
             let file = args.temp_path()?;
-            let param = TrParam::parse(&formula, &args);
-            Ok(Self::Transition(Transition::new(file, param)))
+            let params = transition_params(formula, args).and_then(|params| {
+                // 最初の区間の始点を接続する。
+                let joint = Joint::read(file)?;
+                let params = params.into_iter().enumerate();
+                Ok(params
+                    .map(|(i, param)| if i == 0 { param.attach(joint) } else { param })
+                    .collect())
+            });
+            let drawing = Drawing::parse(args);
+            // 座標ファイルを上書きする前に、プロジェクトの場所を読む。
+            let map = map_output(args, || project(file));
+            Ok(Self::Transition(Transition::new(params, drawing, map)))
+        } else if let Ok(formula) = args.curve() {
+            let file = args.temp_path()?;
+            let param = IpParam::parse(formula, args);
+            let tangents = Tangents::read(file);
+            let drawing = Drawing::parse(args);
+            Ok(Self::Curve(Curve::new(param, tangents, drawing)))
         } else if let Ok(track) = args.track() {
             ensure!(track == "X");
-            Ok(Self::Track(Track::new(&args)))
+            Ok(Self::Track(Track::new(args)))
+        } else if let Ok(path) = args.route() {
+            let file = args.temp_path()?;
+            // 相対パスは作業中のファイルのフォルダから
+            let map = map_path(path)
+                .and_then(|path| project_path(file, path))
+                .and_then(read_map);
+            // 線・円弧を選択すれば、その端点から作図する。
+            let start = Joint::read(file).and_then(|joint| {
+                let (p, t) = joint
+                    .map_or((Point::from((0.0, 0.0)), Tangential::from(0.0)), |joint| {
+                        (joint.p, joint.t)
+                    });
+                Ok((args.l0()?, p, t))
+            });
+            let drawing = args.tick().and_then(|tick| Ok((tick, args.style()?)));
+            Ok(Self::Route(Route::new(
+                map,
+                start,
+                args.end_distance(),
+                drawing,
+            )))
+        } else if let Ok(table) = args.vertical() {
+            let file = args.temp_path()?;
+            // 表を指定しなければ、選択した勾配線と文字から読む。
+            let source = match table.is_empty() {
+                true => JwcTemp::open(file)
+                    .and_then(|mut jwc_temp| Ok(Source::Shapes(jwc_temp.shapes()?.to_vec()))),
+                false => {
+                    project_path(file, PathBuf::from(table)).and_then(|path| vertical_source(&path))
+                }
+            };
+            // 点を指示すれば、そこを基準点にする。
+            let frame = JwcTemp::open(file).and_then(|mut jwc_temp| {
+                let origin = jwc_temp.point(1)?.unwrap_or(Point::from((0.0, 0.0)));
+                vertical_frame(origin, args)
+            });
+            let design = vertical_design(args);
+            let map = map_output(args, || project(file));
+            Ok(Self::Vertical(Vertical::new(
+                source,
+                frame,
+                design,
+                args.style(),
+                map,
+            )))
         } else {
             bail!("機能を指定してください")
         }
     }
+
+    /// コマンドラインから実行する機能のファクトリ関数。
+    ///
+    /// 座標ファイルの代わりに、始点や接線をオプションで受け取る。
+    pub fn command(command: &'a Command) -> Result<Self> {
+        let args = &command.args;
+        match command.sub {
+            Sub::Transition => {
+                let p0 = Point::from((command.number("x", 0.0), command.number("y", 0.0)));
+                let t0 = Tangential::from(Degree(command.number("angle", 0.0)));
+                let params = transition_params(args.transition()?, args).map(|params| {
+                    // 最初の区間の始点を置く。
+                    let params = params.into_iter().enumerate();
+                    params
+                        .map(|(i, param)| match i {
+                            0 => TrParam { p0, t0, ..param },
+                            _ => param,
+                        })
+                        .collect()
+                });
+                let drawing = Drawing::parse(args);
+                let map = map_output(args, || Ok((env::current_dir()?, None)));
+                Ok(Self::Transition(Transition::new(params, drawing, map)))
+            }
+            Sub::Curve => {
+                let param = IpParam::parse(args.curve()?, args);
+                let line = |long| {
+                    command
+                        .line(long)
+                        .map(|[x0, y0, x1, y1]| (Point::from((x0, y0)), Point::from((x1, y1))))
+                };
+                let tangents = line("entry")
+                    .and_then(|entry| Ok((entry, line("exit")?)))
+                    .and_then(|(entry, exit)| Tangents::new(entry, exit));
+                let drawing = Drawing::parse(args);
+                Ok(Self::Curve(Curve::new(param, tangents, drawing)))
+            }
+            Sub::Track => Ok(Self::Track(Track::new(args))),
+            Sub::Route => {
+                let map = args.route().and_then(map_path).and_then(read_map);
+                let p0 = Point::from((command.number("x", 0.0), command.number("y", 0.0)));
+                let t0 = Tangential::from(Degree(command.number("angle", 0.0)));
+                let start = args.l0().map(|l0| (l0, p0, t0));
+                let drawing = args.tick().and_then(|tick| Ok((tick, args.style()?)));
+                Ok(Self::Route(Route::new(
+                    map,
+                    start,
+                    args.end_distance(),
+                    drawing,
+                )))
+            }
+            Sub::Vertical => {
+                let source = args.vertical().and_then(|path| {
+                    ensure!(!path.is_empty(), "勾配の変化点の表を指定してください");
+                    vertical_source(Path::new(path))
+                });
+                let origin = Point::from((command.number("x", 0.0), command.number("y", 0.0)));
+                let frame = vertical_frame(origin, args);
+                let map = map_output(args, || Ok((env::current_dir()?, None)));
+                Ok(Self::Vertical(Vertical::new(
+                    source,
+                    frame,
+                    vertical_design(args),
+                    args.style(),
+                    map,
+                )))
+            }
+        }
+    }
+}
+
+/// 緩和曲線の区間
+///
+/// 区間の並びがなければ1区間。
+fn transition_params(formula: &str, args: &Args) -> Result<Vec<TrParam>> {
+    match args.chain() {
+        Ok(chain) if !chain.is_empty() => TrParam::chain(formula, chain, args),
+        _ => TrParam::parse(formula, args).map(|param| vec![param]),
+    }
+}
+
+/// マップファイルのパス
+fn map_path(path: &str) -> Result<PathBuf> {
+    ensure!(!path.is_empty(), "マップファイルを指定してください");
+    Ok(PathBuf::from(path))
+}
+
+/// BVEマップの出力先と書式
+///
+/// 出力しないなら `None`。`project` は、マップファイル名が相対パスのときのフォルダと作図元のファイル。
+fn map_output(
+    args: &Args,
+    project: impl FnOnce() -> Result<(PathBuf, Option<PathBuf>)>,
+) -> Result<Option<(MapPath, Format)>> {
+    if !args.bve()? {
+        return Ok(None);
+    }
+    let (dir, source) = project()?;
+    let path = MapPath::build(args.map_name(), || Ok(dir.clone()))?;
+    Ok(Some((
+        path,
+        Format {
+            source,
+            ..args.map_format()?
+        },
+    )))
+}
+
+/// 作業中のファイル `file` のフォルダとパス
+fn project(file: &str) -> Result<(PathBuf, Option<PathBuf>)> {
+    let mut jwc_temp = JwcTemp::open(file)?;
+    let dir = jwc_temp.project_dir()?;
+    Ok((dir, Some(PathBuf::from(jwc_temp.project_path()?))))
+}
+
+/// 作業中のファイル `file` のフォルダから見たパス
+///
+/// 絶対パスならそのまま。
+fn project_path(file: &str, path: PathBuf) -> Result<PathBuf> {
+    match path.is_absolute() {
+        true => Ok(path),
+        false => JwcTemp::open(file)
+            .and_then(|mut jwc_temp| jwc_temp.project_dir())
+            .map(|dir| dir.join(path)),
+    }
+}
+
+/// マップファイルを読み込む。
+fn read_map(path: PathBuf) -> Result<(PathBuf, Map)> {
+    let map = Map::open(&path)?;
+    Ok((path, map))
+}
+
+/// 勾配の変化点の読み込み元
+///
+/// 拡張子が `.csv` なら表、そうでなければ縦断面図の座標ファイル。
+fn vertical_source(path: &Path) -> Result<Source> {
+    let csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    match csv {
+        true => Source::open(path),
+        false => {
+            let shapes = JwcTemp::open(path)?.shapes()?.to_vec();
+            Ok(Source::Shapes(shapes))
+        }
+    }
+}
+
+/// 基準点 `origin` に基準の距離程と高さを置いた縦断面図
+fn vertical_frame(origin: Point, args: &Args) -> Result<Frame> {
+    Ok(Frame {
+        origin,
+        z0: args.l0()?,
+        h0: args.datum_height()?,
+        scale: args.vertical_scale()?,
+    })
+}
+
+/// 縦曲線半径と、他軌道の高さの出力間隔
+fn vertical_design(args: &Args) -> Result<(f64, f64)> {
+    Ok((args.vertical_radius()?, args.height_interval()?))
 }
 
 #[cfg(test)]
@@ -0,0 +1,104 @@
+use anyhow::Result;
+
+use crate::{
+    agent::{jww::JwcTemp, sheet::Sheet},
+    transition::{
+        app::{m, r, upright},
+        param::Drawing,
+        unit::Meter,
+    },
+};
+
+use super::{param::Param, tangent::Tangents, Design};
+
+/// 外部変形 "CURVE"
+#[derive(Debug)]
+pub struct Curve {
+    param: Result<Param>,
+    tangents: Result<Tangents>,
+    drawing: Result<Drawing>,
+}
+
+impl Curve {
+    pub fn new(param: Result<Param>, tangents: Result<Tangents>, drawing: Result<Drawing>) -> Self {
+        Self {
+            param,
+            tangents,
+            drawing,
+        }
+    }
+
+    /// エントリーポイント
+    ///
+    /// 座標ファイル `file` に出力する。
+    pub fn draw(&self, file: &str) -> Result<()> {
+        self.render(&mut JwcTemp::create(file)?)
+    }
+
+    /// 曲線を `jwc_temp` に出力する。
+    pub fn render(&self, jwc_temp: &mut impl Sheet) -> Result<()> {
+        match (&self.param, &self.tangents, &self.drawing) {
+            (Ok(p), Ok(tangents), Ok(drawing)) => {
+                let design = match super::fit(p, tangents) {
+                    Ok(design) => design,
+                    Err(e) => return jwc_temp.error(&e),
+                };
+
+                // 線の属性を設定する。
+                jwc_temp.style(&drawing.style)?;
+
+                // TSからSTまでを出力する。
+                jwc_temp.strokes(&design.strokes().copied().collect::<Vec<_>>())?;
+
+                // 注記を出力する。
+                if drawing.annotate {
+                    annotate(jwc_temp, p, &design)?;
+                }
+
+                // 成功メッセージを出力する。
+                jwc_temp.notice(format!(
+                    "IA={}° TL={}m,{}m CL={}m の曲線を描画しました。",
+                    m(design.ia.to_degrees()),
+                    m(design.tl.0),
+                    m(design.tl.1),
+                    m(design.length()),
+                ))?;
+
+                Ok(())
+            }
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => jwc_temp.error(e),
+        }
+    }
+}
+
+/// TS・SC・CS・STに注記を出力する。
+fn annotate(jwc_temp: &mut impl Sheet, p: &Param, design: &Design) -> Result<()> {
+    let (Some(first), Some(last)) = (design.strokes().next(), design.strokes().last()) else {
+        return Ok(());
+    };
+    let sc = design.entry.last().unwrap_or(first);
+    let cs = design.exit.first().unwrap_or(last);
+
+    let l0 = p.l0.meter();
+    let l1 = l0 + p.tcl0.meter();
+    let l2 = l1 + design.arc_length;
+    let l3 = l2 + p.tcl1.meter();
+    let labels = [
+        (first.p0(), first.t0(), format!("TS {}m", m(l0))),
+        (
+            sc.p1(),
+            sc.t1(),
+            format!("SC {}m R={} f={}m", m(l1), r(design.k), m(design.shift.0)),
+        ),
+        (
+            cs.p0(),
+            cs.t0(),
+            format!("CS {}m R={} f={}m", m(l2), r(design.k), m(design.shift.1)),
+        ),
+        (last.p1(), last.t1(), format!("ST {}m", m(l3))),
+    ];
+    for (point, t, s) in labels {
+        jwc_temp.text(&point, &upright(t), s)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,277 @@
+//! IP法による円曲線の設計
+
+pub mod app;
+pub mod param;
+pub mod tangent;
+
+use std::f64::consts::{PI, TAU};
+
+use anyhow::{ensure, Result};
+
+use crate::transition::{
+    self,
+    canvas::{Point, Spiral, Stroke},
+    curve::{Curvature, Radius, Subtension, Tangential, STRAIGHT},
+    distance::Distance,
+    exact,
+    unit::{Meter, Rad, Vector},
+};
+
+use param::Param;
+use tangent::Tangents;
+
+/// 緩和曲線つきの円曲線
+#[derive(Debug)]
+pub struct Design {
+    /// 入口側の緩和曲線 (TS～SC)
+    pub entry: Spiral,
+
+    /// 円曲線 (SC～CS)
+    pub arc: Option<Stroke>,
+
+    /// 出口側の緩和曲線 (CS～ST)
+    pub exit: Spiral,
+
+    /// 円曲線の曲率
+    pub k: Curvature,
+
+    /// 交角 (rad)
+    pub ia: f64,
+
+    /// 接線長 (TS～IP, IP～ST) (m)
+    pub tl: (f64, f64),
+
+    /// 移程量 (入口側, 出口側) (m)
+    pub shift: (f64, f64),
+
+    /// 円曲線長 (m)
+    pub arc_length: f64,
+}
+
+impl Design {
+    /// 曲線長 (m)
+    pub fn length(&self) -> f64 {
+        self.strokes().map(|stroke| stroke.len().meter()).sum()
+    }
+
+    /// TSからSTまでの線
+    pub fn strokes(&self) -> impl Iterator<Item = &Stroke> {
+        self.entry.iter().chain(&self.arc).chain(self.exit.iter())
+    }
+}
+
+/// 2本の接線の間に、緩和曲線つきの円曲線を設計する。
+pub fn fit(param: &Param, tangents: &Tangents) -> Result<Design> {
+    // 交角。反時計回りなら左カーブ。
+    let turn = (tangents.t1.rad() - tangents.t0.rad() + PI).rem_euclid(TAU) - PI;
+    let ia = turn.abs();
+    let k = Curvature::from(Radius(match turn > 0.0 {
+        true => -param.r,
+        false => param.r,
+    }));
+
+    // 緩和曲線の中心角から、円曲線長を決める。
+    let origin = Point::from((0.0, 0.0));
+    let theta0 = spiral_turn(&spiral(param, k, true, origin, tangents.t0, param.l0));
+    let theta1 = spiral_turn(&spiral(param, k, false, origin, tangents.t0, param.l0));
+    let arc_length = (ia - theta0 - theta1) * param.r;
+    ensure!(
+        arc_length > -1e-6,
+        "緩和曲線が長すぎて円曲線を挿入できません"
+    );
+    let arc_length = arc_length.max(0.0);
+
+    // 仮の位置で作図して、TSからSTまでのベクトルを求める。
+    let trial = build(param, k, arc_length, origin, tangents.t0);
+    let end = trial.strokes().last().map_or(origin, Stroke::p1);
+
+    // TS～ST = TL0 * (入口の向き) + TL1 * (出口の向き)
+    let u0 = (tangents.t0.rad().cos(), tangents.t0.rad().sin());
+    let u1 = (tangents.t1.rad().cos(), tangents.t1.rad().sin());
+    let det = u0.0 * u1.1 - u0.1 * u1.0;
+    let tl0 = (end.x() * u1.1 - end.y() * u1.0) / det;
+    let tl1 = (u0.0 * end.y() - u0.1 * end.x()) / det;
+    ensure!(tl0 > 0.0 && tl1 > 0.0, "曲線が接線に収まりません");
+
+    // TSから作図し直す。
+    let ts = tangents.ip + Point::from((-tl0 * u0.0, -tl0 * u0.1));
+    let mut design = build(param, k, arc_length, ts, tangents.t0);
+
+    // 円曲線を接線側へ移した量
+    let sc = design.entry.last().map_or(ts, Stroke::p1);
+    let cs = design
+        .exit
+        .first()
+        .map_or((sc, tangents.t0), |s| (s.p0(), s.t0()));
+    let shift =
+        |p: Option<transition::param::Param>| p.and_then(|p| exact::shift(&p)).unwrap_or(0.0);
+    design.shift = (
+        shift(transition(param, k, true, ts, tangents.t0, param.l0)),
+        shift(transition(param, k, false, cs.0, cs.1, param.l0)),
+    );
+    design.ia = ia;
+    design.tl = (tl0, tl1);
+    Ok(design)
+}
+
+/// TSを `p0` に置いて、TSからSTまでを作図する。
+fn build(param: &Param, k: Curvature, arc_length: f64, p0: Point, t0: Tangential) -> Design {
+    let entry = spiral(param, k, true, p0, t0, param.l0);
+    let sc = entry.last().map_or((p0, t0), |s| (s.p1(), s.t1()));
+
+    let arc = (arc_length > 0.0).then(|| Stroke::new(k, Subtension::from(arc_length), sc.0, sc.1));
+    let cs = arc.map_or(sc, |s| (s.p1(), s.t1()));
+
+    let l1 = param.l0 + param.tcl0 + Subtension::from(arc_length);
+    let exit = spiral(param, k, false, cs.0, cs.1, l1);
+
+    Design {
+        entry,
+        arc,
+        exit,
+        k,
+        ia: 0.0,
+        tl: (0.0, 0.0),
+        shift: (0.0, 0.0),
+        arc_length,
+    }
+}
+
+/// 入口側 (`is_entry`) または出口側の緩和曲線
+///
+/// 緩和曲線長が0なら線はない。
+fn spiral(
+    param: &Param,
+    k: Curvature,
+    is_entry: bool,
+    p0: Point,
+    t0: Tangential,
+    l0: Distance<f64>,
+) -> Spiral {
+    transition(param, k, is_entry, p0, t0, l0)
+        .map_or_else(|| Spiral::from_iter([]), |p| transition::plot(&p))
+}
+
+/// 入口側 (`is_entry`) または出口側の緩和曲線の条件
+///
+/// 緩和曲線長が0なら `None`
+fn transition(
+    param: &Param,
+    k: Curvature,
+    is_entry: bool,
+    p0: Point,
+    t0: Tangential,
+    l0: Distance<f64>,
+) -> Option<transition::param::Param> {
+    let (diminish, tcl, k0, k1) = match is_entry {
+        true => (param.diminish0, param.tcl0, STRAIGHT, k),
+        false => (param.diminish1, param.tcl1, k, STRAIGHT),
+    };
+    (tcl.meter() > 0.0).then_some(transition::param::Param {
+        diminish,
+        k0,
+        k1,
+        l0,
+        tcl,
+        p0,
+        t0,
+        cant: None,
+        governing: None,
+        division: Default::default(),
+    })
+}
+
+/// 緩和曲線の中心角の大きさ (rad)
+fn spiral_turn(spiral: &Spiral) -> f64 {
+    match (spiral.first(), spiral.last()) {
+        (Some(first), Some(last)) => (last.t1().rad() - first.t0().rad()).abs(),
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transition::curve::Diminish;
+
+    use super::*;
+
+    fn 円曲線(diminish: Diminish, r: f64, tcl0: f64, tcl1: f64) -> Param {
+        Param {
+            diminish0: diminish,
+            diminish1: diminish,
+            r,
+            tcl0: tcl0.into(),
+            tcl1: tcl1.into(),
+            l0: 0.0.into(),
+        }
+    }
+
+    /// 東向きの接線と、原点から右へ `ia` 度曲がる接線
+    fn 接線(ia: f64) -> Tangents {
+        Tangents {
+            ip: Point::from((0.0, 0.0)),
+            t0: Tangential::from(0.0),
+            t1: Tangential::from(-ia.to_radians()),
+        }
+    }
+
+    /// 点 `p` から直線 (`o`, 方向 `t`) までの距離
+    fn 直線からの距離(p: Point, o: Point, t: Tangential) -> f64 {
+        ((p.x() - o.x()) * t.rad().sin() - (p.y() - o.y()) * t.rad().cos()).abs()
+    }
+
+    #[test]
+    fn クロソイドの接線長() {
+        let (r, l, ia) = (400.0, 60.0, 30.0_f64);
+        let design = fit(&円曲線(Diminish::Linear, r, l, l), &接線(ia)).unwrap();
+
+        // 教科書の近似式
+        let tau = l / (2.0 * r);
+        let x = l * (1.0 - tau.powi(2) / 10.0);
+        let y = l * tau / 3.0 * (1.0 - tau.powi(2) / 14.0);
+        let f = y - r * (1.0 - tau.cos());
+        let xm = x - r * tau.sin();
+        let tl = xm + (r + f) * (ia.to_radians() / 2.0).tan();
+
+        assert!((design.shift.0 - f).abs() < 0.001);
+        assert!((design.shift.1 - f).abs() < 0.001);
+        assert!((design.tl.0 - tl).abs() < 0.01);
+        assert!((design.tl.1 - tl).abs() < 0.01);
+        assert!((design.length() - (r * ia.to_radians() + l)).abs() < 1e-6);
+        assert!(design.k > STRAIGHT);
+    }
+
+    #[test]
+    fn 両端が接線に乗る() {
+        let mut param = 円曲線(Diminish::Sine, 300.0, 50.0, 80.0);
+        param.diminish1 = Diminish::CubicParabola;
+        let tangents = Tangents {
+            ip: Point::from((100.0, 50.0)),
+            t0: Tangential::from(0.3),
+            t1: Tangential::from(1.2),
+        };
+        let design = fit(&param, &tangents).unwrap();
+        let first = design.strokes().next().unwrap();
+        let last = design.strokes().last().unwrap();
+
+        assert!(直線からの距離(first.p0(), tangents.ip, tangents.t0) < 1e-6);
+        assert!(直線からの距離(last.p1(), tangents.ip, tangents.t1) < 0.001);
+        assert!((last.t1().rad() - 1.2).abs() < 1e-4);
+        assert!(design.tl.0 < design.tl.1);
+        assert!(design.k < STRAIGHT);
+    }
+
+    #[test]
+    fn 緩和曲線がなければ単心曲線() {
+        let design = fit(&円曲線(Diminish::Sine, 100.0, 0.0, 0.0), &接線(90.0)).unwrap();
+        assert_eq!(design.strokes().count(), 1);
+        assert!((design.tl.0 - 100.0).abs() < 1e-9);
+        assert!(design.shift.0.abs() < 1e-9);
+    }
+
+    #[test]
+    fn 緩和曲線が長すぎればエラー() {
+        let e = fit(&円曲線(Diminish::Linear, 100.0, 100.0, 100.0), &接線(30.0)).unwrap_err();
+        assert_eq!(e.to_string(), "緩和曲線が長すぎて円曲線を挿入できません");
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+
+use crate::{
+    agent::bat::Args,
+    transition::{
+        curve::{Diminish, Subtension},
+        distance::Distance,
+    },
+};
+
+/// 円曲線パラメータ
+#[derive(Debug)]
+pub struct Param {
+    /// 入口側の逓減関数
+    pub diminish0: Diminish,
+
+    /// 出口側の逓減関数
+    pub diminish1: Diminish,
+
+    /// 円曲線半径 (m)
+    ///
+    /// 曲がる向きは接線から決まる。
+    pub r: f64,
+
+    /// 入口側の緩和曲線長
+    pub tcl0: Subtension,
+
+    /// 出口側の緩和曲線長
+    pub tcl1: Subtension,
+
+    /// TSの距離程
+    pub l0: Distance<f64>,
+}
+
+impl Param {
+    /// コマンドライン引数を円曲線パラメータにパースする。
+    ///
+    /// 逓減関数は "入口,出口" で指定する。出口を省略すると入口と同じになる。
+    pub fn parse(diminish: &str, args: &Args) -> Result<Self> {
+        let (diminish0, diminish1) = diminish.split_once(',').unwrap_or((diminish, diminish));
+        let tcl0 = args.tcl0()?;
+        Ok(Self {
+            diminish0: Diminish::parse(diminish0)?,
+            diminish1: Diminish::parse(diminish1)?,
+            r: args.r()?,
+            tcl0: Subtension::from(tcl0),
+            tcl1: Subtension::from(args.tcl1()?.unwrap_or(tcl0)),
+            l0: Distance::from(args.l0()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    fn parse(argv: &[&str]) -> Result<Param> {
+        let args = Args::parse(argv.iter().map(OsString::from)).unwrap();
+        Param::parse(args.curve().unwrap(), &args)
+    }
+
+    #[test]
+    fn 出口側は省略できる() {
+        let param = parse(&["/CURVE:2", "/R:400", "/TCL0:60"]).unwrap();
+        assert_eq!(param.diminish0, Diminish::Linear);
+        assert_eq!(param.diminish1, Diminish::Linear);
+        assert_eq!(param.tcl1, 60.0.into());
+    }
+
+    #[test]
+    fn 入口と出口で異なる緩和曲線() {
+        let param = parse(&["/CURVE:1,3", "/R:400", "/TCL0:60", "/TCL1:0", "/L0:100"]).unwrap();
+        assert_eq!(param.diminish0, Diminish::Sine);
+        assert_eq!(param.diminish1, Diminish::CubicParabola);
+        assert_eq!(param.tcl0, 60.0.into());
+        assert_eq!(param.tcl1, 0.0.into());
+        assert_eq!(param.l0, 100.0.into());
+    }
+
+    #[rstest]
+    #[case(&["/CURVE:2", "/TCL0:60"], "Rを指定してください")]
+    #[case(&["/CURVE:2", "/R:0", "/TCL0:60"], "Rに0より大きい値を入力してください")]
+    #[case(&["/CURVE:2", "/R:400"], "TCL0を指定してください")]
+    #[case(&["/CURVE:2", "/R:400", "/TCL0:60", "/TCL1:-1"], "TCL1に0以上の値を入力してください")]
+    #[case(&["/CURVE:2,7", "/R:400", "/TCL0:60"], "緩和曲線関数に正しい値を入力してください")]
+    fn パラメータのエラーチェック(#[case] argv: &[&str], #[case] err: &str) {
+        assert_eq!(parse(argv).unwrap_err().to_string(), err);
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use anyhow::{bail, ensure, Result};
+
+use crate::{
+    agent::jww::{self, Figure, JwcTemp},
+    transition::{canvas::Point, curve::Tangential, unit::Vector},
+};
+
+/// 2本の接線
+#[derive(Debug, Clone, Copy)]
+pub struct Tangents {
+    /// 交点 (IP)
+    pub ip: Point,
+
+    /// 入口側の接線方向 (IPへ向かう向き)
+    pub t0: Tangential,
+
+    /// 出口側の接線方向 (IPから離れる向き)
+    pub t1: Tangential,
+}
+
+impl Tangents {
+    /// 座標ファイルから、選択された2本の直線を読み取る。
+    pub fn read(path: &(impl AsRef<Path> + ?Sized)) -> Result<Self> {
+        ensure!(path.as_ref().exists(), "接線を2本選択してください");
+        JwcTemp::open(path)?.tangents()
+    }
+
+    /// 入口側の直線 `entry` と出口側の直線 `exit` を延長して交わらせる。
+    pub fn new(entry: (Point, Point), exit: (Point, Point)) -> Result<Self> {
+        let (p0, p1) = entry;
+        let (q0, q1) = exit;
+        let d0 = (p1.x() - p0.x(), p1.y() - p0.y());
+        let d1 = (q1.x() - q0.x(), q1.y() - q0.y());
+        let det = d0.0 * d1.1 - d0.1 * d1.0;
+        if det.abs() < 1e-9 * d0.0.hypot(d0.1) * d1.0.hypot(d1.1) {
+            bail!("2本の接線が平行です");
+        }
+
+        // 交点
+        let u = ((q0.x() - p0.x()) * d1.1 - (q0.y() - p0.y()) * d1.0) / det;
+        let ip = p0 + Point::from((d0.0 * u, d0.1 * u));
+
+        // 交点から遠い方の端点が、接線の外側
+        let far = |a: Point, b: Point| match a.distance(ip) < b.distance(ip) {
+            true => b,
+            false => a,
+        };
+        let back = far(p0, p1);
+        let ahead = far(q0, q1);
+        Ok(Self {
+            ip,
+            t0: Tangential::from((ip.y() - back.y()).atan2(ip.x() - back.x())),
+            t1: Tangential::from((ahead.y() - ip.y()).atan2(ahead.x() - ip.x())),
+        })
+    }
+}
+
+impl jww::Read {
+    /// 選択された2本の直線
+    ///
+    /// 指示された点に近い方を入口側とする。指示がなければ選択順。
+    fn tangents(&mut self) -> Result<Tangents> {
+        let lines = self
+            .shapes()?
+            .iter()
+            .filter_map(|shape| match shape.figure {
+                Figure::Straight(p0, p1) => Some((p0, p1)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let [mut entry, mut exit] = <[(Point, Point); 2]>::try_from(lines)
+            .map_err(|_| anyhow::anyhow!("接線を2本選択してください"))?;
+
+        if let Some(p) = self.point(1)? {
+            if p.segment_distance(exit) < p.segment_distance(entry) {
+                std::mem::swap(&mut entry, &mut exit);
+            }
+        }
+        Tangents::new(entry, exit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transition::unit::Rad;
+
+    #[test]
+    fn 接線を延長して交点を求める() {
+        // 東向きの接線と、交点から北東へ離れる接線
+        let entry = (Point::from((-100.0, 0.0)), Point::from((-10.0, 0.0)));
+        let exit = (Point::from((110.0, 110.0)), Point::from((10.0, 10.0)));
+        let tangents = Tangents::new(entry, exit).unwrap();
+        assert!(tangents.ip.distance(Point::from((0.0, 0.0))) < 1e-9);
+        assert!(tangents.t0.rad().abs() < 1e-9);
+        assert!((tangents.t1.rad() - std::f64::consts::FRAC_PI_4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn 平行な接線はエラー() {
+        let entry = (Point::from((0.0, 0.0)), Point::from((10.0, 0.0)));
+        let exit = (Point::from((0.0, 5.0)), Point::from((10.0, 5.0)));
+        let e = Tangents::new(entry, exit).unwrap_err();
+        assert_eq!(e.to_string(), "2本の接線が平行です");
+    }
+}
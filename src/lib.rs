@@ -1,22 +1,68 @@
 mod agent;
 mod factory;
+mod ip;
+mod route;
 mod track;
 mod transition;
+mod vertical;
 
-use std::{ffi::OsStr};
+use std::ffi::OsStr;
 
 use anyhow::Result;
 
-use agent::{bat::Args};
+use agent::{bat::Args, cli::Command, sheet::Sheet};
 use factory::App;
 
-
 /// 配線する
 pub fn layout(args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Result<()> {
     let args = Args::parse(args)?;
 
     match App::new(&args)? {
-        App::Transition(transition) => transition.draw(),
+        App::Transition(transition) => transition.draw(args.temp_path()?),
+        App::Curve(curve) => curve.draw(args.temp_path()?),
         App::Track(track) => track.export(),
+        App::Route(route) => route.draw(args.temp_path()?),
+        App::Vertical(vertical) => vertical.draw(args.temp_path()?),
+    }
+}
+
+/// コマンドラインから実行する
+///
+/// 引数にプログラム名は含めない。
+pub fn command(args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Result<()> {
+    let Some(command) = Command::parse(args)? else {
+        return Ok(());
+    };
+
+    match App::command(&command)? {
+        App::Transition(transition) => {
+            let mut sheet = command.sheet()?;
+            transition.render(&mut sheet)?;
+            sheet.close()
+        }
+        App::Curve(curve) => {
+            let mut sheet = command.sheet()?;
+            curve.render(&mut sheet)?;
+            sheet.close()
+        }
+        App::Track(track) => {
+            let tracks = track.tracks()?;
+            let relative = tracks.relative()?;
+            if let Some(mut svg) = command.preview()? {
+                tracks.preview(&mut svg)?;
+                svg.close()?;
+            }
+            command.write_track(&tracks.name, &relative)
+        }
+        App::Route(route) => {
+            let mut sheet = command.sheet()?;
+            route.render(&mut sheet)?;
+            sheet.close()
+        }
+        App::Vertical(vertical) => {
+            let mut sheet = command.sheet()?;
+            vertical.render(&mut sheet)?;
+            sheet.close()
+        }
     }
 }
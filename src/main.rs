@@ -3,7 +3,12 @@ use std::env;
 use anyhow::Result;
 
 fn main() -> Result<()> {
-    let args = env::args_os();
+    let args = env::args_os().collect::<Vec<_>>();
     // dbg!(&args);
-    f5rail::layout(args)
+
+    // Jw_cadの外部変形からは "/KEY:VALUE" の形式で呼ばれる。
+    match args.get(1).and_then(|arg| arg.to_str()) {
+        Some(arg) if arg.starts_with('/') => f5rail::layout(args),
+        _ => f5rail::command(args.iter().skip(1)),
+    }
 }
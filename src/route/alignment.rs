@@ -0,0 +1,240 @@
+//! BVEマップの自軌道の平面線形
+
+use crate::{
+    agent::bve::{
+        map::{Map, Statement, Value},
+        Curve,
+    },
+    transition::{
+        canvas::{Point, Stroke},
+        curve::{Curvature, Diminish, Radius, Subtension, Tangential, STRAIGHT},
+        distance::{Distance, Ruler},
+        unit::Meter,
+    },
+};
+
+/// 曲率の変化点
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Node {
+    /// 距離程 (m)
+    z: f64,
+
+    /// 曲率
+    k: Curvature,
+
+    /// 構文の種類
+    kind: Kind,
+
+    /// 次の変化点までを補間するときの逓減関数
+    diminish: Diminish,
+}
+
+/// 曲率の変化点の構文の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// `Curve.BeginTransition` 次の変化点まで補間する。
+    Transition,
+
+    /// `Curve.Begin`, `Curve.End` 次の変化点まで曲率が変わらない。
+    Constant,
+
+    /// `Curve.Interpolate` 前後の変化点との間を補間する。
+    Interpolate,
+}
+
+/// 自軌道の平面線形
+#[derive(Debug)]
+pub struct Alignment {
+    /// 始点の距離程と、始点から順に並べた線
+    strokes: Vec<(f64, Stroke)>,
+}
+
+impl Alignment {
+    /// マップ `map` の自軌道を、距離程 `z0` の点 `p0` から方向 `t0` に `z1` まで描く。
+    ///
+    /// 緩和曲線は1m単位の距離程で区切り、区間中央の曲率の円弧で近似する。
+    pub fn new(map: &Map, (z0, p0, t0): (f64, Point, Tangential), z1: f64) -> Self {
+        let mut strokes = vec![];
+        let mut end = (p0, t0);
+        let mut push = |a: f64, b: f64, k: Curvature| {
+            let (a, b) = (a.max(z0), b.min(z1));
+            if a < b {
+                let stroke = Stroke::new(k, Subtension::from(b - a), end.0, end.1);
+                end = (stroke.p1(), stroke.t1());
+                strokes.push((a, stroke));
+            }
+        };
+
+        let nodes = nodes(map);
+        let first = nodes.first().map_or(f64::INFINITY, |node| node.z);
+        push(f64::NEG_INFINITY, first, STRAIGHT);
+        for (i, node) in nodes.iter().enumerate() {
+            let next = nodes.get(i + 1);
+            let b = next.map_or(f64::INFINITY, |next| next.z);
+            match next {
+                Some(next)
+                    if (node.kind != Kind::Constant || next.kind == Kind::Interpolate)
+                        && !node.k.approx_eq(next.k) =>
+                {
+                    let tcl = Subtension::from(b - node.z);
+                    for interval in Ruler::new(Distance::from(node.z), tcl) {
+                        let k = node.diminish.k(tcl, interval.s(), node.k, next.k);
+                        let a = node.z + interval.start().meter();
+                        push(a, node.z + interval.end().meter(), k);
+                    }
+                }
+                _ => push(node.z, b, node.k),
+            }
+        }
+        Self { strokes }
+    }
+
+    /// 始点から順に並べた線
+    pub fn strokes(&self) -> Vec<Stroke> {
+        self.strokes.iter().map(|(_, stroke)| *stroke).collect()
+    }
+
+    /// 距離程 `z` の座標と接線方向
+    ///
+    /// 描いた範囲の外なら `None`
+    pub fn at(&self, z: f64) -> Option<(Point, Tangential)> {
+        let i = self
+            .strokes
+            .partition_point(|(a, _)| *a <= z)
+            .checked_sub(1)?;
+        let (a, stroke) = self.strokes[i];
+        let s = z - a;
+        if s > stroke.len().meter() + 1e-9 {
+            return None;
+        }
+        let part = Stroke::new(stroke.k(), Subtension::from(s), stroke.p0(), stroke.t0());
+        Some((part.p1(), part.t1()))
+    }
+}
+
+/// マップの `Curve` 構文を距離程の順に並べた、曲率の変化点
+///
+/// `Curve.SetFunction` で逓減関数を選ぶ (0: サイン半波長逓減, 1: 直線逓減)。
+/// 無指定は直線逓減。
+fn nodes(map: &Map) -> Vec<Node> {
+    let mut entries = map.entries.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.z.total_cmp(&b.z));
+
+    let mut nodes: Vec<Node> = vec![];
+    let mut diminish = Diminish::Linear;
+    for entry in entries {
+        let (k, kind) = match &entry.statement {
+            Statement::Curve(Curve::BeginTransition) => {
+                let k = nodes.last().map_or(STRAIGHT, |node| node.k);
+                (k, Kind::Transition)
+            }
+            Statement::Curve(Curve::Begin(r, _)) => (curvature(*r), Kind::Constant),
+            Statement::Curve(Curve::End) => (STRAIGHT, Kind::Constant),
+            Statement::Curve(Curve::Interpolate(r, _)) => (curvature(*r), Kind::Interpolate),
+            Statement::Other { name, args, .. }
+                if name.eq_ignore_ascii_case("Curve.SetFunction") =>
+            {
+                diminish = match args.first() {
+                    Some(Some(Value::Number(id))) if *id == 0.0 => Diminish::Sine,
+                    _ => Diminish::Linear,
+                };
+                continue;
+            }
+            _ => continue,
+        };
+        let node = Node {
+            z: entry.z,
+            k,
+            kind,
+            diminish,
+        };
+        // 同じ距離程の構文は後のものを使う
+        match nodes.last_mut() {
+            Some(last) if last.z == entry.z => *last = node,
+            _ => nodes.push(node),
+        }
+    }
+    nodes
+}
+
+/// BVEの半径から曲率を求める。
+///
+/// 半径0は直線。
+fn curvature(r: f64) -> Curvature {
+    match r == 0.0 {
+        true => STRAIGHT,
+        false => Curvature::from(Radius(r)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use crate::transition::unit::{Rad, Vector};
+
+    use super::*;
+
+    fn 線形(text: &str, z1: f64) -> Alignment {
+        let map = Map::parse(&format!("BveTs Map 2.02\n{text}")).unwrap();
+        Alignment::new(
+            &map,
+            (0.0, Point::from((0.0, 0.0)), Tangential::from(0.0)),
+            z1,
+        )
+    }
+
+    #[test]
+    fn 円曲線を描く() {
+        let text = "100; Curve.Begin(-100); 100 + 100 * atan2(0, -1); Curve.End();";
+        let alignment = 線形(text, 500.0);
+        let strokes = alignment.strokes();
+        assert_eq!(strokes.len(), 3);
+        assert_eq!(strokes[1].r().map(|r| r.meter()), Some(-100.0));
+
+        // 左に半円を描いて戻る
+        let (p, t) = alignment.at(500.0).unwrap();
+        let x = 100.0 - (400.0 - 100.0 * PI);
+        assert!((p.x() - x).abs() < 1e-6, "{p:?}");
+        assert!((p.y() - 200.0).abs() < 1e-6, "{p:?}");
+        assert!((t.rad() - PI).abs() < 1e-9);
+        assert_eq!(alignment.at(500.1), None);
+    }
+
+    #[test]
+    fn 緩和曲線は1mごとに区切る() {
+        let alignment = 線形("10.5; Curve.BeginTransition(); 13; Curve.Begin(400);", 14.0);
+        let k = alignment
+            .strokes()
+            .iter()
+            .map(|s| (s.len().meter(), s.k()))
+            .collect::<Vec<_>>();
+        let k_at = |s: f64| Curvature::from(s / 2.5 / 400.0);
+        assert_eq!(
+            k,
+            [
+                (10.5, STRAIGHT),
+                (0.5, k_at(0.25)),
+                (1.0, k_at(1.0)),
+                (1.0, k_at(2.0)),
+                (1.0, Curvature::from(1.0 / 400.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn 逓減関数を選ぶ() {
+        let text = "Curve.SetFunction(0); 0; Curve.Begin(200); 20; Curve.Interpolate(200); 22; Curve.Interpolate(0); Curve.End();";
+        let k = 線形(text, 30.0)
+            .strokes()
+            .iter()
+            .map(|s| s.k())
+            .collect::<Vec<_>>();
+        assert_eq!(k.len(), 4);
+
+        // サイン半波長逓減で、弧長の1/4の位置の曲率
+        let expected = Curvature::from((0.5 - (-PI / 4.0).sin() / 2.0) / 200.0);
+        assert!(k[1].approx_eq(expected), "{k:?}");
+        assert_eq!(k[3], STRAIGHT);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{f64::consts::FRAC_PI_2, path::PathBuf};
+
+use anyhow::Result;
+
+use crate::{
+    agent::{
+        bve::map::Map,
+        jww::{JwcTemp, Style},
+        sheet::Sheet,
+    },
+    transition::{
+        app::{m, upright},
+        canvas::Point,
+        curve::{Subtension, Tangential},
+        unit::Vector,
+    },
+};
+
+use super::{alignment::Alignment, other::Other};
+
+/// 目盛りの片側の長さ (m)
+const TICK: f64 = 1.0;
+
+/// 外部変形 "ROUTE"
+#[derive(Debug)]
+pub struct Route {
+    /// マップファイルのパスと、読み込んだマップ
+    map: Result<(PathBuf, Map)>,
+
+    /// 始点の距離程・座標・接線方向
+    start: Result<(f64, Point, Tangential)>,
+
+    /// 終点の距離程
+    ///
+    /// `None` ならマップの最後の構文まで。
+    end: Result<Option<f64>>,
+
+    /// 目盛りの間隔 (m) と線の属性
+    drawing: Result<(f64, Style)>,
+}
+
+impl Route {
+    /// マップ `map` の自軌道を `start` から描く。
+    pub fn new(
+        map: Result<(PathBuf, Map)>,
+        start: Result<(f64, Point, Tangential)>,
+        end: Result<Option<f64>>,
+        drawing: Result<(f64, Style)>,
+    ) -> Self {
+        Self {
+            map,
+            start,
+            end,
+            drawing,
+        }
+    }
+
+    /// エントリーポイント
+    ///
+    /// 座標ファイル `file` に出力する。
+    pub fn draw(&self, file: &str) -> Result<()> {
+        self.render(&mut JwcTemp::create(file)?)
+    }
+
+    /// 自軌道と距離程の目盛り、他軌道を `sheet` に出力する。
+    pub fn render(&self, sheet: &mut impl Sheet) -> Result<()> {
+        match (&self.map, &self.start, &self.end, &self.drawing) {
+            (Ok((path, map)), Ok(start), Ok(end), Ok((tick, style))) => {
+                let z0 = start.0;
+                let z1 = match end {
+                    Some(z1) if *z1 <= z0 => {
+                        return sheet.error(&"終点距離程に始点距離程より大きい値を入力してください")
+                    }
+                    Some(z1) => *z1,
+                    None => match map.entries.iter().map(|e| e.z).reduce(f64::max) {
+                        Some(z1) if z1 > z0 => z1,
+                        _ => return sheet.error(&"始点距離程より先に構文がありません"),
+                    },
+                };
+                let alignment = Alignment::new(map, *start, z1);
+                let others = Other::read(map)
+                    .into_iter()
+                    .map(|other| (other.points(&alignment, (z0, z1)), other.name))
+                    .filter(|(points, _)| points.len() >= 2)
+                    .collect::<Vec<_>>();
+                let styles = match style.others(others.len()) {
+                    Ok(styles) => styles,
+                    Err(e) => return sheet.error(&e),
+                };
+
+                sheet.style(style)?;
+                sheet.strokes(&alignment.strokes())?;
+                ticks(sheet, &alignment, (z0, z1), *tick)?;
+                for ((points, name), style) in others.iter().zip(&styles) {
+                    other(sheet, points, name, style)?;
+                }
+                let count = others.len();
+
+                sheet.notice(format!(
+                    "{} の自軌道{}を{}mから{}mまで描画しました。",
+                    path.display(),
+                    match count {
+                        0 => String::new(),
+                        n => format!("と他軌道{n}本"),
+                    },
+                    m(z0),
+                    m(z1)
+                ))
+            }
+            (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+                sheet.error(e)
+            }
+        }
+    }
+}
+
+/// 距離程 `z0` から `z1` まで、`tick` ごとに目盛りと距離程を出力する。
+///
+/// 目盛りは軌道に直交し、距離程は軌道の左に書く。
+fn ticks(
+    sheet: &mut impl Sheet,
+    alignment: &Alignment,
+    (z0, z1): (f64, f64),
+    tick: f64,
+) -> Result<()> {
+    let first = (z0 / tick).ceil() as i64;
+    let last = ((z1 + 1e-9) / tick).floor() as i64;
+    for z in (first..=last).map(|n| n as f64 * tick) {
+        let Some((p, t)) = alignment.at(z) else {
+            continue;
+        };
+        let side = |len: f64, a: f64| p + (Subtension::from(len), t + Tangential::from(a));
+        sheet.straight(&side(TICK, FRAC_PI_2), &side(TICK, -FRAC_PI_2))?;
+        sheet.text(&side(2.0 * TICK, FRAC_PI_2), &upright(t), m(z))?;
+    }
+    Ok(())
+}
+
+/// 他軌道の中心線 `points` を属性 `style` で出力する。
+///
+/// 軌道名 `name` は他軌道の始点に書く。
+fn other(sheet: &mut impl Sheet, points: &[Point], name: &str, style: &Style) -> Result<()> {
+    sheet.style(style)?;
+    for pair in points.windows(2) {
+        sheet.straight(&pair[0], &pair[1])?;
+    }
+    let (p0, p1) = (points[0], points[1]);
+    let t = Tangential::from((p1.y() - p0.y()).atan2(p1.x() - p0.x()));
+    sheet.text(&p0, &upright(t), name)
+}
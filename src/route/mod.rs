@@ -0,0 +1,5 @@
+//! BVEマップからの自軌道・他軌道の作図
+
+pub mod alignment;
+pub mod app;
+pub mod other;
@@ -0,0 +1,193 @@
+//! BVEマップの他軌道の平面位置
+
+use std::f64::consts::FRAC_PI_2;
+
+use crate::{
+    agent::bve::map::{Map, Statement, Track},
+    transition::{
+        canvas::Point,
+        curve::{Subtension, Tangential},
+    },
+};
+
+use super::alignment::Alignment;
+
+/// 他軌道の横位置の変化点
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Node {
+    /// 距離程 (m)
+    z: f64,
+
+    /// 自軌道との距離 (m)
+    ///
+    /// 右が正。
+    x: f64,
+
+    /// 次の変化点までの、自軌道との相対半径 (m)
+    ///
+    /// 右カーブが正。直線は0.
+    r: f64,
+}
+
+/// 他軌道
+#[derive(Debug)]
+pub struct Other {
+    /// 軌道名
+    pub name: String,
+
+    /// 距離程の順に並べた横位置の変化点
+    nodes: Vec<Node>,
+}
+
+impl Other {
+    /// マップ `map` の `Track[].X.Interpolate`, `Track[].Position` 構文から他軌道を読む。
+    ///
+    /// 他軌道はマップに最初に現れた順に並べる。
+    pub fn read(map: &Map) -> Vec<Self> {
+        let mut entries = map.entries.iter().collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.z.total_cmp(&b.z));
+
+        let mut others: Vec<Self> = vec![];
+        for entry in &map.entries {
+            if let Statement::Track { key, .. } = &entry.statement {
+                if !others.iter().any(|other| other.name == *key) {
+                    others.push(Self {
+                        name: key.clone(),
+                        nodes: vec![],
+                    });
+                }
+            }
+        }
+        for entry in entries {
+            let (key, x, r) = match &entry.statement {
+                Statement::Track {
+                    key,
+                    track: Track::X(x, r),
+                } => (key, *x, *r),
+                Statement::Track {
+                    key,
+                    track: Track::Position(x, _, r, _),
+                } => (key, *x, *r),
+                _ => continue,
+            };
+            let Some(other) = others.iter_mut().find(|other| other.name == *key) else {
+                continue;
+            };
+            let node = Node { z: entry.z, x, r };
+            // 同じ距離程の構文は後のものを使う
+            match other.nodes.last_mut() {
+                Some(last) if last.z == entry.z => *last = node,
+                _ => other.nodes.push(node),
+            }
+        }
+        others.retain(|other| !other.nodes.is_empty());
+        others
+    }
+
+    /// 距離程 `z` の自軌道との距離 (m)
+    ///
+    /// 変化点の間は相対半径の円弧、相対半径0なら直線で補間する。
+    /// 最初と最後の変化点の外なら `None`
+    pub fn x(&self, z: f64) -> Option<f64> {
+        let i = self
+            .nodes
+            .partition_point(|node| node.z <= z)
+            .checked_sub(1)?;
+        let node = self.nodes[i];
+        let Some(next) = self.nodes.get(i + 1) else {
+            return (z == node.z).then_some(node.x);
+        };
+        let (len, u) = (next.z - node.z, z - node.z);
+        let chord = node.x + (next.x - node.x) * u / len;
+
+        // 弦からの矢高。右カーブは弦の左にふくらむ。
+        let r = node.r.abs();
+        if r <= len / 2.0 {
+            return Some(chord);
+        }
+        let h = (r * r - (u - len / 2.0).powi(2)).sqrt() - (r * r - (len / 2.0).powi(2)).sqrt();
+        Some(chord - h * node.r.signum())
+    }
+
+    /// 自軌道 `alignment` に沿って、距離程 `z0` から `z1` までの中心線の点を並べる。
+    ///
+    /// 変化点と1mごとの距離程に点をとる。
+    pub fn points(&self, alignment: &Alignment, (z0, z1): (f64, f64)) -> Vec<Point> {
+        let (Some(first), Some(last)) = (self.nodes.first(), self.nodes.last()) else {
+            return vec![];
+        };
+        let (a, b) = (first.z.max(z0), last.z.min(z1));
+        if a > b {
+            return vec![];
+        }
+        let mut zs = (a.ceil() as i64..=b.floor() as i64)
+            .map(|z| z as f64)
+            .chain(self.nodes.iter().map(|node| node.z))
+            .chain([a, b])
+            .filter(|z| (a..=b).contains(z))
+            .collect::<Vec<_>>();
+        zs.sort_by(f64::total_cmp);
+        zs.dedup_by(|z, prev| (*z - *prev).abs() < 1e-9);
+
+        zs.into_iter()
+            .filter_map(|z| {
+                let x = self.x(z)?;
+                let (p, t) = alignment.at(z)?;
+                let normal = t + Tangential::from(-FRAC_PI_2.copysign(x));
+                Some(p + (Subtension::from(x), normal))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transition::unit::Vector;
+
+    use super::*;
+
+    fn 他軌道(text: &str) -> Vec<Other> {
+        Other::read(&Map::parse(&format!("BveTs Map 2.02\n{text}")).unwrap())
+    }
+
+    #[test]
+    fn 軌道名ごとに変化点を並べる() {
+        let text = "20; Track['b'].Position(-3.8, 0); Track['a'].X.Interpolate(3.8, 0); 0; Track['a'].X.Interpolate(0, 0); 20; Track['a'].X.Interpolate(4.0);";
+        let others = 他軌道(text);
+        assert_eq!(
+            others.iter().map(|o| o.name.as_str()).collect::<Vec<_>>(),
+            ["b", "a"]
+        );
+        assert_eq!(others[1].x(10.0), Some(2.0));
+        assert_eq!(others[1].x(20.0), Some(4.0));
+        assert_eq!(others[1].x(20.1), None);
+        assert_eq!(others[1].x(-0.1), None);
+        assert_eq!(others[0].x(20.0), Some(-3.8));
+    }
+
+    #[test]
+    fn 相対半径の円弧で補間する() {
+        let others =
+            他軌道("0; Track['a'].X.Interpolate(0, 100); 20; Track['a'].X.Interpolate(0);");
+        let h = 100.0 - (100.0f64.powi(2) - 10.0f64.powi(2)).sqrt();
+        assert!((others[0].x(10.0).unwrap() + h).abs() < 1e-9);
+    }
+
+    #[test]
+    fn 右が正の位置に描く() {
+        let map = Map::parse(
+            "BveTs Map 2.02\n0; Track['a'].X.Interpolate(3.8); 10; Track['a'].X.Interpolate(-2);",
+        )
+        .unwrap();
+        let alignment = Alignment::new(
+            &map,
+            (0.0, Point::from((0.0, 0.0)), Tangential::from(0.0)),
+            10.0,
+        );
+        let points = Other::read(&map)[0].points(&alignment, (0.0, 10.0));
+        assert_eq!(points.len(), 11);
+        assert!((points[0].y() + 3.8).abs() < 1e-9, "{points:?}");
+        assert!((points[10].x() - 10.0).abs() < 1e-9, "{points:?}");
+        assert!((points[10].y() - 2.0).abs() < 1e-9, "{points:?}");
+    }
+}
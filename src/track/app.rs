@@ -1,14 +1,25 @@
-use std::path::Path;
+use std::{
+    f64::consts::TAU,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Error, Result};
+use anyhow::{bail, Context, Error, Result};
 
 use crate::agent::{
     bat::Args,
-    bve::{MapFile, MapPath},
-    jww::{self, JwcTemp},
+    bve::{merge, Format, MapFile, MapPath},
+    dxf,
+    jww::{self, Figure, JwcTemp},
+    sheet::Sheet,
+    svg,
 };
 
-use super::relative::{Polyline, Relative_};
+use crate::transition::canvas::Point;
+
+use super::{
+    polyline::{Polyline, Stroke},
+    relative::{Relative, Ruler},
+};
 
 #[derive(Debug)]
 /// 外部変形 "TRACK"
@@ -27,7 +38,7 @@ impl<'a> Track<'a> {
     /// それ以外のエラーはJWC_TEMPファイルに出力される。
     pub fn export(&self) -> Result<()> {
         match self.make_map_file() {
-            Ok(map_path) => self.show_map_path(&map_path),
+            Ok(message) => self.show_notice(message),
             Err(e) => self.show_err(&e),
         }
     }
@@ -35,32 +46,107 @@ impl<'a> Track<'a> {
     /// 他線座標をBVEマップに出力する。
     ///
     /// ファイル入出力を行なう。それ以外は下層へ移譲する。
-    fn make_map_file(&self) -> Result<MapPath> {
-        // トラック名と図形を読み取る
+    /// 成功したら、出力したファイルを知らせるメッセージを返す。
+    fn make_map_file(&self) -> Result<String> {
+        let tracks = self.tracks()?;
+        let (track_name, relative) = (&tracks.name, tracks.relative()?);
+
+        // マップファイルに書き込む
         let mut temp_file = JwcTemp::open(self.args.temp_path()?)?;
-        let _temp_0_file = JwcTemp::open(self.args.temp_0_path()?)?;
-        let mut temp_x_file = JwcTemp::open(self.args.temp_x_path()?)?;
-        let _track_name = temp_x_file.track_name();
-        // let track_0 = temp_0_file.read_polyline()?;
-        // let track_x = temp_x_file.read_polyline()?;
+        if self.args.merge()? {
+            let map_path = MapPath::existing(self.args.map_name(), || temp_file.project_dir())?;
+            let (path, backup) =
+                merge::merge_track(&map_path, &self.args.map_format()?, track_name, &relative)?;
+            return Ok(format!(
+                "{} に差し込みました (控え: {})",
+                path.display(),
+                backup.display()
+            ));
+        }
+        let map_path = MapPath::build(self.args.map_name(), || temp_file.project_dir())?;
+        let format = Format {
+            source: Some(PathBuf::from(temp_file.project_path()?)),
+            ..self.args.map_format()?
+        };
+        let mut map_file = MapFile::create(&map_path, format)?;
+        map_file.write_track(track_name, &relative)?;
+
+        Ok(format!("{} を作成しました", map_path.display()))
+    }
 
-        // 始点を読み込む
+    /// 座標ファイルから、トラック名と自軌道・他軌道・測距の条件を読み取る。
+    ///
+    /// 拡張子が .dxf のファイルは DXF 形式として読み込む。
+    pub fn tracks(&self) -> Result<Tracks> {
+        // トラック名と図形を読み取る
+        let (name, track_x) = self.other_track()?;
+        let (strokes, start, z0) = self.own_track()?;
+        let track_0 = Polyline::chain(strokes, start)?;
+        let ruler = Ruler {
+            start,
+            z0,
+            interval: self.args.interval()?,
+        };
+        Ok(Tracks {
+            name,
+            track_0,
+            track_x,
+            ruler,
+        })
+    }
 
-        // 相対座標を計算する
-        // let _ = Relative_::between(&track_0, &track_x)?;
+    /// 自軌道の線と、出力始点とその距離程
+    fn own_track(&self) -> Result<(Vec<Stroke>, Point, f64)> {
+        let path = self.args.temp_0_path()?;
+        if !is_dxf(path) {
+            let mut temp_0_file = JwcTemp::open(path)?;
+            let start = temp_0_file
+                .point(1)?
+                .context("出力始点を指定してください")?;
+            return Ok((
+                temp_0_file.read_strokes()?,
+                start,
+                temp_0_file.start_distance()?,
+            ));
+        }
 
-        // マップファイルに書き込む
-        let map_path = MapPath::build(self.args.map_name(), || temp_file.project_dir())?;
-        let _map_file = MapFile::create(&map_path)?;
-        // map_file.write_track(track_name, &relative)?;
+        // DXFには指示点がないので、引数か点 (POINT) で出力始点を指定する
+        let dxf = dxf::Read::open(path)?;
+        let figures = dxf
+            .entities(self.args.own_layer())
+            .map(|entity| (entity.line, &entity.figure))
+            .collect::<Vec<_>>();
+        let start = match self.args.start()? {
+            Some(start) => Some(start),
+            None => figures.iter().find_map(|(_, figure)| match figure {
+                Figure::Dot(p) => Some(*p),
+                _ => None,
+            }),
+        }
+        .context("出力始点を指定してください")?;
+        Ok((strokes(figures)?, start, self.args.start_distance()?))
+    }
 
-        Ok(map_path)
+    /// 他軌道のトラック名と線
+    fn other_track(&self) -> Result<(String, Polyline)> {
+        let path = self.args.temp_x_path()?;
+        if !is_dxf(path) {
+            let mut temp_x_file = JwcTemp::open(path)?;
+            let track_name = temp_x_file.track_name()?.to_string();
+            return Ok((track_name, temp_x_file.read_polyline()?));
+        }
+
+        let dxf = dxf::Read::open(path)?;
+        let figures = dxf
+            .entities(self.args.other_layer())
+            .map(|entity| (entity.line, &entity.figure));
+        let track_x = strokes(figures)?.into_iter().collect();
+        Ok((self.args.track_name().to_string(), track_x))
     }
 
     /// 成功メッセージをJWC_TEMPファイルに出力する。
-    fn show_map_path(&self, path: &(impl AsRef<Path> + ?Sized)) -> Result<()> {
-        self.create_temp_file()?
-            .notice(format!("{} を作成しました", path.as_ref().display()))
+    fn show_notice(&self, message: String) -> Result<()> {
+        self.create_temp_file()?.notice(message)
     }
 
     /// エラーをJWC_TEMPファイルに出力する。
@@ -74,12 +160,73 @@ impl<'a> Track<'a> {
     }
 }
 
-impl jww::Cache {
-    fn read_polyline(&self) -> Result<Polyline> {
-        todo!()
+#[derive(Debug)]
+/// 読み取ったトラック名と、自軌道・他軌道・測距の条件
+pub struct Tracks {
+    pub name: String,
+    track_0: Polyline,
+    track_x: Polyline,
+    ruler: Ruler,
+}
+
+impl Tracks {
+    /// 他線の相対座標を求める。
+    pub fn relative(&self) -> Result<Vec<Relative>> {
+        Relative::between(&self.track_0, &self.track_x, &self.ruler)
+    }
+
+    /// 自軌道と他軌道を `svg` に描く。
+    ///
+    /// 自軌道には距離程の目盛りと曲率図をつける。
+    pub fn preview(&self, svg: &mut svg::Write) -> Result<()> {
+        let s0 = self.ruler.origin(&self.track_0)?;
+        svg.polyline("自軌道", &self.track_0, Some(self.ruler.z0 - s0));
+        svg.polyline(&format!("他軌道 {}", self.name), &self.track_x, None);
+        Ok(())
+    }
+}
+
+impl jww::Read {
+    /// 選択された図形を軌道の線として読み取る。
+    fn read_strokes(&mut self) -> Result<Vec<Stroke>> {
+        strokes(
+            self.shapes()?
+                .iter()
+                .map(|shape| (shape.line, &shape.figure)),
+        )
+    }
+
+    /// 選択された図形を繋がずに読み取る。
+    fn read_polyline(&mut self) -> Result<Polyline> {
+        Ok(self.read_strokes()?.into_iter().collect())
+    }
+}
+
+/// 行番号つきの図形を軌道の線にする。
+///
+/// 点と文字は無視する。
+fn strokes<'a>(figures: impl IntoIterator<Item = (usize, &'a Figure)>) -> Result<Vec<Stroke>> {
+    let mut strokes = vec![];
+    for (line, figure) in figures {
+        match *figure {
+            Figure::Straight(p0, p1) => strokes.push(Stroke::Straight { p0, p1 }),
+            Figure::Curve { c, r, a0, a1 } => {
+                let a0 = a0.0.to_radians();
+                let sweep = (a1.0.to_radians() - a0).rem_euclid(TAU);
+                strokes.push(Stroke::Curve { c, r, a0, sweep });
+            }
+            Figure::Circle { .. } | Figure::Ellipse { .. } => {
+                bail!("{line}行目の円・楕円は軌道にできません")
+            }
+            Figure::Dot(_) | Figure::Text { .. } => (),
+        }
     }
+    Ok(strokes)
 }
 
-trait WriteRelative {
-    fn write(&self, relative: &Relative_) -> Result<()>;
+/// DXFファイルなら `true`
+fn is_dxf(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("dxf"))
 }
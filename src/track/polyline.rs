@@ -0,0 +1,367 @@
+use std::f64::consts::TAU;
+
+use anyhow::{bail, Result};
+
+use crate::transition::{canvas::Point, unit::Vector};
+
+/// 同一点とみなす距離 (m)
+const TOLERANCE: f64 = 0.001;
+
+/// 軌道の線形
+///
+/// 線を始点から順に並べたもの。
+#[derive(Debug, Default)]
+pub struct Polyline {
+    v: Vec<Stroke>,
+}
+
+impl Polyline {
+    /// 線を繋いで軌道にする。
+    ///
+    /// 端点のうち `start` に最も近い方から辿っていく。長さのない線は取り除く。
+    pub fn chain(strokes: impl IntoIterator<Item = Stroke>, start: Point) -> Result<Self> {
+        // 長さのない線は向きが決まらないので使わない
+        let mut rest = strokes
+            .into_iter()
+            .filter(|s| s.length() >= TOLERANCE)
+            .collect::<Vec<_>>();
+        if rest.is_empty() {
+            return Ok(Self::default());
+        }
+
+        // 他の線と繋がっていない端点
+        let is_end = |p: Point, rest: &[Stroke]| {
+            rest.iter()
+                .flat_map(|s| [s.p0(), s.p1()])
+                .filter(|q| p.distance(*q) < TOLERANCE)
+                .count()
+                == 1
+        };
+        let ends = rest
+            .iter()
+            .enumerate()
+            .flat_map(|(i, s)| [(i, s.p0(), false), (i, s.p1(), true)])
+            .filter(|(_, p, _)| is_end(*p, &rest))
+            .collect::<Vec<_>>();
+        let first = ends
+            .into_iter()
+            .min_by(|a, b| a.1.distance(start).total_cmp(&b.1.distance(start)));
+        let Some((i, _, reverse)) = first else {
+            bail!("軌道の端点が見つかりません");
+        };
+
+        let stroke = rest.swap_remove(i);
+        let mut v = vec![if reverse { stroke.reverse() } else { stroke }];
+        while let Some(p1) = v.last().map(Stroke::p1) {
+            let next = rest.iter().enumerate().find_map(|(i, s)| {
+                if s.p0().distance(p1) < TOLERANCE {
+                    Some((i, false))
+                } else if s.p1().distance(p1) < TOLERANCE {
+                    Some((i, true))
+                } else {
+                    None
+                }
+            });
+            let Some((i, reverse)) = next else { break };
+            let stroke = rest.swap_remove(i);
+            v.push(if reverse { stroke.reverse() } else { stroke });
+        }
+
+        if !rest.is_empty() {
+            bail!("軌道が途切れています");
+        }
+        Ok(Self { v })
+    }
+
+    /// 線がなければ `true`
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
+
+    /// 線
+    pub fn iter(&self) -> impl Iterator<Item = &Stroke> {
+        self.v.iter()
+    }
+
+    /// 全長
+    pub fn length(&self) -> f64 {
+        self.v.iter().map(Stroke::length).sum()
+    }
+
+    /// 始点から各線の終点までの長さ
+    pub fn joints(&self) -> Vec<f64> {
+        self.v
+            .iter()
+            .scan(0.0, |s, stroke| {
+                *s += stroke.length();
+                Some(*s)
+            })
+            .collect()
+    }
+
+    /// 始点から長さ `s` の位置にある線と、その線の始点からの長さ
+    ///
+    /// 線の境界では後ろの線を返す。
+    pub fn locate(&self, s: f64) -> Option<(&Stroke, f64)> {
+        let mut s0 = 0.0;
+        for (i, stroke) in self.v.iter().enumerate() {
+            let s1 = s0 + stroke.length();
+            let is_last = i + 1 == self.v.len();
+            if s < s1 || (is_last && s <= s1 + TOLERANCE) {
+                return (s >= s0 - TOLERANCE).then(|| (stroke, (s - s0).max(0.0)));
+            }
+            s0 = s1;
+        }
+        None
+    }
+
+    /// 点 `p` から最も近い線上の位置の、始点からの長さ
+    pub fn project(&self, p: Point) -> Option<f64> {
+        self.v
+            .iter()
+            .zip(std::iter::once(0.0).chain(self.joints()))
+            .filter_map(|(stroke, s0)| stroke.project(p).map(|s| (s0 + s, stroke.foot(p, s))))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(s, _)| s)
+    }
+}
+
+impl FromIterator<Stroke> for Polyline {
+    /// 線を繋がずに集める。
+    fn from_iter<T: IntoIterator<Item = Stroke>>(iter: T) -> Self {
+        Self {
+            v: Vec::from_iter(iter),
+        }
+    }
+}
+
+/// 軌道を構成する線
+///
+/// 向きを持つ。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stroke {
+    /// 直線
+    Straight {
+        /// 始点
+        p0: Point,
+
+        /// 終点
+        p1: Point,
+    },
+
+    /// 円弧
+    Curve {
+        /// 中心点
+        c: Point,
+
+        /// 半径
+        r: f64,
+
+        /// 始点の中心角 (rad)
+        a0: f64,
+
+        /// 始点から終点までの中心角 (rad)
+        ///
+        /// 反時計回りが正。
+        sweep: f64,
+    },
+}
+
+impl Stroke {
+    /// 始点
+    pub fn p0(&self) -> Point {
+        self.at(0.0).0
+    }
+
+    /// 終点
+    pub fn p1(&self) -> Point {
+        self.at(self.length()).0
+    }
+
+    /// 長さ
+    pub fn length(&self) -> f64 {
+        match *self {
+            Stroke::Straight { p0, p1 } => p0.distance(p1),
+            Stroke::Curve { r, sweep, .. } => r * sweep.abs(),
+        }
+    }
+
+    /// 曲率
+    ///
+    /// 右カーブが正、左カーブが負。
+    pub fn k(&self) -> f64 {
+        match *self {
+            Stroke::Straight { .. } => 0.0,
+            Stroke::Curve { r, sweep, .. } => -sweep.signum() / r,
+        }
+    }
+
+    /// 始点から長さ `s` の位置の座標と接線方向 (rad)
+    pub fn at(&self, s: f64) -> (Point, f64) {
+        match *self {
+            Stroke::Straight { p0, p1 } => {
+                let t = (p1.y() - p0.y()).atan2(p1.x() - p0.x());
+                (p0 + Point::from((s * t.cos(), s * t.sin())), t)
+            }
+            Stroke::Curve { c, r, a0, sweep } => {
+                let a = a0 + sweep.signum() * s / r;
+                let t = a + sweep.signum() * TAU / 4.0;
+                (c + Point::from((r * a.cos(), r * a.sin())), t)
+            }
+        }
+    }
+
+    /// 逆向きの線
+    pub fn reverse(&self) -> Self {
+        match *self {
+            Stroke::Straight { p0, p1 } => Stroke::Straight { p0: p1, p1: p0 },
+            Stroke::Curve { c, r, a0, sweep } => Stroke::Curve {
+                c,
+                r,
+                a0: a0 + sweep,
+                sweep: -sweep,
+            },
+        }
+    }
+
+    /// 点 `p` から線に下ろした垂線の足の、始点からの長さ
+    ///
+    /// 足が線上になければ `None`
+    pub fn project(&self, p: Point) -> Option<f64> {
+        let s = match *self {
+            Stroke::Straight { p0, p1 } => {
+                let len = p0.distance(p1);
+                ((p.x() - p0.x()) * (p1.x() - p0.x()) + (p.y() - p0.y()) * (p1.y() - p0.y())) / len
+            }
+            Stroke::Curve { c, r, a0, sweep } => {
+                let a = (p.y() - c.y()).atan2(p.x() - c.x());
+                r * self.sweep_to(a0, sweep, a)?
+            }
+        };
+        (-TOLERANCE..=self.length() + TOLERANCE)
+            .contains(&s)
+            .then(|| s.clamp(0.0, self.length()))
+    }
+
+    /// 点 `p` と線上の長さ `s` の位置との距離
+    fn foot(&self, p: Point, s: f64) -> f64 {
+        p.distance(self.at(s).0)
+    }
+
+    /// 直線 `o + u * (cos t, sin t)` との交点の `u` と、交点の始点からの長さ
+    pub fn intersect(&self, o: Point, t: f64) -> Vec<(f64, f64)> {
+        let (dx, dy) = (t.cos(), t.sin());
+        let candidates = match *self {
+            Stroke::Straight { p0, p1 } => {
+                let len = p0.distance(p1);
+                let (ex, ey) = ((p1.x() - p0.x()) / len, (p1.y() - p0.y()) / len);
+                let det = ex * dy - ey * dx;
+                if det.abs() < f64::EPSILON {
+                    return vec![];
+                }
+                let (wx, wy) = (o.x() - p0.x(), o.y() - p0.y());
+                let u = (ey * wx - ex * wy) / det;
+                let s = (dy * wx - dx * wy) / det;
+                vec![(u, s)]
+            }
+            Stroke::Curve { c, r, a0, sweep } => {
+                let (wx, wy) = (o.x() - c.x(), o.y() - c.y());
+                let b = dx * wx + dy * wy;
+                let d = b * b - (wx * wx + wy * wy - r * r);
+                if d < 0.0 {
+                    return vec![];
+                }
+                [-b - d.sqrt(), -b + d.sqrt()]
+                    .into_iter()
+                    .filter_map(|u| {
+                        let a = (wy + u * dy).atan2(wx + u * dx);
+                        self.sweep_to(a0, sweep, a).map(|a| (u, r * a))
+                    })
+                    .collect()
+            }
+        };
+        candidates
+            .into_iter()
+            .filter(|(_, s)| (-TOLERANCE..=self.length() + TOLERANCE).contains(s))
+            .collect()
+    }
+
+    /// 円弧の始点から中心角 `a` までの中心角の大きさ
+    ///
+    /// 円弧上になければ `None`
+    fn sweep_to(&self, a0: f64, sweep: f64, a: f64) -> Option<f64> {
+        let da = (sweep.signum() * (a - a0)).rem_euclid(TAU);
+        let margin = TOLERANCE / self.length().max(TOLERANCE);
+        if da <= sweep.abs() + margin {
+            Some(da)
+        } else if da >= TAU - margin {
+            Some(0.0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    fn 直線(x0: f64, y0: f64, x1: f64, y1: f64) -> Stroke {
+        Stroke::Straight {
+            p0: (x0, y0).into(),
+            p1: (x1, y1).into(),
+        }
+    }
+
+    #[test]
+    fn 逆向きの線を繋げる() {
+        let strokes = vec![直線(10.0, 0.0, 20.0, 0.0), 直線(10.0, 0.0, 0.0, 0.0)];
+        let polyline = Polyline::chain(strokes, (25.0, 1.0).into()).unwrap();
+        assert_eq!(polyline.iter().count(), 2);
+        assert_eq!(
+            polyline.iter().next().unwrap().p0(),
+            Point::from((20.0, 0.0))
+        );
+        assert_eq!(polyline.length(), 20.0);
+    }
+
+    #[test]
+    fn 長さのない線は取り除く() {
+        let strokes = vec![
+            直線(0.0, 0.0, 0.0, 0.0),
+            直線(0.0, 0.0, 10.0, 0.0),
+            直線(10.0, 0.0, 10.0, 0.0),
+            直線(10.0, 0.0, 20.0, 0.0),
+        ];
+        let polyline = Polyline::chain(strokes, (0.0, 0.0).into()).unwrap();
+        assert_eq!(polyline.iter().count(), 2);
+        assert_eq!(polyline.project((10.0, 1.0).into()), Some(10.0));
+    }
+
+    #[test]
+    fn 途切れた線はエラー() {
+        let strokes = vec![直線(0.0, 0.0, 10.0, 0.0), 直線(11.0, 0.0, 20.0, 0.0)];
+        let e = Polyline::chain(strokes, (0.0, 0.0).into()).unwrap_err();
+        assert_eq!(e.to_string(), "軌道が途切れています");
+    }
+
+    #[test]
+    fn 円弧の曲率と座標() {
+        // 反時計回りの円弧は左カーブ
+        let curve = Stroke::Curve {
+            c: (0.0, 0.0).into(),
+            r: 100.0,
+            a0: -PI / 2.0,
+            sweep: PI / 2.0,
+        };
+        assert_eq!(curve.k(), -0.01);
+        assert!(curve.p1().distance((100.0, 0.0).into()) < 1e-9);
+        assert_eq!(curve.reverse().k(), 0.01);
+
+        let hits = curve.intersect((0.0, -50.0).into(), 0.0);
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].0 - 75.0_f64.sqrt() * 10.0).abs() < 1e-9);
+    }
+}
@@ -1,83 +1,243 @@
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
+
+use crate::transition::canvas::Point;
+
+use super::polyline::Polyline;
+
+/// 直線とみなす曲率 (1/m)
+const STRAIGHT: f64 = 1e-6;
 
 /// BVE相対座標
-pub struct Relative();
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relative {
+    /// 距離程 (m)
+    pub z: f64,
+
+    /// 自線との距離 (m)
+    ///
+    /// 右が正。
+    pub x: f64,
+
+    /// 次の測距点までの、自線との相対半径 (m)
+    ///
+    /// 右カーブが正。直線は0.
+    pub r: f64,
+}
 
-// impl IntoIterator for Relative {
-//     type Item = ();
+/// 測距の条件
+#[derive(Debug, Clone, Copy)]
+pub struct Ruler {
+    /// 出力始点
+    pub start: Point,
 
-//     type IntoIter;
+    /// 出力始点の距離程 (m)
+    pub z0: f64,
 
-//     fn into_iter(self) -> Self::IntoIter {
-//         todo!()
-//     }
-// }
+    /// 測距点の間隔 (m)
+    pub interval: f64,
+}
 
-pub struct Relative_ {
-    z: Trip,    // 距離程
-    x: Spacing, // 自線との距離
-    r: f64,     // 自線との相対半径
+impl Ruler {
+    /// 出力始点の、自軌道の始点からの長さ
+    pub fn origin(&self, track_0: &Polyline) -> Result<f64> {
+        track_0
+            .project(self.start)
+            .context("出力始点が自軌道上にありません")
+    }
 }
 
-impl Relative_ {
+impl Relative {
     /// 他線のBVE相対座標を計算する。
     ///
-    /// エラーが出ないようにしたい。
-    pub fn between(_track_0: &Polyline, _track_x: &Polyline) -> Result<Vec<Relative_>> {
-        // 測距点の距離程を決める (等間隔 or 変化点)
-        // 測距点毎に、自線～他線のスペースを算出する
-        // 距離程とスペースをマップに出力する
+    /// 他線が自線と並んでいない測距点は出力しない。
+    pub fn between(track_0: &Polyline, track_x: &Polyline, ruler: &Ruler) -> Result<Vec<Self>> {
+        ensure!(!track_0.is_empty(), "自軌道を指定してください");
+        ensure!(!track_x.is_empty(), "他軌道を指定してください");
 
-        todo!()
+        // 測距点の距離程を決める (等間隔 + 変化点)
+        let s0 = ruler.origin(track_0)?;
+        let points = measuring_points(track_0, track_x, s0, ruler.interval);
+
+        // 測距点毎に、自線～他線のスペースを算出する
+        let spacings = points
+            .iter()
+            .map(|&s| spacing(track_0, track_x, s))
+            .collect::<Vec<_>>();
+
+        // 次の測距点までの相対半径を算出する
+        let relative = points
+            .iter()
+            .zip(&spacings)
+            .enumerate()
+            .filter_map(|(i, (&s, x))| {
+                let x = (*x)?;
+                let r = points
+                    .get(i + 1)
+                    .and_then(|&s1| relative_radius(track_0, track_x, (s + s1) / 2.0))
+                    .unwrap_or(0.0);
+                let z = ruler.z0 + s - s0;
+                Some(Self { z, x, r })
+            })
+            .collect::<Vec<_>>();
+
+        ensure!(!relative.is_empty(), "他軌道が自軌道と並んでいません");
+        Ok(relative)
     }
 }
 
-pub struct Polyline {
-    v: Vec<Stroke>,
+/// 測距点の自線始点からの長さ
+///
+/// 出力始点から等間隔の点と、自線・他線の変化点。
+fn measuring_points(track_0: &Polyline, track_x: &Polyline, s0: f64, interval: f64) -> Vec<f64> {
+    let end = track_0.length();
+    let regular = (0..)
+        .map(|i| s0 + interval * f64::from(i))
+        .take_while(|s| *s < end);
+    let joints_0 = track_0.joints();
+    let joints_x = track_x
+        .iter()
+        .flat_map(|stroke| [stroke.p0(), stroke.p1()])
+        .filter_map(|p| track_0.project(p));
+
+    let mut points = regular
+        .chain(joints_0)
+        .chain(joints_x)
+        .filter(|s| (s0..=end).contains(s))
+        .collect::<Vec<_>>();
+    points.sort_by(f64::total_cmp);
+    points.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+    points
 }
 
-pub enum Stroke {
-    Straight,
-    Curve,
+/// 自線始点から長さ `s` の位置での、自線と他線の距離
+///
+/// 右が正。最も近い他線を選ぶ。
+fn spacing(track_0: &Polyline, track_x: &Polyline, s: f64) -> Option<f64> {
+    crossing(track_0, track_x, s).map(|(x, _)| x)
 }
 
-impl Stroke {
-    // 自線.anchor() -> 自線上の座標、距離程
-    // 他線.anchor() -> 他線上の座標
-    fn anchor(&self) -> &Anchor {
-        todo!()
-    }
-
-    // 自線.trip(自線アンカー) -> 距離程
-    fn trip(&self, _p: &Anchor) -> Option<f64> {
-        todo!()
-    }
+/// 自線始点から長さ `s` の位置での、他線との相対半径
+///
+/// 自線と同心円なら直線 (0) になる。
+fn relative_radius(track_0: &Polyline, track_x: &Polyline, s: f64) -> Option<f64> {
+    let (stroke_0, _) = track_0.locate(s)?;
+    let (x, k_x) = crossing(track_0, track_x, s)?;
+    let k_0 = stroke_0.k();
+
+    // 自線から x 離れた平行線の曲率
+    let k_parallel = k_0 / (1.0 - k_0 * x);
+    let k = k_x - k_parallel;
+    Some(if k.abs() < STRAIGHT { 0.0 } else { k.recip() })
 }
 
-struct Anchor {
-    x: f64,
-    y: f64,
+/// 自線始点から長さ `s` の位置で、自線の法線と交わる他線
+///
+/// 自線からの距離と、自線と同じ向きでの他線の曲率を返す。
+fn crossing(track_0: &Polyline, track_x: &Polyline, s: f64) -> Option<(f64, f64)> {
+    let (stroke_0, s_local) = track_0.locate(s)?;
+    let (p, t) = stroke_0.at(s_local);
+
+    // 右向きの法線
+    let normal = t - std::f64::consts::FRAC_PI_2;
+
+    track_x
+        .iter()
+        .flat_map(|stroke_x| {
+            stroke_x.intersect(p, normal).into_iter().map(|(u, s_x)| {
+                // 他線が逆向きなら曲率の符号を反転する
+                let (_, t_x) = stroke_x.at(s_x);
+                let k_x = match (t_x - t).cos() < 0.0 {
+                    true => -stroke_x.k(),
+                    false => stroke_x.k(),
+                };
+                (u, k_x)
+            })
+        })
+        .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
 }
 
-impl Anchor {
-    // 自線アンカー.between(他線) -> X, Z
-    // 他線アンカー.between(自線) -> X, Z
-    fn between(&self, _stroke: &Stroke) -> Option<(Spacing, Trip)> {
-        // TODO: 点と直線・円弧の距離
-        // TODO: 距離程も計算
-        // - 自線アンカー ->
-        todo!()
+#[cfg(test)]
+mod test {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::track::polyline::Stroke;
+
+    fn ruler(interval: f64) -> Ruler {
+        Ruler {
+            start: (0.0, 0.0).into(),
+            z0: 100.0,
+            interval,
+        }
     }
 
-    // fn nearest_
+    fn 直線(x0: f64, y0: f64, x1: f64, y1: f64) -> Stroke {
+        Stroke::Straight {
+            p0: (x0, y0).into(),
+            p1: (x1, y1).into(),
+        }
+    }
 
-    // 他線アンカー.trip_on(自線)
-    fn trip_on(&self, _stroke: &Stroke) -> Option<f64> {
-        // TODO: 最も近い直線・円弧上の点
-        todo!()
+    #[test]
+    fn 平行な直線() {
+        let track_0 = Polyline::from_iter([直線(0.0, 0.0, 50.0, 0.0)]);
+        let track_x = Polyline::from_iter([直線(50.0, -3.8, 0.0, -3.8)]);
+        let relative = Relative::between(&track_0, &track_x, &ruler(25.0)).unwrap();
+        let expected = [(100.0, 3.8, 0.0), (125.0, 3.8, 0.0), (150.0, 3.8, 0.0)];
+        assert_eq!(relative.len(), expected.len());
+        for (actual, (z, x, r)) in relative.iter().zip(expected) {
+            assert!((actual.z - z).abs() < 1e-9);
+            assert!((actual.x - x).abs() < 1e-9);
+            assert_eq!(actual.r, r);
+        }
     }
-}
 
-struct Spacing(f64);
+    #[test]
+    fn 同心円は相対半径0() {
+        // 右カーブ R=400 と、その内側 4m の他線
+        let curve = |r: f64| Stroke::Curve {
+            c: (0.0, -400.0).into(),
+            r,
+            a0: PI / 2.0,
+            sweep: -0.1,
+        };
+        let track_0 = Polyline::from_iter([curve(400.0)]);
+        let track_x = Polyline::from_iter([curve(396.0)]);
+        let relative = Relative::between(&track_0, &track_x, &ruler(10.0)).unwrap();
+        assert!(relative
+            .iter()
+            .all(|r| (r.x - 4.0).abs() < 1e-6 && r.r == 0.0));
+    }
 
-struct Trip(f64);
+    #[test]
+    fn 直線から分岐する他線() {
+        // 自線は直線、他線は右へ R=200 で離れていく
+        let track_0 = Polyline::from_iter([直線(0.0, 0.0, 40.0, 0.0)]);
+        let track_x = Polyline::from_iter([Stroke::Curve {
+            c: (0.0, -200.0).into(),
+            r: 200.0,
+            a0: PI / 2.0,
+            sweep: -0.2,
+        }]);
+        let relative = Relative::between(&track_0, &track_x, &ruler(20.0)).unwrap();
+        assert!(relative[0].x.abs() < 1e-9);
+        assert!((relative[0].r - 200.0).abs() < 1e-6);
+        assert!(relative[1].x > 0.0);
+    }
+
+    #[test]
+    fn 出力始点が自軌道になければエラー() {
+        let track_0 = Polyline::from_iter([直線(10.0, 0.0, 50.0, 0.0)]);
+        let track_x = Polyline::from_iter([直線(10.0, -3.8, 50.0, -3.8)]);
+        let e = Relative::between(&track_0, &track_x, &ruler(25.0)).unwrap_err();
+        assert_eq!(e.to_string(), "出力始点が自軌道上にありません");
+    }
+
+    #[test]
+    fn 並んでいなければエラー() {
+        let track_0 = Polyline::from_iter([直線(0.0, 0.0, 10.0, 0.0)]);
+        let track_x = Polyline::from_iter([直線(20.0, 5.0, 30.0, 5.0)]);
+        let e = Relative::between(&track_0, &track_x, &ruler(5.0)).unwrap_err();
+        assert_eq!(e.to_string(), "他軌道が自軌道と並んでいません");
+    }
+}
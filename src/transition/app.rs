@@ -2,56 +2,213 @@ use std::fmt::Display;
 
 use anyhow::Result;
 
-use crate::agent::jww::JwcTemp;
+use crate::agent::{
+    bve::{Format, MapFile, MapPath},
+    jww::JwcTemp,
+    sheet::Sheet,
+};
 
-use super::{curve::Diminish, unit::Meter, Param};
+use super::{
+    canvas::Spiral,
+    continuity,
+    curve::{Curvature, Diminish, Subtension, Tangential},
+    exact,
+    param::Drawing,
+    unit::{Meter, Rad},
+    Param,
+};
 
 /// 外部変形 "TRANSITION"
 #[derive(Debug)]
 pub struct Transition {
-    file: String,
-    param: Result<Param>,
+    params: Result<Vec<Param>>,
+    drawing: Result<Drawing>,
+    map: Result<Option<(MapPath, Format)>>,
 }
 
 impl Transition {
-    pub fn new(file: &str, param: Result<Param>) -> Self {
-        let file = file.to_string();
-        Self { file, param }
+    /// 区間 `params` を順につないで描画する。
+    ///
+    /// `map` があれば、その書式でBVEマップも出力する。
+    pub fn new(
+        params: Result<Vec<Param>>,
+        drawing: Result<Drawing>,
+        map: Result<Option<(MapPath, Format)>>,
+    ) -> Self {
+        Self {
+            params,
+            drawing,
+            map,
+        }
     }
 
     /// エントリーポイント
-    pub fn draw(&self) -> Result<()> {
-        let mut jwc_temp = JwcTemp::create(&self.file)?;
-        match &self.param {
-            Ok(p) => {
-                let spiral = super::plot(&p);
+    ///
+    /// 座標ファイル `file` に出力する。
+    pub fn draw(&self, file: &str) -> Result<()> {
+        self.render(&mut JwcTemp::create(file)?)
+    }
+
+    /// 緩和曲線を `jwc_temp` に出力する。
+    pub fn render(&self, jwc_temp: &mut impl Sheet) -> Result<()> {
+        match (&self.params, &self.drawing, &self.map) {
+            (Ok(params), Ok(drawing), Ok(map)) => {
+                let Some(first) = params.first() else {
+                    return jwc_temp.error(&"区間を入力してください");
+                };
+
+                // カント不足量を確認する。
+                if let Err(e) = params.iter().try_for_each(Param::check_cant) {
+                    return jwc_temp.error(&e);
+                }
+
+                // BVEマップを出力する。
+                if let Some((path, format)) = map {
+                    let written = MapFile::create(path, format.clone())
+                        .and_then(|mut map_file| map_file.write_curve(&super::map::curve(params)));
+                    if let Err(e) = written {
+                        return jwc_temp.error(&e);
+                    }
+                }
+
+                let spirals = super::plot_chain(params);
+
+                // 線の属性を設定する。
+                jwc_temp.style(&drawing.style)?;
 
                 // 緩和曲線 `spiral` を出力する。
-                for stroke in spiral.iter() {
-                    match stroke
-                        .center()
-                        .zip(stroke.r().filter(|r| r.meter().abs() < 100_000.0))
-                    {
-                        Some((c, r)) => jwc_temp.curve(&c, &r, &stroke.a0(), &stroke.a1())?,
-                        None => jwc_temp.straight(&stroke.p0(), &stroke.p1())?,
+                for spiral in &spirals {
+                    jwc_temp.strokes(spiral)?;
+                }
+
+                // 注記を出力する。円曲線・直線の区間には出力しない。
+                if drawing.annotate {
+                    for (p, spiral) in params.iter().zip(&spirals) {
+                        if params.len() == 1 || p.k0 != p.k1 {
+                            annotate(jwc_temp, p, spiral)?;
+                        }
                     }
                 }
 
+                // 線形図を出力する。
+                if let Some(diagram) = &drawing.diagram {
+                    diagram.draw(jwc_temp, params)?;
+                }
+
                 // 成功メッセージを出力する。
-                jwc_temp.notice(format!("{}を描画しました。", p.diminish))?;
+                let message = match params.len() {
+                    1 => match first.governing {
+                        Some(governing) => format!(
+                            "{}を描画しました。TCL={}m ({governing}で決定)",
+                            first.diminish,
+                            m(first.tcl.meter())
+                        ),
+                        None => format!("{}を描画しました。", first.diminish),
+                    },
+                    n => {
+                        let gaps = continuity::check(params, &spirals);
+                        let gaps = match gaps.is_empty() {
+                            true => "位置・接線・曲率は連続しています。".to_string(),
+                            false => gaps.iter().map(|gap| format!("{gap}。")).collect(),
+                        };
+                        format!("{}で{n}区間を描画しました。{gaps}", first.diminish)
+                    }
+                };
+                // 終点が厳密解から大きくずれていれば知らせる。
+                let error = params
+                    .iter()
+                    .zip(&spirals)
+                    .map(|(p, spiral)| exact::discrepancy(p, spiral).position)
+                    .fold(0.0, f64::max);
+                let message = match error < exact::NOTICEABLE {
+                    true => message,
+                    false => format!(
+                        "{message}終点は厳密な曲線から{}mmずれています。",
+                        m(error * 1000.0)
+                    ),
+                };
+                let message = match map {
+                    Some((path, _)) => {
+                        format!("{message} {} を作成しました", path.as_ref().display())
+                    }
+                    None => message,
+                };
+                jwc_temp.notice(message)?;
 
                 Ok(())
             }
-            Err(e) => jwc_temp.error(&e),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => jwc_temp.error(e),
         }
     }
 }
 
+/// 緩和曲線の始点・中間・終点に注記を出力する。
+fn annotate(jwc_temp: &mut impl Sheet, p: &Param, spiral: &Spiral) -> Result<()> {
+    let (Some(first), Some(last)) = (spiral.first(), spiral.last()) else {
+        return Ok(());
+    };
+    let middle = spiral[spiral.len() / 2];
+
+    let l0 = p.l0.meter();
+    let l1 = l0 + p.tcl.meter();
+
+    // カントを設計していれば併記する。
+    let c = |s: f64| {
+        p.cant(Subtension::from(s))
+            .map_or(String::new(), |c| format!(" C={}mm", m(c)))
+    };
+    let labels = [
+        (
+            first.p0(),
+            first.t0(),
+            format!("BTC {}m R={}{}", m(l0), r(p.k0), c(0.0)),
+        ),
+        (
+            middle.p0(),
+            middle.t0(),
+            format!("{} TCL={}m", p.diminish, m(p.tcl.meter())),
+        ),
+        (
+            last.p1(),
+            last.t1(),
+            format!("ETC {}m R={}{}", m(l1), r(p.k1), c(p.tcl.meter())),
+        ),
+    ];
+    for (point, t, s) in labels {
+        jwc_temp.text(&point, &upright(t), s)?;
+    }
+    Ok(())
+}
+
+/// 文字が逆さまにならない方向
+pub fn upright(t: Tangential) -> Tangential {
+    match t.rad().cos() < 0.0 {
+        true => t + std::f64::consts::PI.into(),
+        false => t,
+    }
+}
+
+/// 半径の表記
+pub fn r(k: Curvature) -> String {
+    k.r().map_or("∞".to_string(), |r| m(r.meter()))
+}
+
+/// 長さの表記
+///
+/// 小数点以下3桁に丸める。
+pub fn m(f: f64) -> String {
+    ((f * 1000.0).round() / 1000.0).to_string()
+}
+
 impl Display for Diminish {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Diminish::Sine => f.write_str("サイン半波長逓減曲線"),
             Diminish::Linear => f.write_str("直線逓減（クロソイド）"),
+            Diminish::CubicParabola => f.write_str("三次放物線"),
+            Diminish::Bloss => f.write_str("ブロス曲線"),
+            Diminish::Klein => f.write_str("クライン曲線"),
+            Diminish::Biquadratic => f.write_str("シュラム曲線"),
         }
     }
 }
@@ -0,0 +1,206 @@
+use anyhow::{ensure, Result};
+
+use crate::agent::bat::Args;
+
+use super::{
+    curve::{Curvature, Diminish, Subtension, STRAIGHT},
+    param::Param,
+    unit::Meter,
+};
+
+/// カントの設計条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CantRule {
+    /// 設計速度 (km/h)
+    pub speed: f64,
+
+    /// 軌間 (mm)
+    pub gauge: f64,
+
+    /// 最大カント (mm)
+    pub max: f64,
+
+    /// 許容カント不足量 (mm)
+    pub deficiency: f64,
+}
+
+/// カントの逓減
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cant {
+    /// 逓減関数
+    pub diminish: Diminish,
+
+    /// 設計条件
+    pub rule: CantRule,
+}
+
+impl CantRule {
+    /// コマンドライン引数をカントの設計条件にパースする。
+    ///
+    /// 設計速度がなければ `None`
+    pub fn parse(args: &Args) -> Result<Option<Self>> {
+        let Some(speed) = args.speed()? else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            speed,
+            gauge: args.gauge()?,
+            max: args.max_cant()?,
+            deficiency: args.cant_deficiency()?,
+        }))
+    }
+
+    /// 均衡カント (mm)
+    pub fn equilibrium(&self, k: Curvature) -> f64 {
+        let r = k.r().map_or(f64::INFINITY, |r| r.meter().abs());
+        self.gauge * self.speed.powi(2) / (127.0 * r)
+    }
+
+    /// 曲率 `k` の円曲線に設定するカント (mm)
+    ///
+    /// 均衡カントを1mm単位に丸め、最大カントで抑える。
+    pub fn cant(&self, k: Curvature) -> f64 {
+        self.equilibrium(k).round().min(self.max)
+    }
+
+    /// 右カーブを正とするカント (mm)
+    pub fn signed(&self, k: Curvature) -> f64 {
+        match k < STRAIGHT {
+            true => -self.cant(k),
+            false => self.cant(k),
+        }
+    }
+
+    /// カント不足量が許容範囲内か確認する。
+    pub fn check(&self, k: Curvature) -> Result<()> {
+        let lack = self.equilibrium(k) - self.cant(k);
+        ensure!(
+            lack <= self.deficiency,
+            "R={}m, V={}km/hではカント不足量が{}mmを超えます",
+            k.r().map_or(0.0, |r| r.meter()),
+            self.speed,
+            self.deficiency,
+        );
+        Ok(())
+    }
+}
+
+impl Param {
+    /// 始点から弧長 `s` の位置のカント (mm)
+    ///
+    /// 曲線の内側へ傾く向きが正。カントを設計しなければ `None`
+    pub fn cant(&self, s: Subtension) -> Option<f64> {
+        self.cant.map(|cant| {
+            // 反向曲線では途中で0になるよう、右カーブを正として逓減する。
+            let (c0, c1) = (cant.rule.signed(self.k0), cant.rule.signed(self.k1));
+            let c = match self.tcl.meter() > 0.0 {
+                true => c0 + (c1 - c0) * cant.diminish.ratio(self.tcl, s, self.k0, self.k1),
+                false => c1,
+            };
+            c.abs()
+        })
+    }
+
+    /// 始点と終点のカント不足量を確認する。
+    ///
+    /// 設計速度から緩和曲線長を決められなければエラー。
+    pub fn check_cant(&self) -> Result<()> {
+        ensure!(
+            self.governing.is_none() || self.tcl.meter() > 0.0,
+            "カントが変化しないため、緩和曲線長を決められません"
+        );
+        match self.cant {
+            Some(cant) => cant
+                .rule
+                .check(self.k0)
+                .and_then(|_| cant.rule.check(self.k1)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transition::curve::Radius;
+
+    use super::*;
+
+    fn 条件(speed: f64) -> CantRule {
+        CantRule {
+            speed,
+            gauge: 1067.0,
+            max: 105.0,
+            deficiency: 60.0,
+        }
+    }
+
+    #[test]
+    fn 均衡カント() {
+        // 1067 * 80^2 / (127 * 600) = 89.6mm
+        let rule = 条件(80.0);
+        assert_eq!(rule.cant(Radius(600.0).into()), 90.0);
+        assert_eq!(rule.cant(Radius(-600.0).into()), 90.0);
+        assert_eq!(rule.cant(STRAIGHT), 0.0);
+    }
+
+    #[test]
+    fn 最大カントで抑える() {
+        let rule = 条件(100.0);
+        let k = Radius(600.0).into();
+        assert_eq!(rule.cant(k), 105.0);
+        assert!(rule.check(k).is_ok());
+    }
+
+    #[test]
+    fn カント不足量の超過はエラー() {
+        let e = 条件(120.0).check(Radius(400.0).into()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "R=400m, V=120km/hではカント不足量が60mmを超えます"
+        );
+    }
+
+    #[test]
+    fn 曲率と同じ関数で逓減する() {
+        let param = Param {
+            diminish: Diminish::Sine,
+            k0: STRAIGHT,
+            k1: Radius(600.0).into(),
+            l0: 0.0.into(),
+            tcl: 60.0.into(),
+            p0: (0.0, 0.0).into(),
+            t0: 0.0.into(),
+            cant: Some(Cant {
+                diminish: Diminish::Sine,
+                rule: 条件(80.0),
+            }),
+            governing: None,
+            division: Default::default(),
+        };
+        assert_eq!(param.cant(0.0.into()), Some(0.0));
+        assert!((param.cant(30.0.into()).unwrap() - 45.0).abs() < 1e-9);
+        assert_eq!(param.cant(60.0.into()), Some(90.0));
+    }
+
+    #[test]
+    fn 反向曲線では途中でカントが0になる() {
+        let param = Param {
+            diminish: Diminish::Linear,
+            k0: Radius(-600.0).into(),
+            k1: Radius(600.0).into(),
+            l0: 0.0.into(),
+            tcl: 60.0.into(),
+            p0: (0.0, 0.0).into(),
+            t0: 0.0.into(),
+            cant: Some(Cant {
+                diminish: Diminish::Linear,
+                rule: 条件(80.0),
+            }),
+            governing: None,
+            division: Default::default(),
+        };
+        assert_eq!(param.cant(0.0.into()), Some(90.0));
+        assert_eq!(param.cant(30.0.into()), Some(0.0));
+        assert_eq!(param.cant(45.0.into()), Some(45.0));
+    }
+}
@@ -9,7 +9,7 @@ use std::{f64::consts::PI, ops::Add};
 /// 緩和曲線
 ///
 /// 複数の線で表現される。
-#[derive(Deref)]
+#[derive(Debug, Deref)]
 pub struct Spiral(Vec<Stroke>);
 
 impl FromIterator<Stroke> for Spiral {
@@ -41,6 +41,16 @@ impl Stroke {
         Self { k, len, p0, t0 }
     }
 
+    /// 曲率
+    pub fn k(&self) -> Curvature {
+        self.k
+    }
+
+    /// 弧長
+    pub fn len(&self) -> Subtension {
+        self.len
+    }
+
     /// 曲線半径
     pub fn r(&self) -> Option<Radius> {
         self.k.r()
@@ -76,6 +86,11 @@ impl Stroke {
         }
     }
 
+    /// 始点の接線方向
+    pub fn t0(&self) -> Tangential {
+        self.t0
+    }
+
     /// 終点の接線方向
     pub fn t1(&self) -> Tangential {
         self.t0 + self.k.a(self.len).rad().into()
@@ -86,6 +101,24 @@ impl Stroke {
 #[derive(Debug, Copy, Clone, PartialEq, From)]
 pub struct Point(f64, f64);
 
+impl Point {
+    /// 点 `q` までの距離
+    pub fn distance(&self, q: Point) -> f64 {
+        (self.0 - q.0).hypot(self.1 - q.1)
+    }
+
+    /// 線分 `a`-`b` までの距離
+    pub fn segment_distance(&self, (a, b): (Point, Point)) -> f64 {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len2 = dx * dx + dy * dy;
+        let t = match len2 > 0.0 {
+            true => (((self.0 - a.0) * dx + (self.1 - a.1) * dy) / len2).clamp(0.0, 1.0),
+            false => 0.0,
+        };
+        self.distance(Point(a.0 + t * dx, a.1 + t * dy))
+    }
+}
+
 impl Vector for Point {
     fn x(&self) -> f64 {
         self.0
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+use super::{
+    app::{m, r},
+    canvas::Spiral,
+    curve::{Curvature, Subtension},
+    param::Param,
+    unit::{Meter, Rad},
+};
+
+/// 同一点とみなす距離 (m)
+const TOLERANCE: f64 = 1e-6;
+
+/// 同じ向きとみなす角度 (rad)
+const ANGLE_TOLERANCE: f64 = 1e-9;
+
+/// 区間のつなぎ目の不連続
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gap {
+    /// 位置のずれ (m)
+    Position { l: f64, d: f64 },
+
+    /// 接線の折れ (rad)
+    Tangent { l: f64, a: f64 },
+
+    /// 曲率の急変
+    Curvature {
+        l: f64,
+        k0: Curvature,
+        k1: Curvature,
+    },
+}
+
+/// 区間 `params` を描画した `spirals` の連続性を確認する。
+pub fn check(params: &[Param], spirals: &[Spiral]) -> Vec<Gap> {
+    let mut gaps = vec![];
+
+    // 設計上の曲率。長さ0の区間は曲率が急変する。
+    let ends = params
+        .iter()
+        .map(|p| match p.tcl.meter() > 0.0 {
+            true => (
+                p.diminish.k(p.tcl, Subtension::from(0.0), p.k0, p.k1),
+                p.diminish.k(p.tcl, p.tcl, p.k0, p.k1),
+            ),
+            false => (p.k0, p.k1),
+        })
+        .collect::<Vec<_>>();
+    for (i, p) in params.iter().enumerate() {
+        let l = p.l0.meter();
+        let (k0, k1) = ends[i];
+        if let Some((_, prev)) = i.checked_sub(1).map(|j| ends[j]) {
+            if !prev.approx_eq(k0) {
+                gaps.push(Gap::Curvature {
+                    l,
+                    k0: prev,
+                    k1: k0,
+                });
+            }
+        }
+        if p.tcl.meter() <= 0.0 && !k0.approx_eq(k1) {
+            gaps.push(Gap::Curvature { l, k0, k1 });
+        }
+    }
+
+    // 描画した線の位置と接線
+    let strokes = spirals.iter().flat_map(|s| s.iter()).collect::<Vec<_>>();
+    let mut l = params.first().map_or(0.0, |p| p.l0.meter());
+    for pair in strokes.windows(2) {
+        let (prev, next) = (pair[0], pair[1]);
+        l += prev.len().meter();
+        let (p1, p0) = (prev.p1(), next.p0());
+        let d = p1.distance(p0);
+        if d > TOLERANCE {
+            gaps.push(Gap::Position { l, d });
+        }
+        let a = (next.t0().rad() - prev.t1().rad()).sin().asin();
+        if a.abs() > ANGLE_TOLERANCE {
+            gaps.push(Gap::Tangent { l, a });
+        }
+    }
+    gaps
+}
+
+impl Display for Gap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Gap::Position { l, d } => write!(f, "{}mで位置が{}mずれています", m(*l), m(*d)),
+            Gap::Tangent { l, a } => {
+                write!(f, "{}mで接線が{}°折れています", m(*l), m(a.to_degrees()))
+            }
+            Gap::Curvature { l, k0, k1 } => {
+                write!(
+                    f,
+                    "{}mで曲率が急変しています (R={}→{})",
+                    m(*l),
+                    r(*k0),
+                    r(*k1)
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use crate::{agent::bat::Args, transition::plot_chain};
+
+    use super::*;
+
+    fn 区間(chain: &str) -> Vec<Param> {
+        let args = Args::parse([OsString::from("/L0:100")]).unwrap();
+        Param::chain("2", chain, &args).unwrap()
+    }
+
+    #[test]
+    fn 反向曲線は連続() {
+        let params = 区間("0/10,400/20,400/30,0/20,-300/20,-300/10");
+        let spirals = plot_chain(&params);
+        assert_eq!(check(&params, &spirals), vec![]);
+    }
+
+    #[test]
+    fn 長さ0の区間は曲率が急変する() {
+        let params = 区間("400/20,300/0,300/10");
+        let spirals = plot_chain(&params);
+        let gaps = check(&params, &spirals);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(
+            gaps[0].to_string(),
+            "120mで曲率が急変しています (R=400→300)"
+        );
+    }
+
+    #[test]
+    fn 三次放物線は円曲線に届かない() {
+        let mut params = 区間("400/20,400/10");
+        params
+            .iter_mut()
+            .for_each(|p| p.diminish = crate::transition::curve::Diminish::CubicParabola);
+        let spirals = plot_chain(&params);
+        let gaps = check(&params, &spirals);
+        assert!(matches!(gaps[..], [Gap::Curvature { l, .. }] if l == 120.0));
+    }
+}
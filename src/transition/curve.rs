@@ -15,26 +15,89 @@ pub enum Diminish {
 
     /// 直線逓減（クロソイド曲線）
     Linear,
+
+    /// 三次放物線
+    ///
+    /// 緩和曲線長は弧長とする。終点の曲率は円曲線にわずかに届かない。
+    CubicParabola,
+
+    /// ブロス曲線
+    Bloss,
+
+    /// クライン曲線（サイン全波長逓減）
+    Klein,
+
+    /// シュラム曲線（二次放物線逓減）
+    Biquadratic,
 }
 
 impl Diminish {
     /// 曲率を計算する。
     pub fn k(&self, tcl: Subtension, s: Subtension, k0: Curvature, k1: Curvature) -> Curvature {
+        k0 + (k1 - k0) * self.ratio(tcl, s, k0, k1)
+    }
+
+    /// 始点から弧長 `s` の位置までに逓減する割合 (0 <= y <= 1)
+    ///
+    /// 三次放物線は曲率 `k0`, `k1` によって形が変わる。
+    pub fn ratio(&self, tcl: Subtension, s: Subtension, k0: Curvature, k1: Curvature) -> f64 {
         // 緩和曲線長に対する弧長の比率 (0 <= x <= 1)
         let x = s / tcl;
         debug_assert!(x <= 1.0);
 
         // 曲率の配分 (0 <= y <= 1)
-        let y = match self {
+        match self {
             Diminish::Sine => ((x - 0.5) * PI).sin() / 2.0 + 0.5,
             Diminish::Linear => x,
-        };
-
-        // 曲率
-        k0 + (k1 - k0) * y
+            Diminish::CubicParabola => {
+                let c = cubic_parabola_c(tcl, k1 - k0);
+                // 曲線から離れる場合は、直線から入る場合を裏返す
+                match k1.0.abs() < k0.0.abs() {
+                    true => 1.0 - cubic_parabola(1.0 - x, c),
+                    false => cubic_parabola(x, c),
+                }
+            }
+            Diminish::Bloss => x * x * (3.0 - 2.0 * x),
+            Diminish::Klein => x - (2.0 * PI * x).sin() / (2.0 * PI),
+            Diminish::Biquadratic => match x <= 0.5 {
+                true => 2.0 * x * x,
+                false => 1.0 - 2.0 * (1.0 - x) * (1.0 - x),
+            },
+        }
     }
 }
 
+/// 三次放物線 `Y = X^3 / (6 R X1)` の係数 `c = X1 / 2R`
+///
+/// 弧長が `tcl` になる `X1` を求める。
+fn cubic_parabola_c(tcl: Subtension, dk: Curvature) -> f64 {
+    let half_k = dk.0.abs() / 2.0;
+    (0..8).fold(0.0, |c, _| half_k * tcl.0 / cubic_parabola_len(1.0, c))
+}
+
+/// 三次放物線の、弧長の比率 `x` の位置での曲率の配分
+fn cubic_parabola(x: f64, c: f64) -> f64 {
+    // 弧長の比率が x になる横距の比率 t をニュートン法で求める。
+    let total = cubic_parabola_len(1.0, c);
+    let t = (0..8).fold(x, |t, _| {
+        let slope = (1.0 + (c * t * t).powi(2)).sqrt();
+        (t - (cubic_parabola_len(t, c) - x * total) / slope).clamp(0.0, 1.0)
+    });
+    t / (1.0 + (c * t * t).powi(2)).powf(1.5)
+}
+
+/// 横距の比率 `t` までの三次放物線の弧長 (X1 = 1)
+fn cubic_parabola_len(t: f64, c: f64) -> f64 {
+    // シンプソン則
+    const N: usize = 32;
+    let f = |u: f64| (1.0 + (c * u * u).powi(2)).sqrt();
+    let h = t / N as f64;
+    let sum = (1..N).fold(f(0.0) + f(t), |sum, i| {
+        sum + f(h * i as f64) * if i % 2 == 1 { 4.0 } else { 2.0 }
+    });
+    sum * h / 3.0
+}
+
 /// 弧長 (m)
 #[derive(Debug, Clone, Copy, PartialEq, From, Add, Sub)]
 pub struct Subtension(f64);
@@ -84,6 +147,11 @@ impl Curvature {
         Central(-self.0 * s.meter())
     }
 
+    /// ほぼ等しければ `true`
+    pub fn approx_eq(&self, other: Self) -> bool {
+        (self.0 - other.0).abs() < 1e-6
+    }
+
     /// 半径 (m)
     ///
     /// 直線は `None`.
@@ -153,3 +221,9 @@ impl Rad for Tangential {
 /// Data Transfer Object.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Degree(pub f64);
+
+impl From<Degree> for Tangential {
+    fn from(degree: Degree) -> Self {
+        degree.0.to_radians().into()
+    }
+}
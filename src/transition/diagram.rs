@@ -0,0 +1,240 @@
+//! 線形図
+//!
+//! 平面図の下に添える、距離程に対する曲率とカントの帯。
+
+use anyhow::Result;
+
+use crate::agent::sheet::Sheet;
+
+use super::{
+    app::{m, r},
+    canvas::Point,
+    curve::{Curvature, Tangential},
+    unit::{Meter, Vector},
+    Param,
+};
+
+/// 原点を指定しないときの、緩和曲線の始点から原点までの下がり (m)
+const DROP: f64 = 50.0;
+
+/// 目盛りの長さ (m)
+const TICK: f64 = 1.0;
+
+/// 曲率とカントの帯の間隔 (m)
+const GAP: f64 = 5.0;
+
+/// 線形図の設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Diagram {
+    /// 始点距離程の基線上の位置
+    ///
+    /// `None` なら緩和曲線の始点の下。
+    pub origin: Option<Point>,
+
+    /// 横の縮尺 (距離程1mあたりの図上の長さ)
+    pub scale: f64,
+
+    /// 曲率の縦倍率 (曲率1/mあたりの図上の長さ)
+    pub curvature: f64,
+
+    /// カントの縦倍率 (カント1mmあたりの図上の長さ)
+    pub cant: f64,
+
+    /// 目盛りの間隔 (m)
+    pub tick: f64,
+}
+
+impl Diagram {
+    /// 区間 `params` の曲率とカントを `sheet` に作図する。
+    ///
+    /// 右カーブを上に描く。カントを設計しなければ、曲率だけを描く。
+    pub fn draw(&self, sheet: &mut impl Sheet, params: &[Param]) -> Result<()> {
+        let (Some(first), Some(last)) = (params.first(), params.last()) else {
+            return Ok(());
+        };
+        let l0 = first.l0.meter();
+        let l1 = last.l0.meter() + last.tcl.meter();
+        let origin = self
+            .origin
+            .unwrap_or_else(|| first.p0 + Point::from((0.0, -DROP)));
+        let x = |z: f64| origin.x() + (z - l0) * self.scale;
+
+        // 曲率の帯
+        let k = curvature(params);
+        let k_max = k.iter().map(|(_, k)| k.abs()).fold(0.0, f64::max);
+        let y = |k: f64| origin.y() + k * self.curvature;
+        self.strip(sheet, "曲率", (l0, l1), x, origin.y())?;
+        polyline(sheet, k.iter().map(|&(z, k)| Point::from((x(z), y(k)))))?;
+        for p in params {
+            let z = p.l0.meter() + p.tcl.meter();
+            sheet.text(
+                &Point::from((x(z), y(value(p.k1)) + TICK)),
+                &Tangential::from(0.0),
+                format!("R={}", r(p.k1)),
+            )?;
+        }
+
+        // カントの帯
+        let cant = cant(params);
+        let mut bottom = origin.y() - k_max * self.curvature;
+        if !cant.is_empty() {
+            let c_max = cant.iter().map(|(_, c)| c.abs()).fold(0.0, f64::max);
+            let base = bottom - GAP - c_max * self.cant;
+            let y = |c: f64| base + c * self.cant;
+            self.strip(sheet, "カント", (l0, l1), x, base)?;
+            polyline(sheet, cant.iter().map(|&(z, c)| Point::from((x(z), y(c)))))?;
+            for p in params.iter().filter(|p| p.cant.is_some()) {
+                let z = p.l0.meter() + p.tcl.meter();
+                let c = p.cant(p.tcl).unwrap_or(0.0);
+                sheet.text(
+                    &Point::from((x(z), y(c * value(p.k1).signum()) + TICK)),
+                    &Tangential::from(0.0),
+                    format!("C={}mm", m(c)),
+                )?;
+            }
+            bottom = base - c_max * self.cant;
+        }
+
+        // 距離程の目盛り
+        for z in self.ticks(l0, l1) {
+            sheet.text(
+                &Point::from((x(z), bottom - 3.0 * TICK)),
+                &Tangential::from(0.0),
+                m(z),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 距離程 `l0` から `l1` までの基線と目盛りと、帯の名前を作図する。
+    ///
+    /// `x` は距離程の図上のX座標、`y` は基線のY座標。
+    fn strip(
+        &self,
+        sheet: &mut impl Sheet,
+        name: &str,
+        (l0, l1): (f64, f64),
+        x: impl Fn(f64) -> f64,
+        y: f64,
+    ) -> Result<()> {
+        sheet.straight(&Point::from((x(l0), y)), &Point::from((x(l1), y)))?;
+        sheet.text(
+            &Point::from((x(l0), y + TICK)),
+            &Tangential::from(0.0),
+            name,
+        )?;
+        for z in self.ticks(l0, l1) {
+            sheet.straight(&Point::from((x(z), y)), &Point::from((x(z), y - TICK)))?;
+        }
+        Ok(())
+    }
+
+    /// 距離程 `l0` から `l1` までの目盛りの距離程
+    fn ticks(&self, l0: f64, l1: f64) -> Vec<f64> {
+        let first = (l0 / self.tick).ceil() as i64;
+        let last = ((l1 + 1e-9) / self.tick).floor() as i64;
+        (first..=last).map(|n| n as f64 * self.tick).collect()
+    }
+}
+
+/// 曲率の値 (1/m)
+///
+/// 右カーブが正。
+fn value(k: Curvature) -> f64 {
+    k.r().map_or(0.0, |r| r.meter().recip())
+}
+
+/// 距離程と曲率の折れ線
+///
+/// 緩和曲線を描くときと同じ区間分割で、区間中央の曲率を区間全体の曲率とする。
+fn curvature(params: &[Param]) -> Vec<(f64, f64)> {
+    let mut points: Vec<(f64, f64)> = vec![];
+    for p in params.iter().filter(|p| p.tcl.meter() > 0.0) {
+        let l0 = p.l0.meter();
+        for interval in super::intervals(p) {
+            let k = value(p.diminish.k(p.tcl, interval.s(), p.k0, p.k1));
+            let (z0, z1) = (l0 + interval.start().meter(), l0 + interval.end().meter());
+            match points.as_mut_slice() {
+                // 曲率が変わらなければ区間を延ばす
+                [.., (_, k0), (z, k1)] if *k0 == k && *k1 == k => *z = z1,
+                _ => points.extend([(z0, k), (z1, k)]),
+            }
+        }
+    }
+    points
+}
+
+/// 距離程とカントの折れ線
+///
+/// 区間の境界でカントを求める。右カーブへのカントを正とする。
+fn cant(params: &[Param]) -> Vec<(f64, f64)> {
+    let mut points = vec![];
+    for p in params.iter().filter(|p| p.tcl.meter() > 0.0) {
+        let l0 = p.l0.meter();
+        let intervals = super::intervals(p);
+        let boundaries = intervals
+            .first()
+            .map(|interval| interval.start())
+            .into_iter()
+            .chain(intervals.iter().map(|interval| interval.end()));
+        for s in boundaries {
+            let Some(c) = p.cant(s) else { continue };
+            let k = p.diminish.k(p.tcl, s, p.k0, p.k1);
+            let sign = match value(k) == 0.0 {
+                true => value(p.k0 + p.k1).signum(),
+                false => value(k).signum(),
+            };
+            points.push((l0 + s.meter(), c * sign));
+        }
+    }
+    points
+}
+
+/// 点を順に直線で結ぶ。
+fn polyline(sheet: &mut impl Sheet, points: impl IntoIterator<Item = Point>) -> Result<()> {
+    let points = points.into_iter().collect::<Vec<_>>();
+    for pair in points.windows(2) {
+        if pair[0] != pair[1] {
+            sheet.straight(&pair[0], &pair[1])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use crate::agent::bat::Args;
+
+    use super::*;
+
+    fn 区間(chain: &str, args: &[&str]) -> Vec<Param> {
+        let args = Args::parse(["/L0:100"].iter().chain(args).map(OsString::from)).unwrap();
+        Param::chain("2", chain, &args).unwrap()
+    }
+
+    #[test]
+    fn 円曲線の曲率はまとめる() {
+        let k = curvature(&区間("400/2,400/10,0/0", &[]));
+        let expected = [
+            (100.0, 0.5 / 800.0),
+            (101.0, 0.5 / 800.0),
+            (101.0, 1.5 / 800.0),
+            (102.0, 1.5 / 800.0),
+            (102.0, 1.0 / 400.0),
+            (112.0, 1.0 / 400.0),
+        ];
+        assert_eq!(k.len(), expected.len());
+        for ((z, k), (ez, ek)) in k.iter().zip(expected) {
+            assert_eq!(*z, ez);
+            assert!((k - ek).abs() < 1e-12, "{k} {ek}");
+        }
+    }
+
+    #[test]
+    fn 左カーブのカントは負() {
+        let c = cant(&区間("-600/2", &["/V:80"]));
+        assert_eq!(c, [(100.0, 0.0), (101.0, -45.0), (102.0, -90.0)]);
+    }
+}
@@ -60,7 +60,7 @@ impl Iterator for Ruler {
 ///
 /// 区間境界は1m単位の距離程になる。
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, From)]
-pub struct Distance<T>( T);
+pub struct Distance<T>(T);
 
 impl<T: Sub<Output = T>> Sub for Distance<T> {
     type Output = T;
@@ -80,6 +80,12 @@ impl<T: Meter> Add<T> for Distance<f64> {
     }
 }
 
+impl Meter for Distance<f64> {
+    fn meter(&self) -> f64 {
+        self.0
+    }
+}
+
 impl Distance<f64> {
     /// 切り捨て
     fn floor(&self) -> Distance<i32> {
@@ -119,6 +125,70 @@ impl Distance<i32> {
     }
 }
 
+/// 区間分割の方法
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Division {
+    /// 1m単位の距離程で分割する。
+    #[default]
+    Meter,
+
+    /// 緩和曲線との横方向のずれが `tolerance` (m) 以内になるように分割する。
+    ///
+    /// `snap` なら1m単位の距離程も区間境界にする。
+    Adaptive { tolerance: f64, snap: bool },
+}
+
+/// 最も短い区間 (m)
+const MIN_INTERVAL: f64 = 0.01;
+
+/// 横方向のずれが `tolerance` 以内になるように、緩和曲線を分割する。
+///
+/// 一定の曲率で近似した区間のずれは `|dk/ds| * h^3 / 12` になる。
+/// 全長 `tcl` で積み重なっても許容値を超えないように区間長 `h` を決める。
+pub fn adaptive(
+    l0: Distance<f64>,
+    tcl: Subtension,
+    tolerance: f64,
+    snap: bool,
+    dk: impl Fn(f64) -> f64,
+) -> Vec<Interval> {
+    let total = tcl.meter();
+    let mut intervals = vec![];
+    let mut s0 = 0.0;
+    while s0 < total {
+        // 区間内の曲率変化率の最大値から、許される区間長まで縮める
+        let mut h = total - s0;
+        for _ in 0..32 {
+            let slope = [s0, s0 + h / 2.0, s0 + h]
+                .map(|s| dk(s).abs())
+                .into_iter()
+                .fold(0.0, f64::max);
+            let limit = (12.0 * tolerance / (total * slope))
+                .sqrt()
+                .max(MIN_INTERVAL);
+            if h <= limit {
+                break;
+            }
+            h = limit;
+        }
+
+        let mut s1 = (s0 + h).min(total);
+        if snap && s1 < total {
+            // 手前の距離程の区切りに揃える。区切りがなければ縮めた区間長のまま。
+            let l = (l0.0 + s1).floor();
+            if l - l0.0 > s0 {
+                s1 = l - l0.0;
+            }
+        }
+        intervals.push(Interval {
+            s0: s0.into(),
+            s1: s1.into(),
+        });
+        s0 = s1;
+    }
+    intervals
+}
+
 /// 1つの区間
 #[derive(Debug)]
 pub struct Interval {
@@ -155,6 +225,16 @@ impl Interval {
         (self.s1 + self.s0) * 0.5
     }
 
+    /// 緩和曲線始点から区間始点までの弧長
+    pub fn start(&self) -> Subtension {
+        self.s0
+    }
+
+    /// 緩和曲線始点から区間終点までの弧長
+    pub fn end(&self) -> Subtension {
+        self.s1
+    }
+
     /// 区間長
     pub fn len(&self) -> Subtension {
         self.s1 - self.s0
@@ -0,0 +1,214 @@
+//! 緩和曲線の厳密な計算
+//!
+//! 曲率を数値積分して、円弧の連なりで近似した緩和曲線の誤差を確かめる。
+
+use super::{
+    canvas::{Point, Spiral},
+    curve::{Subtension, Tangential, STRAIGHT},
+    param::Param,
+    unit::{Meter, Rad, Vector},
+};
+
+/// 数値積分の刻み (m)
+const STEP: f64 = 0.05;
+
+/// 利用者に知らせる終点のずれ (m)
+pub const NOTICEABLE: f64 = 0.001;
+
+/// 曲線上の位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct State {
+    /// 始点からの弧長 (m)
+    pub s: f64,
+
+    /// 座標
+    pub p: Point,
+
+    /// 接線方向
+    pub t: Tangential,
+}
+
+/// 近似した緩和曲線と厳密解のずれ
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Discrepancy {
+    /// 終点の位置のずれ (m)
+    pub position: f64,
+
+    /// 終点の接線方向のずれ (rad)
+    pub tangent: f64,
+
+    /// 線の端点での位置のずれの最大値 (m)
+    pub max: f64,
+}
+
+/// 始点 (`p0`, `t0`) から弧長 `stations` の位置を順に求める。
+///
+/// `stations` は昇順に並べる。曲率を4次のルンゲ＝クッタ法で積分する。
+pub fn trace(param: &Param, p0: Point, t0: Tangential, stations: &[f64]) -> Vec<State> {
+    let tcl = param.tcl.meter();
+    let k = |s: f64| {
+        let s = Subtension::from(s.clamp(0.0, tcl));
+        param.diminish.k(param.tcl, s, param.k0, param.k1)
+    };
+    // 接線方向の変化率。反時計回りが正。
+    let dt = |s: f64| k(s).a(Subtension::from(1.0)).rad();
+
+    let mut state = (0.0, p0.x(), p0.y(), t0.rad());
+    stations
+        .iter()
+        .map(|&target| {
+            let (mut s, mut x, mut y, mut t) = state;
+            let n = ((target - s) / STEP).ceil().max(0.0) as usize;
+            let h = (target - s) / n.max(1) as f64;
+            for _ in 0..n {
+                let (t1, x1, y1) = (dt(s), t.cos(), t.sin());
+                let t2 = t + h / 2.0 * t1;
+                let (t2d, x2, y2) = (dt(s + h / 2.0), t2.cos(), t2.sin());
+                let t3 = t + h / 2.0 * t2d;
+                let (t3d, x3, y3) = (t2d, t3.cos(), t3.sin());
+                let t4 = t + h * t3d;
+                let (t4d, x4, y4) = (dt(s + h), t4.cos(), t4.sin());
+                x += h / 6.0 * (x1 + 2.0 * x2 + 2.0 * x3 + x4);
+                y += h / 6.0 * (y1 + 2.0 * y2 + 2.0 * y3 + y4);
+                t += h / 6.0 * (t1 + 2.0 * t2d + 2.0 * t3d + t4d);
+                s += h;
+            }
+            state = (target, x, y, t);
+            State {
+                s: target,
+                p: Point::from((x, y)),
+                t: Tangential::from(t),
+            }
+        })
+        .collect()
+}
+
+/// 弧長 `s` の位置
+pub fn evaluate(param: &Param, s: Subtension) -> State {
+    trace(param, param.p0, param.t0, &[s.meter()])[0]
+}
+
+/// 移程量 (m)
+///
+/// 直線と円曲線をつなぐ緩和曲線で、円曲線を直線側へ移した量。
+/// 両端とも直線、または両端とも曲線なら `None`
+pub fn shift(param: &Param) -> Option<f64> {
+    let end = evaluate(param, param.tcl);
+    let start = State {
+        s: 0.0,
+        p: param.p0,
+        t: param.t0,
+    };
+    // 直線側の端と、円曲線側の端・半径
+    let (line, curve, r) = match (param.k0 == STRAIGHT, param.k1 == STRAIGHT) {
+        (true, false) => (start, end, param.k1.r()?),
+        (false, true) => (end, start, param.k0.r()?),
+        _ => return None,
+    };
+    let (x, y) = (curve.p.x() - line.p.x(), curve.p.y() - line.p.y());
+    let offset = (x * line.t.rad().sin() - y * line.t.rad().cos()).abs();
+    let tau = curve.t.rad() - line.t.rad();
+    Some(offset - r.meter().abs() * (1.0 - tau.cos()))
+}
+
+/// 近似した緩和曲線 `spiral` と厳密解のずれ
+pub fn discrepancy(param: &Param, spiral: &Spiral) -> Discrepancy {
+    let Some(first) = spiral.first() else {
+        return Discrepancy::default();
+    };
+    let stations = spiral
+        .iter()
+        .scan(0.0, |s, stroke| {
+            *s += stroke.len().meter();
+            Some(*s)
+        })
+        .collect::<Vec<_>>();
+    let exact = trace(param, first.p0(), first.t0(), &stations);
+    let max = spiral
+        .iter()
+        .zip(&exact)
+        .map(|(stroke, state)| stroke.p1().distance(state.p))
+        .fold(0.0, f64::max);
+    match (spiral.last(), exact.last()) {
+        (Some(last), Some(end)) => Discrepancy {
+            position: last.p1().distance(end.p),
+            tangent: (last.t1().rad() - end.t.rad()).abs(),
+            max,
+        },
+        _ => Discrepancy::default(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use crate::transition::{
+        curve::{Diminish, Radius},
+        distance::Division,
+    };
+
+    use super::*;
+
+    fn 緩和曲線(diminish: Diminish, r0: f64, r1: f64, tcl: f64) -> Param {
+        let k = |r: f64| match r == 0.0 {
+            true => STRAIGHT,
+            false => Radius(r).into(),
+        };
+        Param {
+            diminish,
+            k0: k(r0),
+            k1: k(r1),
+            l0: 0.0.into(),
+            tcl: tcl.into(),
+            p0: (0.0, 0.0).into(),
+            t0: 0.0.into(),
+            cant: None,
+            governing: None,
+            division: Division::Meter,
+        }
+    }
+
+    #[test]
+    fn クロソイドはフレネル積分に一致する() {
+        // 級数展開: x = L(1 - τ²/10 + τ⁴/216), y = L(τ/3 - τ³/42 + τ⁵/1320)
+        let (r, l): (f64, f64) = (200.0, 80.0);
+        let tau = l / (2.0 * r);
+        let x = l * (1.0 - tau.powi(2) / 10.0 + tau.powi(4) / 216.0);
+        let y = l * (tau / 3.0 - tau.powi(3) / 42.0 + tau.powi(5) / 1320.0);
+
+        let end = evaluate(&緩和曲線(Diminish::Linear, 0.0, r, l), l.into());
+        assert!((end.p.x() - x).abs() < 1e-6);
+        assert!((end.p.y() + y).abs() < 1e-6);
+        assert!((end.t.rad() + tau).abs() < 1e-12);
+    }
+
+    #[test]
+    fn クロソイドの移程量() {
+        // f = L²/24R (近似)
+        let (r, l) = (400.0, 60.0);
+        let f = shift(&緩和曲線(Diminish::Linear, 0.0, r, l)).unwrap();
+        assert!((f - l * l / (24.0 * r)).abs() < 1e-4);
+
+        // 曲線から直線へ向かう場合も同じ
+        let g = shift(&緩和曲線(Diminish::Linear, r, 0.0, l)).unwrap();
+        assert!((f - g).abs() < 1e-9);
+
+        assert_eq!(shift(&緩和曲線(Diminish::Linear, 400.0, 300.0, l)), None);
+    }
+
+    #[rstest]
+    #[case(Diminish::Sine)]
+    #[case(Diminish::Linear)]
+    #[case(Diminish::CubicParabola)]
+    #[case(Diminish::Bloss)]
+    #[case(Diminish::Klein)]
+    #[case(Diminish::Biquadratic)]
+    fn 円弧で近似した誤差(#[case] diminish: Diminish) {
+        let param = 緩和曲線(diminish, 0.0, 300.0, 100.0);
+        let d = discrepancy(&param, &crate::transition::plot(&param));
+        assert!(d.position < 0.001, "{d:?}");
+        assert!(d.tangent < 1e-4, "{d:?}");
+        assert!(d.position <= d.max);
+    }
+}
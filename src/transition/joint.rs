@@ -0,0 +1,135 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::agent::jww::{self, Figure, JwcTemp};
+
+use super::{
+    canvas::Point,
+    curve::{Curvature, Degree, Radius, Tangential, STRAIGHT},
+    unit::Vector,
+};
+
+/// 緩和曲線を接続する端点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Joint {
+    /// 座標
+    pub p: Point,
+
+    /// 接線方向 (端点から外向き)
+    pub t: Tangential,
+
+    /// 曲率
+    pub k: Curvature,
+}
+
+impl Joint {
+    /// 座標ファイルから、選択された線・円弧の端点を読み取る。
+    ///
+    /// 線・円弧が選択されていなければ `None`
+    pub fn read(path: &(impl AsRef<Path> + ?Sized)) -> Result<Option<Self>> {
+        if !path.as_ref().exists() {
+            return Ok(None);
+        }
+        JwcTemp::open(path)?.joint()
+    }
+}
+
+impl jww::Read {
+    /// 指示された点に最も近い、選択された線・円弧の端点
+    fn joint(&mut self) -> Result<Option<Joint>> {
+        let joints = self
+            .shapes()?
+            .iter()
+            .flat_map(|shape| ends(&shape.figure))
+            .collect::<Vec<_>>();
+        if joints.is_empty() {
+            return Ok(None);
+        }
+
+        let p = self.point(1)?.context("接続する端点を指示してください")?;
+        let nearest = joints
+            .into_iter()
+            .min_by(|a, b| a.p.distance(p).total_cmp(&b.p.distance(p)));
+        Ok(nearest)
+    }
+}
+
+/// 線・円弧の両端
+fn ends(figure: &Figure) -> Vec<Joint> {
+    match *figure {
+        Figure::Straight(p0, p1) => {
+            let t = (p1.y() - p0.y()).atan2(p1.x() - p0.x());
+            vec![
+                Joint {
+                    p: p0,
+                    t: Tangential::from(t + PI),
+                    k: STRAIGHT,
+                },
+                Joint {
+                    p: p1,
+                    t: Tangential::from(t),
+                    k: STRAIGHT,
+                },
+            ]
+        }
+        Figure::Curve { c, r, a0, a1 } => {
+            let at = |a: Degree| {
+                c + Point::from((r * a.0.to_radians().cos(), r * a.0.to_radians().sin()))
+            };
+            vec![
+                // 始点から外へは時計回り (右カーブ)
+                Joint {
+                    p: at(a0),
+                    t: Tangential::from(a0.0.to_radians() - FRAC_PI_2),
+                    k: Curvature::from(Radius(r)),
+                },
+                // 終点から外へは反時計回り (左カーブ)
+                Joint {
+                    p: at(a1),
+                    t: Tangential::from(a1.0.to_radians() + FRAC_PI_2),
+                    k: Curvature::from(Radius(-r)),
+                },
+            ]
+        }
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transition::unit::Rad;
+
+    #[test]
+    fn 直線の始点から外向き() {
+        let figure = Figure::Straight(Point::from((0.0, 0.0)), Point::from((-10.0, 0.0)));
+        let joint = ends(&figure)[0];
+        assert_eq!(joint.p, Point::from((0.0, 0.0)));
+        assert!(joint.t.rad().cos() > 0.999);
+        assert_eq!(joint.k, STRAIGHT);
+    }
+
+    #[test]
+    fn 円弧の端点は半径を引き継ぐ() {
+        let figure = Figure::Curve {
+            c: Point::from((0.0, 0.0)),
+            r: 100.0,
+            a0: Degree(-90.0),
+            a1: Degree(0.0),
+        };
+        let [start, end] = <[Joint; 2]>::try_from(ends(&figure)).unwrap();
+
+        // 始点 (0, -100) から西へ、右カーブ
+        assert!((start.t.rad().cos() + 1.0).abs() < 1e-9);
+        assert_eq!(start.k.r(), Some(Radius(100.0)));
+
+        // 終点 (100, 0) から北へ、左カーブ
+        assert!((end.p.x() - 100.0).abs() < 1e-9);
+        assert!((end.t.rad() - FRAC_PI_2).abs() < 1e-9);
+        assert_eq!(end.k.r(), Some(Radius(-100.0)));
+    }
+}
@@ -0,0 +1,123 @@
+//! BVEマップへの出力
+
+use crate::agent::bve::Curve;
+
+use super::{
+    curve::{Curvature, Diminish, Subtension, STRAIGHT},
+    param::Param,
+    unit::Meter,
+};
+
+/// 区間をBVEマップの曲線構文に変換する。
+///
+/// 直線逓減以外の緩和曲線は、区間分割の境界で曲率とカントを補間する。
+pub fn curve(params: &[Param]) -> Vec<(f64, Curve)> {
+    let mut curve = vec![];
+    for p in params {
+        let l0 = p.l0.meter();
+        let l1 = l0 + p.tcl.meter();
+        if p.tcl.meter() <= 0.0 || p.k0.approx_eq(p.k1) {
+            // 曲率の急変・円曲線・直線
+            curve.push((l0, begin(p.k1, cant(p, p.tcl))));
+            continue;
+        }
+
+        curve.push((l0, Curve::BeginTransition));
+        let is_linear = p.cant.is_none_or(|cant| cant.diminish == Diminish::Linear);
+        if p.diminish != Diminish::Linear || !is_linear {
+            let intervals = super::intervals(p);
+            for interval in intervals.iter().take(intervals.len().saturating_sub(1)) {
+                let s = interval.end();
+                let k = p.diminish.k(p.tcl, s, p.k0, p.k1);
+                curve.push((l0 + s.meter(), Curve::Interpolate(r(k), cant(p, s))));
+            }
+        }
+        curve.push((l1, begin(p.k1, cant(p, p.tcl))));
+    }
+
+    // 同じ構文の繰り返しを除く
+    curve.dedup_by(|a, b| a.1 == b.1 && !matches!(a.1, Curve::Interpolate(..)));
+    curve
+}
+
+/// 曲率 `k` の円曲線または直線を開始する。
+fn begin(k: Curvature, cant: f64) -> Curve {
+    match k == STRAIGHT {
+        true => Curve::End,
+        false => Curve::Begin(r(k), cant),
+    }
+}
+
+/// BVEの半径
+///
+/// 直線は0.
+fn r(k: Curvature) -> f64 {
+    k.r().map_or(0.0, |r| r.meter())
+}
+
+/// BVEのカント (m)
+///
+/// カントを設計しなければ0.
+fn cant(p: &Param, s: Subtension) -> f64 {
+    p.cant(s).map_or(0.0, |c| c / 1000.0)
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use crate::agent::bat::Args;
+
+    use super::*;
+
+    fn 区間(diminish: &str, chain: &str) -> Vec<Param> {
+        let args = Args::parse([OsString::from("/L0:100")]).unwrap();
+        Param::chain(diminish, chain, &args).unwrap()
+    }
+
+    #[test]
+    fn 直線逓減は補間しない() {
+        let curve = curve(&区間("2", "-400/30.5"));
+        let expected = [
+            (100.0, Curve::BeginTransition),
+            (130.5, Curve::Begin(-400.0, 0.0)),
+        ];
+        assert_eq!(curve, expected);
+    }
+
+    #[test]
+    fn サイン半波長逓減は区間境界で補間する() {
+        let curve = curve(&区間("1", "400/2.5,400/10,0/2"));
+        let z = curve.iter().map(|(z, _)| *z).collect::<Vec<_>>();
+        assert_eq!(z, [100.0, 101.0, 102.0, 102.5, 112.5, 113.0, 114.0, 114.5]);
+        assert_eq!(curve[0].1, Curve::BeginTransition);
+        assert!(matches!(curve[1].1, Curve::Interpolate(r, _) if r > 400.0));
+        assert_eq!(curve[3].1, Curve::Begin(400.0, 0.0));
+        assert_eq!(curve[4].1, Curve::BeginTransition);
+        assert!(matches!(curve[6].1, Curve::Interpolate(r, _) if r > 400.0));
+        assert_eq!(curve[7].1, Curve::End);
+    }
+
+    #[test]
+    fn カントを逓減する() {
+        let args = ["/L0:100", "/V:80", "/CANT:1"].map(OsString::from);
+        let args = Args::parse(args).unwrap();
+        let curve = curve(&Param::chain("2", "600/2", &args).unwrap());
+        let expected = [
+            (100.0, Curve::BeginTransition),
+            (101.0, Curve::Interpolate(1200.0, 0.045)),
+            (102.0, Curve::Begin(600.0, 0.09)),
+        ];
+        assert_eq!(curve.len(), expected.len());
+        for ((z, actual), (ez, expected)) in curve.iter().zip(expected) {
+            assert_eq!(*z, ez);
+            match (actual, expected) {
+                (Curve::Interpolate(r, c), Curve::Interpolate(er, ec))
+                | (Curve::Begin(r, c), Curve::Begin(er, ec)) => {
+                    assert!((r - er).abs() < 1e-9 && (c - ec).abs() < 1e-9)
+                }
+                _ => assert_eq!(*actual, expected),
+            }
+        }
+    }
+}
@@ -1,21 +1,31 @@
 pub mod app;
+pub mod cant;
 pub mod canvas;
+pub mod continuity;
 pub mod curve;
+pub mod diagram;
+pub mod distance;
+pub mod exact;
+pub mod joint;
+pub mod map;
 pub mod param;
+pub mod solver;
 pub mod unit;
 
-mod distance;
 #[cfg(test)]
 mod test;
 
 use canvas::{Spiral, Stroke};
-use distance::Ruler;
+use curve::Subtension;
+use distance::{Division, Interval, Ruler};
 use param::Param;
+use unit::{Meter, Rad};
 
 /// 緩和曲線を描画する。
 pub fn plot(param: &Param) -> Spiral {
     // 距離程を区間に分割する。
-    Ruler::new(param.l0, param.tcl)
+    intervals(param)
+        .into_iter()
         .map(|interval| {
             // 曲率を計算する。
             let k = param
@@ -36,3 +46,40 @@ pub fn plot(param: &Param) -> Spiral {
         })
         .collect()
 }
+
+/// 区間を順につないで描画する。
+///
+/// 2区間目以降の始点は、前の区間の終点になる。
+pub fn plot_chain(params: &[Param]) -> Vec<Spiral> {
+    params
+        .iter()
+        .scan(None, |end, param| {
+            let (p0, t0) = end.unwrap_or((param.p0, param.t0));
+            let spiral = match param.tcl.meter() > 0.0 {
+                true => plot(&Param { p0, t0, ..*param }),
+                false => Spiral::from_iter([]),
+            };
+            *end = Some(spiral.last().map_or((p0, t0), |s| (s.p1(), s.t1())));
+            Some(spiral)
+        })
+        .collect()
+}
+
+/// 緩和曲線を区間に分割する。
+pub fn intervals(param: &Param) -> Vec<Interval> {
+    match param.division {
+        Division::Meter => Ruler::new(param.l0, param.tcl).collect(),
+        Division::Adaptive { tolerance, snap } => {
+            // 弧長1mあたりの曲率の変化
+            let k = |s: f64| {
+                let s = Subtension::from(s.clamp(0.0, param.tcl.meter()));
+                param.diminish.k(param.tcl, s, param.k0, param.k1)
+            };
+            let dk = |s: f64| {
+                let ds = 1e-3;
+                (k(s + ds) - k(s - ds)).a(Subtension::from(1.0)).rad() / (2.0 * ds)
+            };
+            distance::adaptive(param.l0, param.tcl, tolerance, snap, dk)
+        }
+    }
+}
@@ -1,16 +1,18 @@
-
-
 use crate::{
-    agent::bat::{Args},
+    agent::{bat::Args, jww::Style},
     transition::curve::{Radius, STRAIGHT},
 };
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 
 use super::{
+    cant::{Cant, CantRule},
     canvas::Point,
     curve::{Curvature, Diminish, Subtension, Tangential},
-    distance::Distance,
+    diagram::Diagram,
+    distance::{Distance, Division},
+    joint::Joint,
+    solver::{self, Criterion},
 };
 
 /// 緩和曲線パラメータ
@@ -36,29 +38,212 @@ pub struct Param {
 
     /// 始点の接線方向
     pub t0: Tangential,
+
+    /// カントの逓減
+    ///
+    /// カントを設計しなければ `None`
+    pub cant: Option<Cant>,
+
+    /// 緩和曲線長を決めた基準
+    ///
+    /// 緩和曲線長を指定したときは `None`
+    pub governing: Option<Criterion>,
+
+    /// 区間分割の方法
+    pub division: Division,
 }
 
 impl Param {
     /// コマンドライン引数を緩和曲線パラメータにパースする。
+    ///
+    /// 緩和曲線長を指定しなければ、設計速度から決める。
     pub fn parse(diminish: &str, args: &Args) -> Result<Self> {
-        Ok(Self {
+        let param = Self {
             diminish: Diminish::parse(diminish)?,
             k0: args.r0()?.map_or(STRAIGHT, |r| Curvature::from(Radius(r))),
             k1: args.r1()?.map_or(STRAIGHT, |r| Curvature::from(Radius(r))),
-            l0: Distance::from(0.0),
-            tcl: Subtension::from(args.tcl()?),
+            l0: Distance::from(args.l0()?),
+            tcl: Subtension::from(args.tcl()?.unwrap_or(0.0)),
             p0: Point::from((0.0, 0.0)),
             t0: Tangential::from(0.0),
+            cant: Cant::parse(diminish, args)?,
+            governing: None,
+            division: Division::parse(args)?,
+        };
+        match (args.tcl()?, param.cant) {
+            (Some(_), _) => Ok(param),
+            (None, Some(_)) => Ok(param.solve()),
+            (None, None) => bail!("TCLを指定してください"),
+        }
+    }
+
+    /// 設計速度から緩和曲線長を決める。
+    fn solve(self) -> Self {
+        match self.cant {
+            Some(cant) => {
+                let (tcl, governing) = solver::solve(&cant.rule, self.k0, self.k1);
+                Self {
+                    tcl: Subtension::from(tcl),
+                    governing: Some(governing),
+                    ..self
+                }
+            }
+            None => self,
+        }
+    }
+
+    /// 区間の並び "R/L,R/L,..." を緩和曲線パラメータの列にパースする。
+    ///
+    /// 各区間は終点の半径と長さで指定する。半径0は直線。
+    /// 半径が前の区間と同じなら円曲線、長さ0なら曲率が急変する。
+    pub fn chain(diminish: &str, chain: &str, args: &Args) -> Result<Vec<Self>> {
+        let cant = Cant::parse(diminish, args)?;
+        let division = Division::parse(args)?;
+        let diminish = Diminish::parse(diminish)?;
+        let mut k0 = args.r0()?.map_or(STRAIGHT, |r| Curvature::from(Radius(r)));
+        let mut l0 = Distance::from(args.l0()?);
+        chain
+            .split(',')
+            .enumerate()
+            .map(|(i, pair)| {
+                let (r, len) = pair
+                    .split_once('/')
+                    .and_then(|(r, len)| Some((number(r)?, number(len)?)))
+                    .filter(|(_, len)| *len >= 0.0)
+                    .with_context(|| format!("{}区間目に 半径/長さ を入力してください", i + 1))?;
+                let k1 = match r == 0.0 {
+                    true => STRAIGHT,
+                    false => Curvature::from(Radius(r)),
+                };
+                let param = Self {
+                    diminish,
+                    k0,
+                    k1,
+                    l0,
+                    tcl: Subtension::from(len),
+                    p0: Point::from((0.0, 0.0)),
+                    t0: Tangential::from(0.0),
+                    cant,
+                    governing: None,
+                    division,
+                };
+                k0 = k1;
+                l0 = l0 + param.tcl;
+                Ok(param)
+            })
+            .collect()
+    }
+
+    /// 始点を端点 `joint` に接続する。
+    ///
+    /// 始点の曲率は端点の線・円弧に合わせる。
+    pub fn attach(self, joint: Option<Joint>) -> Self {
+        match joint {
+            Some(joint) => {
+                let param = Self {
+                    p0: joint.p,
+                    t0: joint.t,
+                    k0: joint.k,
+                    ..self
+                };
+                // 始点の曲率が変わるので、緩和曲線長を決め直す。
+                match param.governing {
+                    Some(_) => param.solve(),
+                    None => param,
+                }
+            }
+            None => self,
+        }
+    }
+}
+
+/// 作図の設定
+#[derive(Debug, Default)]
+pub struct Drawing {
+    /// 線の属性
+    pub style: Style,
+
+    /// 注記を作図するなら `true`
+    pub annotate: bool,
+
+    /// 線形図
+    ///
+    /// 作図しなければ `None`
+    pub diagram: Option<Diagram>,
+}
+
+impl Drawing {
+    /// コマンドライン引数を作図の設定にパースする。
+    pub fn parse(args: &Args) -> Result<Self> {
+        Ok(Self {
+            style: args.style()?,
+            annotate: args.annotate()?,
+            diagram: Diagram::parse(args)?,
         })
     }
 }
 
+impl Cant {
+    /// コマンドライン引数をカントの逓減にパースする。
+    ///
+    /// 逓減関数を指定しなければ、曲率と同じ関数で逓減する。
+    fn parse(diminish: &str, args: &Args) -> Result<Option<Self>> {
+        let Some(rule) = CantRule::parse(args)? else {
+            return Ok(None);
+        };
+        let diminish = Diminish::parse(args.cant_diminish().unwrap_or(diminish))?;
+        Ok(Some(Self { diminish, rule }))
+    }
+}
+
+impl Diagram {
+    /// コマンドライン引数を線形図の設定にパースする。
+    ///
+    /// 線形図を作図しなければ `None`
+    fn parse(args: &Args) -> Result<Option<Self>> {
+        if !args.diagram()? {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            origin: args.diagram_origin()?,
+            scale: args.diagram_scale()?,
+            curvature: args.curvature_scale()?,
+            cant: args.cant_scale()?,
+            tick: args.tick()?,
+        }))
+    }
+}
+
+impl Division {
+    /// コマンドライン引数を区間分割の方法にパースする。
+    ///
+    /// 許容誤差がなければ1m単位で分割する。
+    fn parse(args: &Args) -> Result<Self> {
+        Ok(match args.tolerance()? {
+            Some(tolerance) => Division::Adaptive {
+                tolerance,
+                snap: args.snap()?,
+            },
+            None => Division::Meter,
+        })
+    }
+}
+
+/// 有限の数値
+fn number(s: &str) -> Option<f64> {
+    s.trim().parse().ok().filter(|f: &f64| f.is_finite())
+}
+
 impl Diminish {
     /// 緩和曲線関数に変換する。
-    fn parse(pair: &str) -> Result<Self> {
+    pub fn parse(pair: &str) -> Result<Self> {
         match pair {
             "1" => Ok(Diminish::Sine),
             "2" => Ok(Diminish::Linear),
+            "3" => Ok(Diminish::CubicParabola),
+            "4" => Ok(Diminish::Bloss),
+            "5" => Ok(Diminish::Klein),
+            "6" => Ok(Diminish::Biquadratic),
             _ => bail!("緩和曲線関数に正しい値を入力してください"),
         }
     }
@@ -121,6 +306,11 @@ mod test {
     #[rstest]
     #[case("/TRANSITION:0",Err(anyhow::anyhow!("緩和曲線関数に正しい値を入力してください")))]
     #[case("/TRANSITION:2", Ok(Diminish::Linear))]
+    #[case("/TRANSITION:3", Ok(Diminish::CubicParabola))]
+    #[case("/TRANSITION:4", Ok(Diminish::Bloss))]
+    #[case("/TRANSITION:5", Ok(Diminish::Klein))]
+    #[case("/TRANSITION:6", Ok(Diminish::Biquadratic))]
+    #[case("/TRANSITION:7",Err(anyhow::anyhow!("緩和曲線関数に正しい値を入力してください")))]
     fn 緩和曲線関数をパースする(#[case] arg: &str, #[case] expected: Result<Diminish>) {
         let args = vec![
             OsString::from("transition.exe"),
@@ -130,12 +320,44 @@ mod test {
         ];
         let (_, param) = Args::parse(args).unwrap().unwrap_transition();
         match (param, expected) {
-            (Ok(param), Ok(_expected)) => assert!(matches!(param.diminish, _expected)),
+            (Ok(param), Ok(expected)) => assert_eq!(param.diminish, expected),
             (Err(e), Err(expected)) => assert_eq!(e.to_string(), expected.to_string()),
             _ => panic!(),
         }
     }
 
+    #[rstest]
+    #[case("", 0.0)]
+    #[case("/L0:", 0.0)]
+    #[case("/L0:12.5", 12.5)]
+    #[case("/L0:-100", -100.0)]
+    fn 始点の距離程をパースする(#[case] arg: &str, #[case] expected: f64) {
+        let args = vec![
+            OsString::from("transition.exe"),
+            OsString::from("/TRANSITION:1"),
+            OsString::from(arg),
+            OsString::from("/TCL:3"),
+            OsString::from("/TEMP:./JWC_TEMP.TXT"),
+        ];
+        let (_, param) = Args::parse(args).unwrap().unwrap_transition();
+        assert_eq!(param.unwrap().l0, expected.into());
+    }
+
+    #[rstest]
+    #[case("/L0:abc", "L0を数値で入力してください")]
+    #[case("/L0:inf", "L0を数値で入力してください")]
+    fn 始点の距離程のエラーチェック(#[case] arg: &str, #[case] err: &str) {
+        let args = vec![
+            OsString::from("transition.exe"),
+            OsString::from("/TRANSITION:1"),
+            OsString::from(arg),
+            OsString::from("/TCL:3"),
+            OsString::from("/TEMP:./JWC_TEMP.TXT"),
+        ];
+        let (_, param) = Args::parse(args).unwrap().unwrap_transition();
+        assert_eq!(param.unwrap_err().to_string(), err);
+    }
+
     #[rstest]
     #[case("", "TCLを指定してください")]
     #[case("/TCL:0", "TCLに0より大きい値を入力してください")]
@@ -174,11 +396,38 @@ mod test {
         assert_eq!(e.to_string(), err);
     }
 
+    #[test]
+    fn 区間の並びをパースする() {
+        let args = Args::parse([OsString::from("/R0:-200"), OsString::from("/L0:10")]).unwrap();
+        let params = Param::chain("1", "-200/30, 0 / 40,500/0", &args).unwrap();
+        let expected = [
+            (Radius(-200.0).into(), Radius(-200.0).into(), 10.0, 30.0),
+            (Radius(-200.0).into(), STRAIGHT, 40.0, 40.0),
+            (STRAIGHT, Radius(500.0).into(), 80.0, 0.0),
+        ];
+        assert_eq!(params.len(), expected.len());
+        for (param, (k0, k1, l0, tcl)) in params.iter().zip(expected) {
+            assert_eq!((param.k0, param.k1), (k0, k1));
+            assert_eq!(param.l0, l0.into());
+            assert_eq!(param.tcl, tcl.into());
+        }
+    }
+
+    #[rstest]
+    #[case("400", "1区間目に 半径/長さ を入力してください")]
+    #[case("400/20,abc/10", "2区間目に 半径/長さ を入力してください")]
+    #[case("400/20,400/-1", "2区間目に 半径/長さ を入力してください")]
+    fn 区間の並びのエラーチェック(#[case] chain: &str, #[case] err: &str) {
+        let args = Args::parse(Vec::<OsString>::new()).unwrap();
+        let e = Param::chain("1", chain, &args).unwrap_err();
+        assert_eq!(e.to_string(), err);
+    }
+
     impl Args {
         fn unwrap_transition(&self) -> (String, Result<transition::Param>) {
             if let Ok(formula) = self.transition() {
                 let file = self.temp_path().unwrap().into();
-                let param = Param::parse(&formula, &self);
+                let param = Param::parse(formula, self);
                 (file, param)
             } else {
                 panic!("This is not a transition.")
@@ -0,0 +1,133 @@
+//! 設計速度から緩和曲線長を決める
+
+use std::fmt::Display;
+
+use super::{
+    cant::CantRule,
+    curve::{Curvature, STRAIGHT},
+};
+
+// 係数は普通鉄道構造規則の解説に示される在来線の緩和曲線長の式
+// L = max(0.8C, 0.01CV, 0.008CdV) による (C, Cd は mm, V は km/h, L は m)。
+
+/// カント逓減倍率
+const TWIST: f64 = 800.0;
+
+/// カントの時間変化率の係数 (m/(mm・km/h))
+const CANT_RATE: f64 = 0.01;
+
+/// カント不足量の時間変化率の係数 (m/(mm・km/h))
+const DEFICIENCY_RATE: f64 = 0.008;
+
+/// 緩和曲線長を決める基準
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Criterion {
+    /// カントの逓減 (ねじれ)
+    Twist,
+
+    /// カントの時間変化率
+    CantRate,
+
+    /// カント不足量の時間変化率 (横加速度の変化)
+    DeficiencyRate,
+}
+
+/// 曲率 `k0` から `k1` への緩和曲線に必要な長さ (m) と、それを決めた基準
+///
+/// 1m単位に切り上げる。同じ長さなら先に並べた基準で決まったものとする。
+pub fn solve(rule: &CantRule, k0: Curvature, k1: Curvature) -> (f64, Criterion) {
+    lengths(rule, k0, k1)
+        .into_iter()
+        .map(|(tcl, criterion)| ((tcl - 1e-9).ceil().max(0.0), criterion))
+        .fold((0.0, Criterion::Twist), |max, next| match next.0 > max.0 {
+            true => next,
+            false => max,
+        })
+}
+
+/// 基準ごとに必要な緩和曲線長 (m)
+pub fn lengths(rule: &CantRule, k0: Curvature, k1: Curvature) -> [(f64, Criterion); 3] {
+    // 右カーブを正とする。
+    let dc = (rule.signed(k1) - rule.signed(k0)).abs();
+    let deficiency = |k: Curvature| {
+        let cd = rule.equilibrium(k) - rule.cant(k);
+        match k < STRAIGHT {
+            true => -cd,
+            false => cd,
+        }
+    };
+    let dcd = (deficiency(k1) - deficiency(k0)).abs();
+
+    let v = rule.speed;
+    [
+        (TWIST * dc / 1000.0, Criterion::Twist),
+        (CANT_RATE * dc * v, Criterion::CantRate),
+        (DEFICIENCY_RATE * dcd * v, Criterion::DeficiencyRate),
+    ]
+}
+
+impl Display for Criterion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Criterion::Twist => f.write_str("カント逓減"),
+            Criterion::CantRate => f.write_str("カント変化率"),
+            Criterion::DeficiencyRate => f.write_str("カント不足量変化率"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transition::curve::Radius;
+
+    use super::*;
+
+    fn 条件(speed: f64, max: f64) -> CantRule {
+        CantRule {
+            speed,
+            gauge: 1067.0,
+            max,
+            deficiency: 60.0,
+        }
+    }
+
+    #[test]
+    fn カント逓減で決まる() {
+        // C = 1067 * 60^2 / (127 * 600) = 50mm, L = 0.8 * 50 = 40m > 0.01 * 50 * 60 = 30m
+        let (tcl, criterion) = solve(&条件(60.0, 105.0), STRAIGHT, Radius(600.0).into());
+        assert_eq!(tcl, 40.0);
+        assert_eq!(criterion, Criterion::Twist);
+    }
+
+    #[test]
+    fn カントの変化で決まる() {
+        // C = 1067 * 100^2 / (127 * 1200) = 70mm, L = 0.01 * 70 * 100 = 70m > 0.8 * 70 = 56m
+        let (tcl, criterion) = solve(&条件(100.0, 105.0), STRAIGHT, Radius(1200.0).into());
+        assert_eq!(tcl, 70.0);
+        assert_eq!(criterion, Criterion::CantRate);
+    }
+
+    #[test]
+    fn カント不足量の変化で決まる() {
+        // C = 0mm, Cd = 1067 * 100^2 / (127 * 1000) = 84mm, L = 0.008 * 84 * 100 = 67.2m
+        let (tcl, criterion) = solve(&条件(100.0, 0.0), STRAIGHT, Radius(1000.0).into());
+        assert_eq!(tcl, 68.0);
+        assert_eq!(criterion, Criterion::DeficiencyRate);
+    }
+
+    #[test]
+    fn 同じ長さならカント逓減で決まる() {
+        // C = 90mm, 0.8 * 90 = 0.01 * 90 * 80 = 72m
+        let (tcl, criterion) = solve(&条件(80.0, 105.0), STRAIGHT, Radius(600.0).into());
+        assert_eq!(tcl, 72.0);
+        assert_eq!(criterion, Criterion::Twist);
+    }
+
+    #[test]
+    fn 反向曲線はカントの差が大きい() {
+        let rule = 条件(80.0, 105.0);
+        let (s, _) = solve(&rule, STRAIGHT, Radius(600.0).into());
+        let (r, _) = solve(&rule, Radius(-600.0).into(), Radius(600.0).into());
+        assert_eq!(r, s * 2.0);
+    }
+}
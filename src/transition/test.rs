@@ -1,4 +1,4 @@
-use std::f64::consts::FRAC_2_PI;
+use std::f64::consts::{FRAC_2_PI, PI};
 
 use rstest::rstest;
 
@@ -6,11 +6,12 @@ use crate::transition::unit::Vector;
 
 use super::canvas::Point;
 use super::curve::*;
+use super::distance::Division;
 use super::param::Param;
-use super::unit::Deg;
+use super::unit::{Deg, Rad};
 use super::*;
 
-fn 緩和曲線(r0 :f64, r1: f64, tcl: f64, btc: f64) -> Param {
+fn 緩和曲線(r0: f64, r1: f64, tcl: f64, btc: f64) -> Param {
     Param {
         diminish: Diminish::Sine,
         k0: Radius(r0).into(),
@@ -19,6 +20,9 @@ fn 緩和曲線(r0 :f64, r1: f64, tcl: f64, btc: f64) -> Param {
         l0: btc.into(),
         p0: (0.0, 0.0).into(),
         t0: 0.0.into(),
+        cant: None,
+        governing: None,
+        division: Default::default(),
     }
 }
 
@@ -31,6 +35,9 @@ fn 円(r: f64, c: (f64, f64)) -> Param {
         l0: 0.0.into(),
         p0: c.into(),
         t0: Degree(90.0).into(),
+        cant: None,
+        governing: None,
+        division: Default::default(),
     }
 }
 
@@ -43,6 +50,9 @@ fn 直線(t0: f64) -> Param {
         l0: 0.0.into(),
         p0: (0.0, 0.0).into(),
         t0: Degree(t0).into(),
+        cant: None,
+        governing: None,
+        division: Default::default(),
     }
 }
 
@@ -110,6 +120,138 @@ fn 座標(#[case] param: Param, #[case] expected: Vec<(&str, &str)>) {
     });
 }
 
+fn 直線から円(diminish: Diminish, r: f64, tcl: f64) -> Param {
+    Param {
+        diminish,
+        k0: STRAIGHT,
+        k1: Radius(r).into(),
+        tcl: tcl.into(),
+        l0: 0.0.into(),
+        p0: (0.0, 0.0).into(),
+        t0: 0.0.into(),
+        cant: None,
+        governing: None,
+        division: Default::default(),
+    }
+}
+
+/// 移程量 f = (L^2 / R) * 係数
+#[rstest]
+#[case(Diminish::Linear, 1.0 / 24.0)]
+#[case(Diminish::Sine, 1.0 / 8.0 - 1.0 / (PI * PI))]
+#[case(Diminish::Bloss, 1.0 / 40.0)]
+#[case(Diminish::Klein, 1.0 / 24.0 - 1.0 / (4.0 * PI * PI))]
+#[case(Diminish::Biquadratic, 1.0 / 48.0)]
+fn 移程量(#[case] diminish: Diminish, #[case] coefficient: f64) {
+    let (r, tcl) = (400.0, 60.0);
+    let spiral = plot(&直線から円(diminish, r, tcl));
+    let last = spiral.last().unwrap();
+    let theta = last.t1().rad().abs();
+    let shift = last.p1().y().abs() - r * (1.0 - theta.cos());
+    let expected = tcl * tcl / r * coefficient;
+    assert!(
+        (shift - expected).abs() < expected * 0.01,
+        "{diminish}: {shift} != {expected}"
+    );
+}
+
+/// 三次放物線の縦距 Y = X^2 / 6R
+#[rstest]
+#[case(400.0, 60.0)]
+#[case(-200.0, 80.0)]
+fn 三次放物線の縦距(#[case] r: f64, #[case] tcl: f64) {
+    let spiral = plot(&直線から円(Diminish::CubicParabola, r, tcl));
+    let p1 = spiral.last().unwrap().p1();
+    let expected = p1.x() * p1.x() / (6.0 * r.abs());
+    assert!((p1.y().abs() - expected).abs() < expected * 0.01);
+    assert!(p1.x() < tcl);
+}
+
+#[rstest]
+#[case(Diminish::CubicParabola)]
+#[case(Diminish::Bloss)]
+#[case(Diminish::Klein)]
+#[case(Diminish::Biquadratic)]
+fn 始点と終点の曲率(#[case] diminish: Diminish) {
+    let (k0, k1): (Curvature, Curvature) = (STRAIGHT, Radius(300.0).into());
+    let tcl = 50.0.into();
+    assert_eq!(diminish.k(tcl, 0.0.into(), k0, k1), k0);
+    // 三次放物線は円曲線にわずかに届かない
+    let end = diminish.k(tcl, tcl, k0, k1);
+    assert!(end <= k1 && end > k1 * 0.98);
+}
+
+/// 細かく分割した緩和曲線との、終点のずれ
+#[rstest]
+#[case(Diminish::Linear, 100.0, 100.0, 0.01)]
+#[case(Diminish::Sine, 100.0, 100.0, 0.01)]
+#[case(Diminish::Sine, 2000.0, 300.0, 0.005)]
+fn 許容誤差で分割する(
+    #[case] diminish: Diminish,
+    #[case] r: f64,
+    #[case] tcl: f64,
+    #[case] tolerance: f64,
+) {
+    let 分割 = |tolerance: f64| Param {
+        division: Division::Adaptive {
+            tolerance,
+            snap: false,
+        },
+        ..直線から円(diminish, r, tcl)
+    };
+    let spiral = plot(&分割(tolerance));
+    let exact = plot(&分割(1e-7));
+    let (p, q) = (spiral.last().unwrap().p1(), exact.last().unwrap().p1());
+    assert!(p.distance(q) < tolerance);
+    assert!(spiral.len() < tcl as usize);
+    assert_eq!(spiral.iter().map(|s| s.len().meter()).sum::<f64>(), tcl);
+}
+
+#[test]
+fn 距離程の区切りに揃える() {
+    let param = Param {
+        l0: 10.5.into(),
+        division: Division::Adaptive {
+            tolerance: 0.01,
+            snap: true,
+        },
+        ..直線から円(Diminish::Sine, 200.0, 100.0)
+    };
+    let intervals = intervals(&param);
+    let boundaries = intervals
+        .iter()
+        .take(intervals.len() - 1)
+        .map(|interval| 10.5 + interval.end().meter());
+    assert!(boundaries.clone().all(|l| l == l.round()));
+    assert!(boundaries.count() < 99);
+}
+
+#[test]
+fn 揃えても許容誤差の区間長を超えない() {
+    let (r, tcl, tolerance) = (400.0, 100.0, 1e-5);
+    let param = Param {
+        l0: 10.5.into(),
+        division: Division::Adaptive {
+            tolerance,
+            snap: true,
+        },
+        ..直線から円(Diminish::Linear, r, tcl)
+    };
+    // 直線逓減の曲率変化率は一定
+    let limit = (12.0 * tolerance * r).sqrt();
+    let intervals = intervals(&param);
+    assert!(intervals
+        .iter()
+        .all(|interval| interval.len().meter() <= limit + 1e-9));
+
+    // 1m単位の距離程は全て区間境界になる
+    let boundaries = intervals
+        .iter()
+        .map(|interval| 10.5 + interval.end().meter())
+        .collect::<Vec<_>>();
+    assert!((11..=110).all(|l| boundaries.contains(&(l as f64))));
+}
+
 impl PartialEq<&str> for Radius {
     fn eq(&self, other: &&str) -> bool {
         let fractional_digit = other
@@ -129,12 +271,6 @@ fn radius_eq_test() {
     assert_ne!(Radius(300.005), "300.00");
 }
 
-impl From<Degree> for Tangential {
-    fn from(degree: Degree) -> Self {
-        degree.0.to_radians().into()
-    }
-}
-
 impl PartialEq<(&str, &str)> for Point {
     fn eq(&self, rhs: &(&str, &str)) -> bool {
         [self.x(), self.y()]
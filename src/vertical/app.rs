@@ -0,0 +1,173 @@
+use anyhow::Result;
+
+use crate::{
+    agent::{
+        bve::{Format, MapFile, MapPath},
+        jww::{JwcTemp, Style},
+        sheet::Sheet,
+    },
+    transition::{app::m, curve::Tangential, unit::Vector},
+};
+
+use super::{
+    profile::Profile,
+    source::{Frame, Source},
+};
+
+/// 外部変形 "VERTICAL"
+#[derive(Debug)]
+pub struct Vertical {
+    /// 勾配の変化点の読み込み元
+    source: Result<Source>,
+
+    /// 縦断面図の座標の対応
+    frame: Result<Frame>,
+
+    /// 縦曲線半径 (m) と、他軌道の高さの出力間隔 (m)
+    design: Result<(f64, f64)>,
+
+    /// 線の属性
+    style: Result<Style>,
+
+    /// BVEマップの出力先と書式
+    map: Result<Option<(MapPath, Format)>>,
+}
+
+impl Vertical {
+    /// `source` の勾配の変化点に縦曲線を入れて、縦断面図 `frame` に描く。
+    ///
+    /// `map` があれば、その書式でBVEマップも出力する。
+    pub fn new(
+        source: Result<Source>,
+        frame: Result<Frame>,
+        design: Result<(f64, f64)>,
+        style: Result<Style>,
+        map: Result<Option<(MapPath, Format)>>,
+    ) -> Self {
+        Self {
+            source,
+            frame,
+            design,
+            style,
+            map,
+        }
+    }
+
+    /// エントリーポイント
+    ///
+    /// 座標ファイル `file` に出力する。
+    pub fn draw(&self, file: &str) -> Result<()> {
+        self.render(&mut JwcTemp::create(file)?)
+    }
+
+    /// 自軌道と他軌道の縦断線形を `sheet` に出力する。
+    pub fn render(&self, sheet: &mut impl Sheet) -> Result<()> {
+        match (
+            &self.source,
+            &self.frame,
+            &self.design,
+            &self.style,
+            &self.map,
+        ) {
+            (Ok(source), Ok(frame), Ok((radius, interval)), Ok(style), Ok(map)) => {
+                let profiles = source.changes(frame).and_then(|changes| {
+                    let own =
+                        Profile::new(&changes.own, *radius).map_err(|e| e.context("自軌道"))?;
+                    let others = changes
+                        .others
+                        .into_iter()
+                        .map(|(name, changes)| {
+                            let profile = Profile::new(&changes, *radius)
+                                .map_err(|e| e.context(format!("他軌道 {name}")))?;
+                            Ok((name, profile))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok((own, others))
+                });
+                let (own, others) = match profiles {
+                    Ok(profiles) => profiles,
+                    Err(e) => return sheet.error(&format!("{e:#}")),
+                };
+                let styles = match style.others(others.len()) {
+                    Ok(styles) => styles,
+                    Err(e) => return sheet.error(&e),
+                };
+
+                // BVEマップを出力する。
+                if let Some((path, format)) = map {
+                    let tracks = others
+                        .iter()
+                        .map(|(name, profile)| (name.as_str(), profile.relative(&own, *interval)))
+                        .collect::<Vec<_>>();
+                    let written = MapFile::create(path, format.clone()).and_then(|mut map_file| {
+                        map_file.write_vertical(&own.gradients(), &tracks)
+                    });
+                    if let Err(e) = written {
+                        return sheet.error(&e);
+                    }
+                }
+
+                // 自軌道に勾配と縦曲線の注記をつける。
+                sheet.style(style)?;
+                polyline(sheet, frame, &own)?;
+                for i in 0..own.sections() {
+                    let ((z0, h0), (z1, h1)) = own.tangent(i);
+                    let (p0, p1) = (frame.point(z0, h0), frame.point(z1, h1));
+                    let t = Tangential::from((p1.y() - p0.y()).atan2(p1.x() - p0.x()));
+                    let p = frame.point((z0 + z1) / 2.0, (h0 + h1) / 2.0);
+                    sheet.text(&p, &t, format!("{}‰", m(own.gradient(i))))?;
+                }
+                let curves = own.curves();
+                for curve in &curves {
+                    let p = frame.point(curve.z, curve.h);
+                    let s = format!("R={} VCL={}", m(curve.radius), m(curve.len));
+                    sheet.text(&p, &Tangential::from(0.0), s)?;
+                }
+
+                // 他軌道は1本ずつレイヤを変えて、始点に軌道名を書く。
+                for ((name, profile), style) in others.iter().zip(&styles) {
+                    sheet.style(style)?;
+                    polyline(sheet, frame, profile)?;
+                    let (z0, _) = profile.range();
+                    let p = frame.point(z0, profile.height(z0).unwrap_or_default());
+                    sheet.text(&p, &Tangential::from(0.0), name)?;
+                }
+
+                // 成功メッセージを出力する。
+                let (z0, z1) = own.range();
+                let mut message = format!(
+                    "縦断線形を{}mから{}mまで描画しました。縦曲線{}箇所",
+                    m(z0),
+                    m(z1),
+                    curves.len()
+                );
+                if !others.is_empty() {
+                    message += &format!("、他軌道{}本", others.len());
+                }
+                message += "。";
+                if let Some((path, _)) = map {
+                    message += &format!(" {} を作成しました", path.display());
+                }
+                sheet.notice(message)
+            }
+            (Err(e), _, _, _, _)
+            | (_, Err(e), _, _, _)
+            | (_, _, Err(e), _, _)
+            | (_, _, _, Err(e), _)
+            | (_, _, _, _, Err(e)) => sheet.error(e),
+        }
+    }
+}
+
+/// 縦断線形 `profile` を縦断面図 `frame` に折れ線で出力する。
+fn polyline(sheet: &mut impl Sheet, frame: &Frame, profile: &Profile) -> Result<()> {
+    let points = profile
+        .points()
+        .into_iter()
+        .map(|(z, h)| frame.point(z, h))
+        .collect::<Vec<_>>();
+    for pair in points.windows(2) {
+        sheet.straight(&pair[0], &pair[1])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,5 @@
+//! 縦断線形 (勾配と縦曲線) の設計と作図
+
+pub mod app;
+pub mod profile;
+pub mod source;
@@ -0,0 +1,322 @@
+//! 勾配と縦曲線の設計
+
+use anyhow::{ensure, Result};
+
+use crate::agent::bve::Gradient;
+
+/// 同じとみなす勾配の差 (‰)
+const SAME_GRADIENT: f64 = 1e-9;
+
+/// 勾配の変化点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Change {
+    /// 距離程 (m)
+    pub z: f64,
+
+    /// 高さ (m)
+    pub h: f64,
+
+    /// 縦曲線半径 (m)
+    ///
+    /// `None` なら既定の半径。0なら縦曲線を入れない。
+    pub radius: Option<f64>,
+}
+
+/// 縦曲線
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerticalCurve {
+    /// 勾配の変化点の距離程 (m)
+    pub z: f64,
+
+    /// 勾配の変化点の高さ (m)
+    pub h: f64,
+
+    /// 縦曲線半径 (m)
+    pub radius: f64,
+
+    /// 縦曲線長 (m)
+    pub len: f64,
+}
+
+/// 縦断線形
+///
+/// 勾配の変化点の間を直線でつなぎ、変化点に放物線の縦曲線を入れる。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// 勾配の変化点の距離程と高さ
+    points: Vec<(f64, f64)>,
+
+    /// 変化点ごとの縦曲線半径と縦曲線長の半分 (m)
+    ///
+    /// 両端の点と縦曲線のない変化点は長さ0
+    curves: Vec<(f64, f64)>,
+}
+
+impl Profile {
+    /// 変化点 `changes` に縦曲線を入れる。
+    ///
+    /// 変化点に半径の指定がなければ `radius` の縦曲線にする。
+    pub fn new(changes: &[Change], radius: f64) -> Result<Self> {
+        ensure!(changes.len() >= 2, "勾配の変化点を2つ以上指定してください");
+        for pair in changes.windows(2) {
+            ensure!(
+                pair[1].z > pair[0].z,
+                "距離程 {}m の変化点が前の変化点と同じか手前にあります",
+                pair[1].z
+            );
+        }
+        let points = changes.iter().map(|c| (c.z, c.h)).collect::<Vec<_>>();
+        let mut profile = Self {
+            points,
+            curves: vec![(0.0, 0.0); changes.len()],
+        };
+        for (i, change) in changes.iter().enumerate().skip(1) {
+            if i + 1 == changes.len() {
+                break;
+            }
+            let radius = change.radius.unwrap_or(radius);
+            ensure!(
+                radius >= 0.0,
+                "距離程 {}m の縦曲線半径に0以上の値を入力してください",
+                change.z
+            );
+            let change = (profile.gradient(i) - profile.gradient(i - 1)).abs() / 1000.0;
+            profile.curves[i] = (radius, radius * change / 2.0);
+        }
+        for (i, pair) in profile.points.windows(2).enumerate() {
+            let end = pair[0].0 + profile.curves[i].1;
+            let start = pair[1].0 - profile.curves[i + 1].1;
+            ensure!(
+                end <= start + 1e-9,
+                "距離程 {}m から {}m までに縦曲線が収まりません",
+                pair[0].0,
+                pair[1].0
+            );
+        }
+        Ok(profile)
+    }
+
+    /// 始点と終点の距離程
+    pub fn range(&self) -> (f64, f64) {
+        (self.points[0].0, self.points[self.points.len() - 1].0)
+    }
+
+    /// 変化点 `i` から次の変化点までの勾配 (‰)
+    ///
+    /// 上り勾配が正。
+    pub fn gradient(&self, i: usize) -> f64 {
+        let ((z0, h0), (z1, h1)) = (self.points[i], self.points[i + 1]);
+        (h1 - h0) / (z1 - z0) * 1000.0
+    }
+
+    /// 勾配の区間の数
+    pub fn sections(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    /// 勾配の区間 `i` の、縦曲線を除いた始点と終点の距離程と高さ
+    pub fn tangent(&self, i: usize) -> ((f64, f64), (f64, f64)) {
+        let a = self.points[i].0 + self.curves[i].1;
+        let b = self.points[i + 1].0 - self.curves[i + 1].1;
+        let h = |z: f64| self.points[i].1 + self.gradient(i) / 1000.0 * (z - self.points[i].0);
+        ((a, h(a)), (b, h(b)))
+    }
+
+    /// 縦曲線
+    pub fn curves(&self) -> Vec<VerticalCurve> {
+        self.points
+            .iter()
+            .zip(&self.curves)
+            .filter(|(_, (_, half))| *half > 0.0)
+            .map(|((z, h), (radius, half))| VerticalCurve {
+                z: *z,
+                h: *h,
+                radius: *radius,
+                len: 2.0 * half,
+            })
+            .collect()
+    }
+
+    /// 距離程 `z` の高さ (m)
+    ///
+    /// 始点と終点の外なら `None`
+    pub fn height(&self, z: f64) -> Option<f64> {
+        let (first, last) = self.range();
+        if z < first - 1e-9 || z > last + 1e-9 {
+            return None;
+        }
+        let i = self
+            .points
+            .partition_point(|(zi, _)| *zi <= z)
+            .clamp(1, self.sections())
+            - 1;
+        for j in [i, i + 1] {
+            let (zj, hj) = self.points[j];
+            let half = self.curves[j].1;
+            if half > 0.0 && (z - zj).abs() < half {
+                // 縦曲線の始点からの放物線
+                let (g0, g1) = (self.gradient(j - 1) / 1000.0, self.gradient(j) / 1000.0);
+                let x = z - (zj - half);
+                return Some(hj - g0 * half + g0 * x + (g1 - g0) / (4.0 * half) * x * x);
+            }
+        }
+        let (zi, hi) = self.points[i];
+        Some(hi + self.gradient(i) / 1000.0 * (z - zi))
+    }
+
+    /// 作図する点の距離程と高さ
+    ///
+    /// 縦曲線は1mごとの距離程で区切る。
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let mut zs = vec![];
+        for ((z, _), (_, half)) in self.points.iter().zip(&self.curves) {
+            match *half > 0.0 {
+                true => {
+                    let (a, b) = (z - half, z + half);
+                    zs.push(a);
+                    zs.extend((a.floor() as i64 + 1..=b.ceil() as i64 - 1).map(|z| z as f64));
+                    zs.push(b);
+                }
+                false => zs.push(*z),
+            }
+        }
+        zs.dedup_by(|z, prev| (*z - *prev).abs() < 1e-9);
+        zs.into_iter()
+            .filter_map(|z| Some((z, self.height(z)?)))
+            .collect()
+    }
+
+    /// BVEの勾配の構文
+    ///
+    /// 縦曲線は始点の `Gradient.BeginTransition` と終点の `Gradient.Begin` で表す。
+    pub fn gradients(&self) -> Vec<(f64, Gradient)> {
+        let begin = |g: f64| match g.abs() < SAME_GRADIENT {
+            true => Gradient::End,
+            false => Gradient::Begin(g),
+        };
+        let mut statements = vec![(self.points[0].0, begin(self.gradient(0)))];
+        for i in 1..self.sections() {
+            let g = self.gradient(i);
+            if (g - self.gradient(i - 1)).abs() < SAME_GRADIENT {
+                continue;
+            }
+            let (z, half) = (self.points[i].0, self.curves[i].1);
+            match half > 0.0 {
+                true => {
+                    statements.push((z - half, Gradient::BeginTransition));
+                    statements.push((z + half, begin(g)));
+                }
+                false => statements.push((z, begin(g))),
+            }
+        }
+        statements
+    }
+
+    /// 自軌道 `own` との高さの差 (m)
+    ///
+    /// 両方の縦断線形がある範囲で、`interval` ごとの距離程と変化点・縦曲線の端に点をとる。
+    pub fn relative(&self, own: &Profile, interval: f64) -> Vec<(f64, f64)> {
+        let ((a0, b0), (a1, b1)) = (self.range(), own.range());
+        let (a, b) = (a0.max(a1), b0.min(b1));
+        if a > b {
+            return vec![];
+        }
+        let ends = |profile: &Profile| {
+            profile
+                .points
+                .iter()
+                .zip(&profile.curves)
+                .flat_map(|((z, _), (_, half))| [z - half, *z, z + half])
+                .collect::<Vec<_>>()
+        };
+        let mut zs = ((a / interval).ceil() as i64..=(b / interval).floor() as i64)
+            .map(|n| n as f64 * interval)
+            .chain(ends(self))
+            .chain(ends(own))
+            .chain([a, b])
+            .filter(|z| (a..=b).contains(z))
+            .collect::<Vec<_>>();
+        zs.sort_by(f64::total_cmp);
+        zs.dedup_by(|z, prev| (*z - *prev).abs() < 1e-9);
+        zs.into_iter()
+            .filter_map(|z| Some((z, self.height(z)? - own.height(z)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn 変化点(points: &[(f64, f64)]) -> Vec<Change> {
+        points
+            .iter()
+            .map(|&(z, h)| Change { z, h, radius: None })
+            .collect()
+    }
+
+    #[test]
+    fn 縦曲線を入れる() {
+        // 10‰ の上りから 10‰ の下り
+        let changes = 変化点(&[(0.0, 0.0), (500.0, 5.0), (1000.0, 0.0)]);
+        let profile = Profile::new(&changes, 3000.0).unwrap();
+        let curves = profile.curves();
+        assert_eq!(curves.len(), 1);
+        assert!((curves[0].len - 60.0).abs() < 1e-9);
+
+        // 縦曲線の中央は変化点から R(Δi)²/8 下がる
+        let h = profile.height(500.0).unwrap();
+        assert!((h - (5.0 - 3000.0 * 0.02 * 0.02 / 8.0)).abs() < 1e-9, "{h}");
+        assert!((profile.height(470.0).unwrap() - 4.7).abs() < 1e-9);
+        assert!((profile.height(530.0).unwrap() - 4.7).abs() < 1e-9);
+        assert_eq!(profile.height(1000.1), None);
+
+        assert_eq!(
+            profile.gradients(),
+            [
+                (0.0, Gradient::Begin(10.0)),
+                (470.0, Gradient::BeginTransition),
+                (530.0, Gradient::Begin(-10.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn 半径0は縦曲線を入れない() {
+        let mut changes = 変化点(&[(0.0, 0.0), (100.0, 1.0), (200.0, 1.0)]);
+        changes[1].radius = Some(0.0);
+        let profile = Profile::new(&changes, 3000.0).unwrap();
+        assert!(profile.curves().is_empty());
+        assert_eq!(
+            profile.gradients(),
+            [(0.0, Gradient::Begin(10.0)), (100.0, Gradient::End)]
+        );
+        assert_eq!(profile.points(), [(0.0, 0.0), (100.0, 1.0), (200.0, 1.0)]);
+    }
+
+    #[rstest]
+    #[case(&[(0.0, 0.0)], "勾配の変化点を2つ以上指定してください")]
+    #[case(&[(0.0, 0.0), (0.0, 1.0)], "距離程 0m の変化点が前の変化点と同じか手前にあります")]
+    #[case(&[(0.0, 0.0), (20.0, 1.0), (40.0, 0.0)], "距離程 0m から 20m までに縦曲線が収まりません")]
+    fn 変化点のエラー(#[case] points: &[(f64, f64)], #[case] expected: &str) {
+        let e = Profile::new(&変化点(points), 3000.0).unwrap_err();
+        assert_eq!(e.to_string(), expected);
+    }
+
+    #[test]
+    fn 自軌道との高さの差() {
+        let own = Profile::new(&変化点(&[(0.0, 0.0), (100.0, 1.0)]), 3000.0).unwrap();
+        let other = Profile::new(&変化点(&[(50.0, 1.0), (120.0, 1.0)]), 3000.0).unwrap();
+        let relative = other.relative(&own, 25.0);
+        let expected = [(50.0, 0.5), (75.0, 0.25), (100.0, 0.0)];
+        assert_eq!(relative.len(), expected.len(), "{relative:?}");
+        for ((z, y), (ez, ey)) in relative.iter().zip(expected) {
+            assert!(
+                (z - ez).abs() < 1e-9 && (y - ey).abs() < 1e-9,
+                "{relative:?}"
+            );
+        }
+    }
+}
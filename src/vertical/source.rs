@@ -0,0 +1,315 @@
+//! 勾配の変化点の読み込み
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use encoding_rs::SHIFT_JIS;
+
+use crate::{
+    agent::jww::{Figure, Shape},
+    transition::{canvas::Point, unit::Vector},
+};
+
+use super::profile::Change;
+
+/// 同じ点とみなす距離 (図面上の長さ)
+const SAME_POINT: f64 = 1e-6;
+
+/// 縦断面図の座標と、距離程・高さの対応
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    /// 基準点の図面上の位置
+    pub origin: Point,
+
+    /// 基準点の距離程 (m)
+    pub z0: f64,
+
+    /// 基準点の高さ (m)
+    pub h0: f64,
+
+    /// 高さ1mあたりの図面上の長さ
+    pub scale: f64,
+}
+
+impl Frame {
+    /// 距離程 `z` と高さ `h` の図面上の位置
+    pub fn point(&self, z: f64, h: f64) -> Point {
+        self.origin + Point::from((z - self.z0, (h - self.h0) * self.scale))
+    }
+
+    /// 図面上の位置 `p` の距離程と高さ
+    pub fn distance_height(&self, p: Point) -> (f64, f64) {
+        (
+            self.z0 + p.x() - self.origin.x(),
+            self.h0 + (p.y() - self.origin.y()) / self.scale,
+        )
+    }
+}
+
+/// 自軌道と他軌道の勾配の変化点
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Changes {
+    /// 自軌道
+    pub own: Vec<Change>,
+
+    /// 他軌道の軌道名と変化点
+    pub others: Vec<(String, Vec<Change>)>,
+}
+
+/// 勾配の変化点の読み込み元
+#[derive(Debug)]
+pub enum Source {
+    /// 表から読んだ変化点
+    Table(Changes),
+
+    /// 縦断面図から選択した図形
+    Shapes(Vec<Shape>),
+}
+
+impl Source {
+    /// 表のファイル `path` を読む。
+    ///
+    /// UTF-8 で読めなければ Shift_JIS として読む。
+    pub fn open(path: &Path) -> Result<Self> {
+        let bytes =
+            fs::read(path).with_context(|| format!("{} を読み込めません", path.display()))?;
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => SHIFT_JIS.decode(e.as_bytes()).0.into_owned(),
+        };
+        let changes = Changes::parse(&text).map_err(|e| anyhow!("{} {e}", path.display()))?;
+        Ok(Self::Table(changes))
+    }
+
+    /// 縦断面図の座標の対応 `frame` で、変化点を読む。
+    pub fn changes(&self, frame: &Frame) -> Result<Changes> {
+        match self {
+            Source::Table(changes) => Ok(changes.clone()),
+            Source::Shapes(shapes) => Changes::read(shapes, frame),
+        }
+    }
+}
+
+impl Changes {
+    /// 表の文字列を読む。
+    ///
+    /// 1行に `軌道名,距離程,高さ[,縦曲線半径]` を書く。軌道名が空欄の行は自軌道。
+    /// 空行と `#` で始まる行は読まない。
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut changes = Self::default();
+        for (i, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+            let number = |s: &str| s.parse::<f64>().ok().filter(|f| f.is_finite());
+            let (name, change) = match fields[..] {
+                [name, z, h] | [name, z, h, ""] => (
+                    name,
+                    number(z)
+                        .zip(number(h))
+                        .map(|(z, h)| Change { z, h, radius: None }),
+                ),
+                [name, z, h, r] => (
+                    name,
+                    number(z)
+                        .zip(number(h))
+                        .zip(number(r))
+                        .map(|((z, h), r)| Change {
+                            z,
+                            h,
+                            radius: Some(r),
+                        }),
+                ),
+                _ => (Default::default(), None),
+            };
+            let change =
+                change.with_context(|| format!("表の{}行目を読み取れません: {line}", i + 1))?;
+            changes.push(name, change);
+        }
+        Ok(changes)
+    }
+
+    /// 縦断面図から選択した図形 `shapes` を読む。
+    ///
+    /// つながった直線を1本の勾配線とし、その頂点を変化点とする。
+    /// 文字は最も近い勾配線の軌道名とし、軌道名のない勾配線を自軌道とする。
+    /// 直線と文字のほかの図形は無視する。
+    pub fn read(shapes: &[Shape], frame: &Frame) -> Result<Self> {
+        // つながった直線を、選択した順にまとめる
+        let mut lines: Vec<(usize, Vec<(Point, Point)>)> = vec![];
+        for (n, shape) in shapes.iter().enumerate() {
+            let Figure::Straight(p0, p1) = shape.figure else {
+                continue;
+            };
+            let connected = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, line))| {
+                    line.iter()
+                        .any(|(a, b)| [a, b].iter().any(|q| same(q, &p0) || same(q, &p1)))
+                })
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            let mut line = (n, vec![(p0, p1)]);
+            for i in connected.into_iter().rev() {
+                let (first, segments) = lines.remove(i);
+                line.0 = line.0.min(first);
+                line.1.extend(segments);
+            }
+            lines.push(line);
+        }
+        lines.sort_by_key(|(first, _)| *first);
+        let lines = lines.into_iter().map(|(_, line)| line).collect::<Vec<_>>();
+
+        // 文字を最も近い勾配線の軌道名にする
+        let mut names: Vec<Vec<&str>> = vec![vec![]; lines.len()];
+        for shape in shapes {
+            let Figure::Text { p, ref s, .. } = shape.figure else {
+                continue;
+            };
+            let nearest = lines
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|segment| p.segment_distance(*segment))
+                        .fold(f64::INFINITY, f64::min)
+                })
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((i, _)) = nearest {
+                names[i].push(s.trim());
+            }
+        }
+
+        let mut changes = Self::default();
+        let mut own = 0;
+        for (line, names) in lines.iter().zip(&names) {
+            let name = match names[..] {
+                [] => {
+                    own += 1;
+                    ""
+                }
+                [name] => name,
+                _ => bail!("1本の勾配線に軌道名が複数あります: {}", names.join(", ")),
+            };
+            let mut points = line
+                .iter()
+                .flat_map(|(a, b)| [*a, *b])
+                .map(|p| frame.distance_height(p))
+                .collect::<Vec<_>>();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            points.dedup_by(|p, prev| {
+                (p.0 - prev.0).abs() < SAME_POINT && (p.1 - prev.1).abs() < SAME_POINT
+            });
+            for pair in points.windows(2) {
+                ensure!(
+                    pair[1].0 - pair[0].0 >= SAME_POINT,
+                    "距離程 {}m に鉛直な勾配線があります",
+                    pair[0].0
+                );
+            }
+            for (z, h) in points {
+                changes.push(name, Change { z, h, radius: None });
+            }
+        }
+        match own {
+            0 => bail!("自軌道の勾配線 (軌道名の文字を添えない線) を選択してください"),
+            1 => Ok(changes),
+            _ => bail!("軌道名の文字を添えない勾配線が複数あります"),
+        }
+    }
+
+    /// 軌道名 `name` の変化点を追加する。
+    fn push(&mut self, name: &str, change: Change) {
+        if name.is_empty() {
+            return self.own.push(change);
+        }
+        match self.others.iter_mut().find(|(other, _)| other == name) {
+            Some((_, changes)) => changes.push(change),
+            None => self.others.push((name.to_string(), vec![change])),
+        }
+    }
+}
+
+/// 同じ点なら `true`
+fn same(a: &Point, b: &Point) -> bool {
+    a.distance(*b) < SAME_POINT
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{agent::jww::Pen, transition::curve::Degree};
+
+    use super::*;
+
+    #[test]
+    fn 表を読む() {
+        let text = "# 軌道名,距離程,高さ,縦曲線半径\n,0,10\n,500,15,4000\n\n1,100,10.5\n,1000,12,\n1,200,11\n";
+        let changes = Changes::parse(text).unwrap();
+        let own = changes
+            .own
+            .iter()
+            .map(|c| (c.z, c.h, c.radius))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            own,
+            [
+                (0.0, 10.0, None),
+                (500.0, 15.0, Some(4000.0)),
+                (1000.0, 12.0, None)
+            ]
+        );
+        assert_eq!(changes.others.len(), 1);
+        assert_eq!(changes.others[0].0, "1");
+        assert_eq!(changes.others[0].1.len(), 2);
+
+        let e = Changes::parse(",0,10\n,a,1\n").unwrap_err();
+        assert_eq!(e.to_string(), "表の2行目を読み取れません: ,a,1");
+    }
+
+    #[test]
+    fn 縦断面図を読む() {
+        let frame = Frame {
+            origin: Point::from((100.0, 50.0)),
+            z0: 1000.0,
+            h0: 10.0,
+            scale: 10.0,
+        };
+        let shape = |figure| Shape {
+            line: 0,
+            pen: Pen::default(),
+            figure,
+        };
+        let line = |x0, y0, x1, y1| {
+            shape(Figure::Straight(
+                Point::from((x0, y0)),
+                Point::from((x1, y1)),
+            ))
+        };
+        let shapes = [
+            line(200.0, 60.0, 300.0, 60.0),
+            line(100.0, 50.0, 200.0, 60.0),
+            line(150.0, 80.0, 250.0, 80.0),
+            shape(Figure::Text {
+                p: Point::from((160.0, 81.0)),
+                dir: Degree(0.0),
+                s: "上り".to_string(),
+            }),
+        ];
+        let changes = Changes::read(&shapes, &frame).unwrap();
+        let own = changes.own.iter().map(|c| (c.z, c.h)).collect::<Vec<_>>();
+        assert_eq!(own, [(1000.0, 10.0), (1100.0, 11.0), (1200.0, 11.0)]);
+        assert_eq!(changes.others[0].0, "上り");
+        let other = changes.others[0]
+            .1
+            .iter()
+            .map(|c| (c.z, c.h))
+            .collect::<Vec<_>>();
+        assert_eq!(other, [(1050.0, 13.0), (1150.0, 13.0)]);
+
+        let e = Changes::read(&shapes[..3], &frame).unwrap_err();
+        assert_eq!(e.to_string(), "軌道名の文字を添えない勾配線が複数あります");
+    }
+}
@@ -0,0 +1,369 @@
+use std::fs::{self, File};
+use std::io::Write;
+
+use anyhow::Result;
+use encoding_rs::SHIFT_JIS;
+use rstest::rstest;
+use tempfile::TempDir;
+
+#[rstest]
+#[case(vec!["transition", "--diminish", "linear", "--r1", "-123.4", "--tcl", "1", "--format", "csv"],
+       "arc,0.00000000000001511214150147834,246.8,246.8,-90,-89.76784530181085\n")]
+#[case(vec!["transition", "--tcl", "2", "--x", "10", "--y", "5", "--format", "csv"],
+       "line,10,5,11,5\nline,11,5,12,5\n")]
+#[case(vec!["curve", "--r", "100", "--tcl0", "0", "--entry", "-200,0,-100,0", "--exit", "0,-100,0,-200"],
+       "ci -100 -100 100 0 90\r\n")]
+fn 図形を出力する(#[case] argv: Vec<&str>, #[case] expected: &str) -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let path = dir.path().join("out.txt");
+    let output = path.to_str().unwrap();
+    f5rail::command(argv.into_iter().chain(["--output", output]))?;
+
+    let bytes = fs::read(&path)?;
+    let (text, _, _) = SHIFT_JIS.decode(&bytes);
+    assert_eq!(text, expected);
+    dir.close()?;
+    Ok(())
+}
+
+#[rstest]
+#[case(vec!["transition"], "TCLを指定してください")]
+#[case(vec!["transition", "--tcl", "1", "--r1", "0"], "R1に0を指定できません")]
+#[case(vec!["curve", "--r", "100", "--tcl0", "0", "--entry", "0,0,1,0", "--exit", "0,1,1,1"],
+       "2本の接線が平行です")]
+#[case(vec!["transition", "--tcl", "1", "--format", "png"],
+       "--format に jww, csv, dxf, svg のいずれかを指定してください: png")]
+#[case(vec!["transition", "--tcl", "1", "--diagram", "--tick", "0"],
+       "目盛り間隔に0より大きい値を入力してください")]
+#[case(vec!["route"], "マップファイルを指定してください")]
+#[case(vec!["vertical"], "勾配の変化点の表を指定してください")]
+#[case(vec!["foo"], "不明なサブコマンド foo です")]
+fn エラーを返す(#[case] argv: Vec<&str>, #[case] expected: &str) {
+    let e = f5rail::command(argv).unwrap_err();
+    assert!(e.to_string().starts_with(expected), "{e}");
+}
+
+#[test]
+fn 他線座標をマップに出力する() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let own = dir.path().join("own.txt");
+    let other = dir.path().join("other.txt");
+    let map = dir.path().join("map.txt");
+    let sjis = |s: &str| SHIFT_JIS.encode(s).0.into_owned();
+    File::create(&own)?.write_all(&sjis(" 0 0 30 0\r\nhp1 0 0\r\n/始点距離程:100\r\n"))?;
+    File::create(&other)?.write_all(&sjis(" 0 3.8 30 3.8\r\n/トラック名:1\r\n"))?;
+
+    let path = |p: &std::path::Path| p.to_str().unwrap().to_string();
+    f5rail::command([
+        "track".to_string(),
+        "--own".to_string(),
+        path(&own),
+        "--other".to_string(),
+        path(&other),
+        "--interval=15".to_string(),
+        "--output".to_string(),
+        path(&map),
+    ])?;
+
+    let version = format!("// f5rail {} で作成", env!("CARGO_PKG_VERSION"));
+    let source = format!("// 作図元: {}", path(&own));
+    let expected = [
+        "BveTs Map 2.02:utf-8",
+        &version,
+        &source,
+        "",
+        "100;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "115;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "130;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+    ];
+    assert_eq!(fs::read_to_string(&map)?, expected.join("\r\n"));
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn 他線座標を既存のマップに差し込む() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let own = dir.path().join("own.txt");
+    let other = dir.path().join("other.txt");
+    let map = dir.path().join("map.txt");
+    let sjis = |s: &str| SHIFT_JIS.encode(s).0.into_owned();
+    File::create(&own)?.write_all(&sjis(" 0 0 30 0\r\nhp1 0 0\r\n/始点距離程:100\r\n"))?;
+    File::create(&other)?.write_all(&sjis(" 0 3.8 30 3.8\r\n/トラック名:1\r\n"))?;
+    let original = [
+        "BveTs Map 2.02:utf-8",
+        "100;",
+        "Curve.Begin(400); // 円曲線",
+        "Track['1'].X.Interpolate(-4, 0);",
+        "Track['2'].X.Interpolate(4, 0);",
+        "",
+        "130;",
+        "Curve.End();",
+        "",
+    ]
+    .join("\n");
+    fs::write(&map, &original)?;
+
+    let path = |p: &std::path::Path| p.to_str().unwrap().to_string();
+    f5rail::command([
+        "track".to_string(),
+        "--own".to_string(),
+        path(&own),
+        "--other".to_string(),
+        path(&other),
+        "--interval=15".to_string(),
+        "--output".to_string(),
+        path(&map),
+        "--merge".to_string(),
+    ])?;
+
+    let expected = [
+        "BveTs Map 2.02:utf-8",
+        "100;",
+        "Curve.Begin(400); // 円曲線",
+        "Track['2'].X.Interpolate(4, 0);",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "115;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "130;",
+        "Curve.End();",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+    ];
+    assert_eq!(fs::read_to_string(&map)?, expected.join("\n"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("map.txt.bak"))?,
+        original
+    );
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn dxfの他線座標をマップに出力する() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let path = dir.path().join("track.dxf");
+    let groups = [
+        ("0", "SECTION"),
+        ("2", "ENTITIES"),
+        ("0", "LWPOLYLINE"),
+        ("8", "own"),
+        ("90", "2"),
+        ("10", "0"),
+        ("20", "0"),
+        ("10", "30"),
+        ("20", "0"),
+        ("0", "LINE"),
+        ("8", "other"),
+        ("10", "0"),
+        ("20", "3.8"),
+        ("11", "30"),
+        ("21", "3.8"),
+        ("0", "ENDSEC"),
+        ("0", "EOF"),
+    ];
+    let dxf = groups.map(|(code, value)| format!("{code}\r\n{value}\r\n"));
+    fs::write(&path, dxf.concat())?;
+
+    let path = path.to_str().unwrap();
+    let map = dir.path().join("map.txt");
+    let preview = dir.path().join("preview.svg");
+    f5rail::command([
+        "track",
+        "--own",
+        path,
+        "--other",
+        path,
+        "--own-layer",
+        "own",
+        "--other-layer",
+        "other",
+        "--start",
+        "0,0",
+        "--z0",
+        "100",
+        "--name",
+        "1",
+        "--interval",
+        "15",
+        "--output",
+        map.to_str().unwrap(),
+        "--preview",
+        preview.to_str().unwrap(),
+    ])?;
+
+    let map = fs::read_to_string(&map)?;
+    assert!(map.starts_with("BveTs Map 2.02:utf-8\r\n// f5rail "));
+    assert!(map.contains("\r\n\r\n100;\r\nTrack['1'].X.Interpolate(-3.8, 0);"));
+    assert_eq!(map.matches("Interpolate").count(), 3);
+    let preview = fs::read_to_string(&preview)?;
+    assert!(preview.contains(">自軌道<"));
+    assert!(preview.contains(">他軌道 1<"));
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn 曲線をdxfに出力する() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let path = dir.path().join("curve.dxf");
+    f5rail::command([
+        "curve",
+        "--r",
+        "100",
+        "--tcl0",
+        "10",
+        "--entry",
+        "-200,0,-100,0",
+        "--exit",
+        "0,-100,0,-200",
+        "--format",
+        "dxf",
+        "--output",
+        path.to_str().unwrap(),
+    ])?;
+
+    let dxf = fs::read_to_string(&path)?;
+    let lines = dxf.lines().map(str::trim).collect::<Vec<_>>();
+    assert_eq!(lines[..4], ["0", "SECTION", "2", "HEADER"]);
+    assert_eq!(lines.iter().filter(|l| **l == "POLYLINE").count(), 1);
+    assert_eq!(lines[lines.len() - 4..], ["0", "ENDSEC", "0", "EOF"]);
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn 緩和曲線をsvgでプレビューする() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let path = dir.path().join("transition.svg");
+    f5rail::command([
+        "transition",
+        "--r1",
+        "400",
+        "--tcl",
+        "60",
+        "--l0",
+        "1000",
+        "--format",
+        "svg",
+        "--output",
+        path.to_str().unwrap(),
+    ])?;
+
+    let svg = fs::read_to_string(&path)?;
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    // 曲率図の縦軸は最小半径、横軸は始点距離程から
+    assert!(svg.contains(">+1/400<"));
+    assert!(svg.contains(">1000<") && svg.contains(">1060<"));
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn マップの自軌道を作図する() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let map = dir.path().join("map.txt");
+    let out = dir.path().join("route.csv");
+    fs::write(
+        &map,
+        "BveTs Map 2.02\r\n0;\r\nCurve.Begin(100);\r\n10;\r\nCurve.End();\r\n",
+    )?;
+    f5rail::command([
+        "route",
+        "--map",
+        map.to_str().unwrap(),
+        "--l1",
+        "20",
+        "--tick",
+        "10",
+        "--format",
+        "csv",
+        "--output",
+        out.to_str().unwrap(),
+    ])?;
+
+    let csv = fs::read_to_string(&out)?;
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("arc,"), "{csv}");
+    assert!(lines[1].starts_with("line,"), "{csv}");
+    let texts = lines
+        .iter()
+        .filter_map(|l| l.strip_prefix("text,"))
+        .map(|l| l.rsplit(',').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, ["\"0\"", "\"10\"", "\"20\""]);
+
+    let e = f5rail::command(["route", "--map", map.to_str().unwrap(), "--l0", "10"]).unwrap_err();
+    assert_eq!(e.to_string(), "始点距離程より先に構文がありません");
+    dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn 縦断線形を作図してマップに出力する() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let table = dir.path().join("profile.csv");
+    let map = dir.path().join("map.txt");
+    let out = dir.path().join("out.csv");
+    fs::write(&table, ",0,10\n,200,12\n,400,12\n1,0,10\n1,400,12\n")?;
+    f5rail::command([
+        "vertical",
+        "--profile",
+        table.to_str().unwrap(),
+        "--radius",
+        "2000",
+        "--interval",
+        "200",
+        "--bve",
+        map.to_str().unwrap(),
+        "--format",
+        "csv",
+        "--output",
+        out.to_str().unwrap(),
+    ])?;
+
+    let version = format!("// f5rail {} で作成", env!("CARGO_PKG_VERSION"));
+    let expected = [
+        "BveTs Map 2.02:utf-8",
+        &version,
+        "",
+        "0;",
+        "Gradient.Begin(10);",
+        "Track['1'].Y.Interpolate(0, 0);",
+        "",
+        "190;",
+        "Gradient.BeginTransition();",
+        "Track['1'].Y.Interpolate(-0.95, 0);",
+        "",
+        "200;",
+        "Track['1'].Y.Interpolate(-0.975, 0);",
+        "",
+        "210;",
+        "Gradient.End();",
+        "Track['1'].Y.Interpolate(-0.95, 0);",
+        "",
+        "400;",
+        "Track['1'].Y.Interpolate(0, 0);",
+        "",
+    ];
+    assert_eq!(fs::read_to_string(&map)?, expected.join("\r\n"));
+
+    let csv = fs::read_to_string(&out)?;
+    let texts = csv
+        .lines()
+        .filter_map(|l| l.strip_prefix("text,"))
+        .map(|l| l.rsplit(',').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, ["\"10‰\"", "\"0‰\"", "\"R=2000 VCL=20\"", "\"1\""]);
+    dir.close()?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::fs;
+
+use anyhow::Result;
+use encoding_rs::SHIFT_JIS;
+use tempfile::TempDir;
+
+#[test]
+fn 図面のフォルダのマップを作図する() -> Result<()> {
+    let project_dir = TempDir::new_in("tests")?;
+    fs::create_dir(project_dir.path().join("route"))?;
+    fs::write(
+        project_dir.path().join("route").join("map.txt"),
+        "BveTs Map 2.02\r\ninclude 'curve.txt';\r\n",
+    )?;
+    fs::write(
+        project_dir.path().join("route").join("curve.txt"),
+        "BveTs Map 2.02\r\n100;\r\nCurve.Begin(-400);\r\n150;\r\nCurve.End();\r\n",
+    )?;
+    let jwc_temp = project_dir.path().join("JWC_TEMP.TXT");
+    let project = project_dir.path().join("foo.jww");
+    fs::write(&jwc_temp, format!("file={}\r\n", project.display()))?;
+
+    f5rail::layout([
+        "/ROUTE:route/map.txt".to_string(),
+        "/L0:50".to_string(),
+        "/目盛り間隔:50".to_string(),
+        format!("/TEMP:{}", jwc_temp.display()),
+    ])?;
+
+    let bytes = fs::read(&jwc_temp)?;
+    let (text, _, _) = SHIFT_JIS.decode(&bytes);
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines.iter().filter(|l| l.starts_with("ci ")).count(),
+        1,
+        "{text}"
+    );
+    let labels = lines
+        .iter()
+        .filter(|l| l.starts_with("ch "))
+        .map(|l| l.rsplit(' ').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, ["50", "100", "150"], "{text}");
+    assert!(
+        text.contains("map.txt の自軌道を50mから150mまで描画しました。"),
+        "{text}"
+    );
+    project_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn 他軌道をレイヤを変えて作図する() -> Result<()> {
+    let project_dir = TempDir::new_in("tests")?;
+    let map = project_dir.path().join("map.txt");
+    fs::write(
+        &map,
+        "BveTs Map 2.02\r\n0;\r\nTrack['1'].X.Interpolate(-3.8, 0);\r\n20;\r\nTrack['1'].X.Interpolate(-3.8, 0);\r\n",
+    )?;
+    let jwc_temp = project_dir.path().join("JWC_TEMP.TXT");
+    fs::write(&jwc_temp, "")?;
+
+    f5rail::layout([
+        format!("/ROUTE:{}", map.display()),
+        "/LY:2".to_string(),
+        format!("/TEMP:{}", jwc_temp.display()),
+    ])?;
+
+    let bytes = fs::read(&jwc_temp)?;
+    let (text, _, _) = SHIFT_JIS.decode(&bytes);
+    let lines = text.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"ly2"), "{text}");
+    assert!(lines.contains(&"ly3"), "{text}");
+    let other = lines
+        .iter()
+        .skip_while(|l| **l != "ly3")
+        .filter(|l| matches!(l.split(' ').collect::<Vec<_>>()[..], [_, "3.8", _, "3.8"]))
+        .count();
+    assert_eq!(other, 20, "{text}");
+    assert!(
+        lines
+            .iter()
+            .any(|l| l.starts_with("ch ") && l.ends_with(" 1")),
+        "{text}"
+    );
+    assert!(
+        text.contains("の自軌道と他軌道1本を0mから20mまで描画しました。"),
+        "{text}"
+    );
+    project_dir.close()?;
+    Ok(())
+}
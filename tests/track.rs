@@ -1,11 +1,12 @@
 use std::{
     ffi::{OsStr, OsString},
-    fs::{File, OpenOptions},
+    fs::{self, File, OpenOptions},
     io::{self, Write},
     path::Path,
 };
 
 use anyhow::Result;
+use encoding_rs::SHIFT_JIS;
 use rstest::rstest;
 use tempfile::{NamedTempFile, TempDir, TempPath};
 
@@ -17,6 +18,10 @@ fn relative(#[case] map_name: &str) -> Result<()> {
     let jwc_temp = TestFile::create()?;
     let project_dir = TestDir::create()?;
     jwc_temp.write_path(&project_dir.path().join("foo.jww"))?;
+    jwc_temp_0.puts(" 0 0 30 0")?;
+    jwc_temp_0.puts("hp1 0 0")?;
+    jwc_temp_0.puts("/始点距離程:100 ")?;
+    jwc_temp_x.puts(" 0 3.8 30 3.8")?;
     jwc_temp_x.write_track_name("1")?;
 
     let args = vec![
@@ -25,10 +30,35 @@ fn relative(#[case] map_name: &str) -> Result<()> {
         Arg::new("/TEMP_X:").push(jwc_temp_x.path()),
         Arg::new("/TEMP:").push(jwc_temp.path()),
         Arg::new("/出力ファイル名:").push(map_name),
+        Arg::new("/出力間隔:10"),
     ];
     f5rail::layout(args)?;
 
-    assert!(project_dir.path().join(map_name).exists());
+    let map = fs::read_to_string(project_dir.path().join(map_name))?;
+    let version = format!("// f5rail {} で作成", env!("CARGO_PKG_VERSION"));
+    let source = format!(
+        "// 作図元: {}",
+        project_dir.path().join("foo.jww").display()
+    );
+    let expected = [
+        "BveTs Map 2.02:utf-8",
+        &version,
+        &source,
+        "",
+        "100;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "110;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "120;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+        "130;",
+        "Track['1'].X.Interpolate(-3.8, 0);",
+        "",
+    ];
+    assert_eq!(map, expected.join("\r\n"));
 
     jwc_temp_0.close()?;
     jwc_temp_x.close()?;
@@ -51,7 +81,13 @@ impl TestFile {
         writeln!(self.open()?, "file={}", path)
     }
     fn write_track_name(&self, s: &str) -> io::Result<()> {
-        writeln!(self.open()?, "/トラック名:{}", s)
+        self.puts(&format!("/トラック名:{} ", s))
+    }
+    fn puts(&self, s: &str) -> io::Result<()> {
+        let (sjis, _, _) = SHIFT_JIS.encode(s);
+        let mut file = self.open()?;
+        file.write_all(&sjis)?;
+        file.write_all(b"\r\n")
     }
     fn open(&self) -> io::Result<File> {
         OpenOptions::new().append(true).open(&self.0)
@@ -2,8 +2,9 @@ use std::io::Result as IoResult;
 use std::{
     ffi::{OsStr, OsString},
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Write},
     iter,
+    path::Path,
 };
 
 use anyhow::Result;
@@ -12,23 +13,163 @@ use encoding_rs::SHIFT_JIS;
 use encoding_rs_io::DecodeReaderBytesBuilder;
 
 use rstest::rstest;
-use tempfile::NamedTempFile;
+use tempfile::{NamedTempFile, TempDir};
 
 #[rstest]
 #[case(vec!["/TRANSITION:1"], vec!["heTCLを指定してください"])]
 #[case(vec!["/TRANSITION:1", "/R1:-123.4", "/TCL:1"],
        vec!["ci 0.00000000000001511214150147834 246.8 246.8 -90 -89.76784530181085",
             "h#サイン半波長逓減曲線を描画しました。"])]
+#[case(vec!["/TRANSITION:1", "/R1:-123.4", "/TCL:1", "/LY:a", "/LG:0", "/LC:2"],
+       vec!["lg0",
+            "lya",
+            "lc2",
+            "ci 0.00000000000001511214150147834 246.8 246.8 -90 -89.76784530181085",
+            "h#サイン半波長逓減曲線を描画しました。"])]
+#[case(vec!["/TRANSITION:2", "/TCL:2", "/注記:1"],
+       vec!["0 0 1 0",
+            "1 0 2 0",
+            "ch 0 0 1 0 BTC 0m R=∞",
+            "ch 1 0 1 0 直線逓減（クロソイド） TCL=2m",
+            "ch 2 0 1 0 ETC 2m R=∞",
+            "h#直線逓減（クロソイド）を描画しました。"])]
+#[case(vec!["/TRANSITION:2", "/CHAIN:0/1,-1000/0,-1000/1"],
+       vec!["0 0 1 0",
+            "ci 1.0000000000000613 1000 1000 -90 -89.94270422048692",
+            "h#直線逓減（クロソイド）で3区間を描画しました。1mで曲率が急変しています (R=∞→-1000)。"])]
+#[case(vec!["/TRANSITION:2", "/CHAIN:", "/TCL:1"],
+       vec!["0 0 1 0", "h#直線逓減（クロソイド）を描画しました。"])]
+#[case(vec!["/TRANSITION:2", "/R1:600", "/TCL:2", "/V:80", "/注記:1"],
+       vec!["ci -0.00000000000044087284769304714 -2400 2400 89.97612675853621 90",
+            "ci 0.6666666473765621 -800.0001388888868 800 89.90450703414486 89.97612675853621",
+            "ch 0 0 1 0 BTC 0m R=∞ C=0mm",
+            "ch 0.9999999710646775 -0.00020833333019254496 0.9999999131944457 -0.0004166666546103396 直線逓減（クロソイド） TCL=2m",
+            "ch 1.9999993634261104 -0.001249999740707608 0.9999986111114326 -0.0016666658950618357 ETC 2m R=600 C=90mm",
+            "h#直線逓減（クロソイド）を描画しました。"])]
+#[case(vec!["/TRANSITION:2", "/R1:400", "/TCL:2", "/V:120"],
+       vec!["heR=400m, V=120km/hではカント不足量が60mmを超えます"])]
+#[case(vec!["/TRANSITION:2", "/R1:6000", "/TCL:", "/V:80"],
+       vec!["ci -0.000000000017634913907721884 -96000 96000 89.99940316896341 90",
+            "ci 0.6666666666164738 -32000.00000347222 32000 89.99761267585363 89.99940316896341",
+            "ci 1.1999999997959914 -19200.000014583333 19200 89.99462852067064 89.99761267585362",
+            "ci 1.7142857133331237 -13714.285752976193 13714.285714285716 89.99045070341448 89.99462852067064",
+            "ci 2.22222221891453 -10666.666747685187 10666.666666666666 89.98507922408514 89.99045070341448",
+            "ci 2.7272727182575056 -8727.272874053033 8727.272727272728 89.9785140826826 89.98507922408514",
+            "ci 3.230769209952507 -7384.6156258012825 7384.615384615385 89.97075527920686 89.9785140826826",
+            "ci 3.7333332906941883 -6400.000369444441 6400 89.96180281365794 89.97075527920686",
+            "h#直線逓減（クロソイド）を描画しました。TCL=8m (カント逓減で決定)"])]
+#[case(vec!["/TRANSITION:2", "/R0:600", "/R1:600", "/V:80"],
+       vec!["heカントが変化しないため、緩和曲線長を決められません"])]
+#[case(vec!["/TRANSITION:1", "/TCL:1", "/注記:2"], vec!["he注記に0か1を入力してください"])]
+#[case(vec!["/TRANSITION:1", "/TCL:1", "/LC:10"], vec!["heLCに1～9の整数を入力してください"])]
+#[case(vec!["/TRANSITION:1", "/TCL:1", "/LY:G"], vec!["heLYに0～Fを入力してください"])]
 fn transition(#[case] strv: Vec<&str>, #[case] expected: Vec<&str>) -> Result<()> {
     let path = NamedTempFile::new_in("./tests")?.into_temp_path();
     f5rail::layout(Args::new(&path, &strv))?;
+    assert_lines(&path, expected)?;
+    path.close()?;
+    Ok(())
+}
+
+#[rstest]
+#[case(vec![" -10 0 0 0", "hp1 0.1 0.1"], vec!["0 0 1 0", "1 0 2 0"])]
+#[case(vec![" 0 0 -10 0", " -10 0 -10 5"], vec!["he接続する端点を指示してください"])]
+#[case(vec!["ci 0 -100 100 90 100", "hp1 1 1"],
+       vec!["ci -0.000000000000018369701987210297 -133.33333333333334 133.33333333333334 89.57028165365188 90",
+            "ci -1.9999812500529233 -399.9925000351561 399.99999999999994 89.42704220486918 89.57028165365188"])]
+fn attach(#[case] jwc_temp: Vec<&str>, #[case] expected: Vec<&str>) -> Result<()> {
+    let path = NamedTempFile::new_in("./tests")?.into_temp_path();
+    let mut file = File::create(&path)?;
+    for line in jwc_temp {
+        write!(file, "{}\r\n", line)?;
+    }
+    drop(file);
 
-    let file = File::open(&path)?;
+    f5rail::layout(Args::new(&path, &["/TRANSITION:2", "/TCL:2"]))?;
+    let mut expected = expected;
+    if !expected[0].starts_with("he") {
+        expected.push("h#直線逓減（クロソイド）を描画しました。");
+    }
+    assert_lines(&path, expected)?;
+    path.close()?;
+    Ok(())
+}
+
+#[rstest]
+#[case(vec![" -200 0 -100 0", " 0 -100 0 -200"], vec!["/CURVE:2", "/R:100", "/TCL0:0"],
+       vec!["ci -100 -100 100 0 90", "h#IA=90° TL=100m,100m CL=157.08m の曲線を描画しました。"])]
+#[case(vec![" -200 0 -100 0", " 0 -100 0 -200"], vec!["/CURVE:2", "/R:100", "/TCL0:1", "/注記:1"],
+       vec!["ci -100.50106468615972 -200 200 89.7135211024346 90",
+            "ci -100.00106676949046 -100.00124999739583 100 0.2864788975653928 89.7135211024346",
+            "ci -185.2481914098629 -100.42748917259672 185.24819024053045 0.0018166668654054704 0.2864788975653928",
+            "ci -2511.531713885012 -100.50124822138058 2511.531713885012 -0.000000000000020361451381185628 0.0018166668654039794",
+            "ch -100.50106468615968 0 1 0 TS 0m",
+            "ch -99.50106885282119 -0.0024999947916626297 0.9999875000260416 -0.004999979166692708 SC 1m R=100 f=0m",
+            "ch -0.0023167668862953406 -99.50125208072659 0.004999979166692219 -0.9999875000260416 CS 157.08m R=100 f=0m",
+            "ch 0 -100.50124822138147 0.0000000000000005053215498074303 1 ST 158.08m",
+            "h#IA=90° TL=100.501m,100.501m CL=158.08m の曲線を描画しました。"])]
+#[case(vec![" -200 0 -100 0"], vec!["/CURVE:2", "/R:100", "/TCL0:0"],
+       vec!["he接線を2本選択してください"])]
+fn curve(
+    #[case] jwc_temp: Vec<&str>,
+    #[case] strv: Vec<&str>,
+    #[case] expected: Vec<&str>,
+) -> Result<()> {
+    let path = NamedTempFile::new_in("./tests")?.into_temp_path();
+    let mut file = File::create(&path)?;
+    for line in jwc_temp {
+        write!(file, "{}\r\n", line)?;
+    }
+    drop(file);
+
+    f5rail::layout(Args::new(&path, &strv))?;
+    assert_lines(&path, expected)?;
+    path.close()?;
+    Ok(())
+}
+
+#[test]
+fn bve() -> Result<()> {
+    let dir = TempDir::new_in("./tests")?;
+    let path = NamedTempFile::new_in("./tests")?.into_temp_path();
+    let mut file = File::create(&path)?;
+    write!(file, "file={}\r\n", dir.path().join("foo.jww").display())?;
+    drop(file);
+
+    let strv = ["/TRANSITION:1", "/R1:400", "/TCL:2", "/L0:100", "/BVE:1"];
+    f5rail::layout(Args::new(&path, &strv))?;
+    let map = std::fs::read_to_string(dir.path().join("map.txt"))?;
+    let version = format!("// f5rail {} で作成", env!("CARGO_PKG_VERSION"));
+    let source = format!("// 作図元: {}", dir.path().join("foo.jww").display());
+    let expected = [
+        "BveTs Map 2.02:utf-8",
+        &version,
+        &source,
+        "",
+        "100;",
+        "Curve.BeginTransition();",
+        "",
+        "101;",
+        "Curve.Interpolate(800, 0);",
+        "",
+        "102;",
+        "Curve.Begin(400, 0);",
+        "",
+    ];
+    assert_eq!(map, expected.join("\r\n"));
+    path.close()?;
+    dir.close()?;
+    Ok(())
+}
+
+/// 座標ファイルが期待する行と一致することを確認する。
+fn assert_lines(path: &impl AsRef<Path>, expected: Vec<&str>) -> Result<()> {
+    let file = File::open(path)?;
     let reader = DecodeReaderBytesBuilder::new()
         .encoding(Some(SHIFT_JIS))
         .build(file);
     let reader = BufReader::new(reader);
-    let lines = reader.lines().into_iter().collect::<IoResult<Vec<_>>>()?;
+    let lines = reader.lines().collect::<IoResult<Vec<_>>>()?;
     let lines = lines
         .iter()
         .map(AsRef::as_ref)
@@ -42,8 +183,6 @@ fn transition(#[case] strv: Vec<&str>, #[case] expected: Vec<&str>) -> Result<()
         .zip(expects)
         .enumerate()
         .for_each(|(i, (line, expect))| assert_eq!(line, expect, "line {}", i + 1));
-
-    path.close()?;
     Ok(())
 }
 